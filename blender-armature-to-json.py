@@ -159,21 +159,19 @@ class ExportArmatureToJSON(bpy.types.Operator):
                     previous_trans_y = 0
                     previous_trans_z = 0
 
-                    previous_scale_x = None
-                    previous_scale_y = None
-                    previous_scale_z = None
+                    previous_scale_x = 1
+                    previous_scale_y = 1
+                    previous_scale_z = 1
 
                     for frame in sorted(frames):
                         transforms = frames[frame]
 
-                        mat_loc = mathutils.Matrix.Translation((0, 0, 0))
                         mat_rot = mathutils.Euler((0, 0, 0), 'XYZ').to_matrix()
-                        mat_scale = mathutils.Matrix.Scale(1.0, 4, (1.0, 1.0, 1.0))
 
                         poseBone = allPoseBones[boneName]
 
                         # Handles Blender baking optimization that deletes redundant keyframes when neighbors share the
-                        #  same value. We need to make sure to include the value in our transformation matrix
+                        #  same value. We need to make sure to include the value in our transform
 
                         t = transforms.get('location') if transforms.get('location') is not None else {}
                         trans = [0, 0, 0]
@@ -187,8 +185,6 @@ class ExportArmatureToJSON(bpy.types.Operator):
                         trans[2] = t.get(2) if t.get(2) is not None else previous_trans_z
                         previous_trans_z = trans[2]
 
-                        mat_loc = mathutils.Matrix.Translation(trans)
-
                         ### ---
 
                         if poseBone.rotation_mode == 'QUATERNION':
@@ -222,14 +218,21 @@ class ExportArmatureToJSON(bpy.types.Operator):
 
                             mat_rot = mathutils.Euler((euler[0], euler[1], euler[2]), poseBone.rotation_mode).to_matrix()
 
-                        # FIXME: Treat this like the above. Need to re-write and test all of this when we port
-                        #  to Rust.
-                        # if 'scale' in transforms:
-                        #     mat_scale = mathutils.Matrix.Scale(1.0, 4, transforms['scale'])
+                        s = transforms.get('scale') if transforms.get('scale') is not None else {}
+                        scale = [1, 1, 1]
+
+                        scale[0] = s.get(0) if s.get(0) is not None else previous_scale_x
+                        previous_scale_x = scale[0]
+
+                        scale[1] = s.get(1) if s.get(1) is not None else previous_scale_y
+                        previous_scale_y = scale[1]
 
-                        mat_rot = mat_rot.to_4x4()
+                        scale[2] = s.get(2) if s.get(2) is not None else previous_scale_z
+                        previous_scale_z = scale[2]
 
-                        local_space_transform_matrix = mat_loc @ mat_rot @ mat_scale
+                        # Exported as a translation, rotation and scale instead of a matrix so that
+                        # scaled bones keep their scale when interpolated.
+                        rotation = mat_rot.to_quaternion().normalized()
 
                         bone_idx = armatureJSON['joint_indices'][boneName]
                         if bone_idx not in armatureJSON['bone_space_actions'][actionInfo.name]['bone_keyframes']['keyframes']:
@@ -238,7 +241,11 @@ class ExportArmatureToJSON(bpy.types.Operator):
                         # bpy.context.scene.frame_set(frame)
                         armatureJSON['bone_space_actions'][actionInfo.name]['bone_keyframes']['keyframes'][bone_idx].append({
                             'frame': math.floor(frame),
-                            'bone': {'Matrix': matrixToArray(local_space_transform_matrix)}
+                            'bone': {'Trs': {
+                                'translation': list(trans),
+                                'rotation': [rotation.x, rotation.y, rotation.z, rotation.w],
+                                'scale': scale
+                            }}
                         })
 
 
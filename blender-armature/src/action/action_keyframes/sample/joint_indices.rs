@@ -6,7 +6,7 @@
 /// assuming that you've created a `lower_body` bone group in Blender.
 #[derive(Debug, Clone, Copy)]
 pub enum JointIndicesRef<'a> {
-    /// Encodes that all of an armature's joints you be used
+    /// Use all of the armature's joints, including joints that the action has no keyframes for
     All,
     /// Use some subset of the armature's joints.
     ///
@@ -1,12 +1,31 @@
 use crate::action::get_surrounding_keyframes;
-use crate::{interpolate_bone, Bone, BoneKeyframes, SampleDesc};
+use crate::{try_interpolate_bone, Bone, BoneKeyframes, InterpolationError, SampleDesc};
 
 impl BoneKeyframes {
     /// Sample the bone transforms
+    ///
+    /// # Panics
+    ///
+    /// Panics if the joint has no keyframes. See [`BoneKeyframes::try_sample`].
     pub fn sample(&self, joint_idx: u8, sample_desc: SampleDesc) -> Bone {
-        let keyframes = self.keyframes.get(&joint_idx).unwrap();
+        self.try_sample(joint_idx, sample_desc)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sample the bone transforms, returning an error if the joint has no keyframes.
+    pub fn try_sample(
+        &self,
+        joint_idx: u8,
+        sample_desc: SampleDesc,
+    ) -> Result<Bone, InterpolationError> {
+        let keyframes = match self.keyframes.get(&joint_idx) {
+            Some(keyframes) if !keyframes.is_empty() => keyframes,
+            _ => return Err(InterpolationError::MissingJoint { joint_idx }),
+        };
 
-        let (lowest_keyframe, highest_keyframe) = self.frame_range_inclusive().unwrap();
+        let (lowest_keyframe, highest_keyframe) = self
+            .frame_range_inclusive()
+            .ok_or(InterpolationError::EmptyFrameRange)?;
 
         let mut frames_elapsed = sample_desc.frame_offset.get();
 
@@ -15,7 +34,8 @@ impl BoneKeyframes {
         let action_duration = (highest_keyframe - lowest_keyframe) as f32;
 
         if frames_elapsed > action_duration {
-            if sample_desc.should_loop {
+            // An action with a single frame has nothing to loop over
+            if sample_desc.should_loop && action_duration > 0.0 {
                 frames_elapsed = frames_elapsed % action_duration;
             } else {
                 frames_elapsed = action_duration;
@@ -37,6 +57,6 @@ impl BoneKeyframes {
         let lower_bone = action_lower_keyframe.bone();
         let upper_bone = action_upper_keyframe.bone();
 
-        interpolate_bone(lower_bone, upper_bone, percent_elapsed_into_keyframe)
+        try_interpolate_bone(lower_bone, upper_bone, percent_elapsed_into_keyframe)
     }
 }
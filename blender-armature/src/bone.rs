@@ -1,15 +1,20 @@
-use nalgebra::{DualQuaternion, Matrix4};
+use crate::BlenderArmature;
+use nalgebra::{DualQuaternion, Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3};
 
-/// A bone in an armature. Can either be a dual quaternion or a matrix. When you export bones
-/// from Blender they come as matrices - BlenderArmature lets you convert them into dual
-/// quaternions which are usually more favorable for when implementing skeletal animation with
-/// rigid transformations.
+/// A bone in an armature. Can either be a dual quaternion, a matrix or a translation, rotation
+/// and scale. When you export bones from Blender their keyframes come as translations, rotations
+/// and scales and their inverse bind poses come as matrices - BlenderArmature lets you convert
+/// them into dual quaternions which are usually more favorable for when implementing skeletal
+/// animation with rigid transformations.
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Bone {
     /// A transform represented as a matrix
     Matrix(Matrix4<f32>),
     /// A rigid transform represented as a dual quaternion
     DualQuat(DualQuaternion<f32>),
+    /// A transform represented as a translation, rotation and scale. Unlike dual quaternions
+    /// these can be scaled, such as for squash and stretch.
+    Trs(Trs),
 }
 
 impl Bone {
@@ -17,6 +22,8 @@ impl Bone {
     ///
     /// You could typically use this when both bones are in world space.
     ///
+    /// The parent can be any kind of bone. The relative bone is the same kind of bone as this one.
+    ///
     /// ```
     /// # use blender_armature::Bone;
     /// use nalgebra::{Matrix4, Vector3, Point3};
@@ -41,12 +48,258 @@ impl Bone {
     /// assert_eq!(child.relative_to_parent(parent), expected);
     /// ```
     pub fn relative_to_parent(&self, parent_bone: Bone) -> Bone {
-        match (self, parent_bone) {
-            (Bone::Matrix(child), Bone::Matrix(parent)) => {
-                //
-                Bone::Matrix(parent.try_inverse().unwrap() * child)
+        let relative = parent_bone.to_matrix().try_inverse().unwrap() * self.to_matrix();
+
+        match self {
+            Bone::Matrix(_) => Bone::Matrix(relative),
+            Bone::Trs(_) => Bone::Trs(Trs::from_matrix(&relative)),
+            Bone::DualQuat(_) => BlenderArmature::matrix_to_dual_quat(&Bone::Matrix(relative)),
+        }
+    }
+}
+
+/// A transform broken up into a translation, a rotation and a scale.
+///
+/// Useful when you need to interpolate or store the pieces of a transform separately, such as
+/// when writing glTF animation channels.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Trs {
+    /// The translation
+    pub translation: Vector3<f32>,
+    /// The rotation
+    pub rotation: UnitQuaternion<f32>,
+    /// The scale along the x, y and z axis
+    pub scale: Vector3<f32>,
+}
+
+impl Trs {
+    /// Decompose a column major transformation matrix.
+    ///
+    /// Shear can't be represented by a `Trs` and is discarded.
+    ///
+    /// If the matrix mirrors (negative determinant) the mirroring is stored as a negative x scale.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Self {
+        let translation = Vector3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+
+        let linear = Matrix3::new(
+            matrix[(0, 0)],
+            matrix[(0, 1)],
+            matrix[(0, 2)],
+            matrix[(1, 0)],
+            matrix[(1, 1)],
+            matrix[(1, 2)],
+            matrix[(2, 0)],
+            matrix[(2, 1)],
+            matrix[(2, 2)],
+        );
+
+        let mut scale = Vector3::new(
+            linear.column(0).norm(),
+            linear.column(1).norm(),
+            linear.column(2).norm(),
+        );
+        if linear.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+
+        let mut rotation = linear;
+        for axis in 0..3 {
+            if scale[axis] != 0.0 {
+                let column = rotation.column(axis) / scale[axis];
+                rotation.set_column(axis, &column);
             }
-            _ => unimplemented!(),
         }
+        let rotation =
+            UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation));
+
+        Trs {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    /// Interpolate from this transform to the end transform using the given amount between
+    /// [0.0, 1.0] inclusive.
+    ///
+    /// The translation and scale are linearly interpolated and the rotation is spherically
+    /// interpolated along the shortest path.
+    pub fn interpolate(&self, end: &Trs, amount: f32) -> Trs {
+        // Quaternions q and -q are the same rotation, so we flip the end rotation when needed
+        // in order to rotate along the shortest path.
+        let end_rotation = if self.rotation.coords.dot(&end.rotation.coords) < 0.0 {
+            UnitQuaternion::new_unchecked(-end.rotation.into_inner())
+        } else {
+            end.rotation
+        };
+
+        // Slerp is undefined when the rotations are nearly identical, where a normalized lerp
+        // gives the same result.
+        let rotation = self
+            .rotation
+            .try_slerp(&end_rotation, amount, 1.0e-6)
+            .unwrap_or_else(|| self.rotation.nlerp(&end_rotation, amount));
+
+        Trs {
+            translation: self.translation.lerp(&end.translation, amount),
+            rotation,
+            scale: self.scale.lerp(&end.scale, amount),
+        }
+    }
+
+    /// Compose the translation, rotation and scale into a column major transformation matrix.
+    ///
+    /// The scale is applied first, then the rotation, then the translation.
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+impl Bone {
+    /// Split the bone's transform into a translation, rotation and scale.
+    ///
+    /// Matrix bones are expected to be column major, so you'll typically call
+    /// [`BlenderArmature::transpose_actions`] before decomposing bones that were exported from
+    /// Blender.
+    ///
+    /// Dual quaternions are rigid transforms, so their scale is always one.
+    pub fn to_trs(&self) -> Trs {
+        match self {
+            Bone::Matrix(matrix) => Trs::from_matrix(matrix),
+            Bone::Trs(trs) => *trs,
+            Bone::DualQuat(dual_quat) => {
+                let translation = (dual_quat.dual * dual_quat.real.conjugate()) * 2.0;
+
+                Trs {
+                    translation: translation.imag(),
+                    rotation: UnitQuaternion::from_quaternion(dual_quat.real),
+                    scale: Vector3::new(1.0, 1.0, 1.0),
+                }
+            }
+        }
+    }
+
+    /// This bone's transform as a column major matrix.
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        match BlenderArmature::dual_quat_to_matrix(self) {
+            Bone::Matrix(matrix) => matrix,
+            Bone::DualQuat(_) | Bone::Trs(_) => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Point3;
+
+    /// Verify that decomposing a matrix and composing it again gives us back the same matrix.
+    #[test]
+    fn trs_round_trip() {
+        let rotation = UnitQuaternion::from_euler_angles(0.3, -1.2, 2.0);
+        let matrix = Matrix4::new_translation(&Vector3::new(1., 2., 3.))
+            * rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&Vector3::new(2., 0.5, 3.));
+
+        let trs = Trs::from_matrix(&matrix);
+
+        assert!((trs.translation - Vector3::new(1., 2., 3.)).norm() < 1e-5);
+        assert!((trs.scale - Vector3::new(2., 0.5, 3.)).norm() < 1e-5);
+        assert!(trs.rotation.angle_to(&rotation) < 1e-4);
+        assert!((trs.to_matrix() - matrix).norm() < 1e-4);
+    }
+
+    /// Verify that we rotate along the shortest path even when the end rotation's quaternion is
+    /// negated, and that interpolating between identical rotations doesn't fail.
+    #[test]
+    fn trs_interpolate_shortest_path() {
+        let start = Trs {
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::new(1., 1., 1.),
+        };
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 0.5);
+        let end = Trs {
+            rotation: UnitQuaternion::new_unchecked(-rotation.into_inner()),
+            ..start
+        };
+
+        let halfway = start.interpolate(&end, 0.5);
+        assert!((halfway.rotation.angle() - 0.25).abs() < 1e-5);
+
+        assert_eq!(start.interpolate(&start, 0.3), start);
+    }
+
+    /// Verify that dual quaternion bones can be made relative to a parent, including a parent
+    /// of a different kind.
+    #[test]
+    fn dual_quat_relative_to_parent() {
+        let rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.5);
+        let parent =
+            Matrix4::new_translation(&Vector3::new(1., 2., 3.)) * rotation.to_homogeneous();
+        let local = Matrix4::new_translation(&Vector3::new(0., 4., 0.));
+
+        let child = BlenderArmature::matrix_to_dual_quat(&Bone::Matrix(parent * local));
+
+        for parent in [
+            Bone::Matrix(parent),
+            BlenderArmature::matrix_to_dual_quat(&Bone::Matrix(parent)),
+        ]
+        .iter()
+        {
+            let relative = child.relative_to_parent(*parent);
+
+            assert!(matches!(relative, Bone::DualQuat(_)));
+            assert!((relative.to_matrix() - local).abs().max() < 1e-5);
+        }
+    }
+
+    /// Verify the JSON format that the armature exporter writes translation rotation scale
+    /// bones in. The rotation is `[x, y, z, w]`.
+    #[test]
+    fn deserialize_trs_bone() {
+        let bone: Bone = serde_json::from_str(
+            r#"{"Trs": {
+                "translation": [1.0, 2.0, 3.0],
+                "rotation": [0.0, 0.0, 1.0, 0.0],
+                "scale": [2.0, 2.0, 0.5]
+            }}"#,
+        )
+        .unwrap();
+
+        let expected = Trs {
+            translation: Vector3::new(1., 2., 3.),
+            rotation: UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f32::consts::PI),
+            scale: Vector3::new(2., 2., 0.5),
+        };
+        let trs = bone.to_trs();
+        assert_eq!(trs.translation, expected.translation);
+        assert!(trs.rotation.angle_to(&expected.rotation) < 1e-4);
+        assert_eq!(trs.scale, expected.scale);
+
+        assert!((bone.to_matrix() - expected.to_matrix()).abs().max() < 1e-5);
+    }
+
+    /// Verify that we properly pull the translation and rotation out of a dual quaternion.
+    #[test]
+    fn dual_quat_to_trs() {
+        let rotation = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+        let translation = Vector3::new(4., 5., 6.);
+        let matrix = Matrix4::new_translation(&translation) * rotation.to_homogeneous();
+
+        let bone = BlenderArmature::matrix_to_dual_quat(&Bone::Matrix(matrix));
+        let trs = bone.to_trs();
+
+        assert!((trs.translation - translation).norm() < 1e-4);
+        assert!(trs.rotation.angle_to(&rotation) < 1e-4);
+        assert_eq!(trs.scale, Vector3::new(1., 1., 1.));
+
+        let point = Point3::new(1., 2., 3.);
+        assert!(
+            (bone.to_matrix().transform_point(&point) - matrix.transform_point(&point)).norm()
+                < 1e-4
+        );
     }
 }
@@ -7,9 +7,21 @@ use nalgebra::{DualQuaternion, Matrix4, Quaternion};
 impl BlenderArmature {
     /// Convert a matrix into a dual quaternion
     /// https://github.com/chinedufn/mat4-to-dual-quat/blob/master/src/mat4-to-dual-quat.js
+    ///
+    /// Dual quaternions are rigid transforms, so any scale is lost. Use [`Bone::Trs`] for bones
+    /// that are scaled.
     pub fn matrix_to_dual_quat(bone: &Bone) -> Bone {
         match bone {
             Bone::DualQuat(_dual_quat) => *bone,
+            Bone::Trs(trs) => {
+                let rotation_quat = trs.rotation.into_inner();
+                let trans_quat = Quaternion::from_imag(trs.translation) * rotation_quat * 0.5;
+
+                Bone::DualQuat(DualQuaternion::from_real_and_dual(
+                    rotation_quat,
+                    trans_quat,
+                ))
+            }
             Bone::Matrix(matrix) => {
                 let matrix = matrix.as_slice();
 
@@ -46,6 +58,7 @@ impl BlenderArmature {
     pub fn dual_quat_to_matrix(bone: &Bone) -> Bone {
         match bone {
             Bone::Matrix(matrix) => Bone::Matrix(matrix.clone()),
+            Bone::Trs(trs) => Bone::Matrix(trs.to_matrix()),
             Bone::DualQuat(dual_quat) => {
                 let mut matrix: [f32; 16] = [0.0; 16];
 
@@ -1,4 +1,5 @@
-use crate::{BlenderArmature, Bone};
+use crate::{BlenderArmature, Bone, Trs};
+use nalgebra::{Matrix3, UnitQuaternion, Vector3, Vector4};
 
 /// A coordinate system is used to make sense of coordinates.
 ///
@@ -15,6 +16,63 @@ impl CoordinateSystem {
     pub fn new(up: Axis, hand: Hand) -> Self {
         CoordinateSystem { up, hand }
     }
+
+    /// The axis that points up
+    pub fn up(&self) -> Axis {
+        self.up
+    }
+
+    /// Whether the coordinate system is right or left handed
+    pub fn hand(&self) -> Hand {
+        self.hand
+    }
+
+    /// The matrix that converts positions and directions from this coordinate system into
+    /// another one.
+    ///
+    /// Every coordinate system has a right, up and back (towards the viewer) direction:
+    ///
+    /// | Up | Right | Back (right handed) | Back (left handed) |
+    /// | -- | ----- | ------------------- | ------------------ |
+    /// | X  | +Y    | -Z                  | +Z                 |
+    /// | Y  | +X    | +Z                  | -Z                 |
+    /// | Z  | +X    | -Y                  | +Y                 |
+    ///
+    /// So converting from Blender's Z up right handed system to Y up right handed maps
+    /// (x, y, z) to (x, z, -y).
+    ///
+    /// The matrix is a rotation, or a reflection when the handedness changes.
+    pub fn conversion_matrix(&self, to: CoordinateSystem) -> Matrix3<f32> {
+        to.basis() * self.basis().transpose()
+    }
+
+    /// Whether converting to another coordinate system mirrors the data, which reverses the
+    /// winding order of triangles.
+    pub fn flips_handedness(&self, to: CoordinateSystem) -> bool {
+        self.hand != to.hand
+    }
+
+    /// The right, up and back directions of the coordinate system as the columns of a matrix.
+    fn basis(&self) -> Matrix3<f32> {
+        let axis = |axis: Axis| match axis {
+            Axis::X => Vector3::x(),
+            Axis::Y => Vector3::y(),
+            Axis::Z => Vector3::z(),
+        };
+
+        let up = axis(self.up);
+        let right = match self.up {
+            Axis::X => Vector3::y(),
+            Axis::Y | Axis::Z => Vector3::x(),
+        };
+
+        let back = match self.hand {
+            Hand::Right => right.cross(&up),
+            Hand::Left => -right.cross(&up),
+        };
+
+        Matrix3::from_columns(&[right, up, back])
+    }
 }
 
 #[allow(missing_docs)]
@@ -52,54 +110,81 @@ impl BlenderArmature {
     /// For example, if the armature was previously Z up and we're switching to Y up
     ///  - the new +Y axis would be the old +Z axis
     ///  - the new +Z axis would be the old -Y axis
+    ///
+    /// See [`CoordinateSystem.conversion_matrix`] for how every other pair of coordinate systems
+    /// is converted. The inverse bind poses and every action's keyframes are converted, whether
+    /// they are matrices or dual quaternions.
+    ///
+    /// Converting to another coordinate system and back again gives you the bones that you
+    /// started with (give or take floating point error).
+    ///
+    /// [`CoordinateSystem.conversion_matrix`]: struct.CoordinateSystem.html#method.conversion_matrix
     pub fn change_coordinate_system(&mut self, system: CoordinateSystem) {
         if self.coordinate_system == system {
             return;
         }
 
-        match (
-            (self.coordinate_system.hand, self.coordinate_system.up),
-            (system.hand, system.up),
-        ) {
-            ((Hand::Right, Axis::Z), (Hand::Right, Axis::Y)) => {
-                for bone in self.inverse_bind_poses.iter_mut() {
-                    *bone = dual_quat_z_up_right_to_y_up_right(*bone);
-                }
+        let conversion = self.coordinate_system.conversion_matrix(system);
+
+        for bone in self.inverse_bind_poses.iter_mut() {
+            *bone = convert_bone(*bone, &conversion);
+        }
 
-                for (_action_name, action) in self.bone_space_actions.iter_mut() {
-                    for (bone_idx, keyframes) in action.keyframes_mut() {
-                        for bone_keyframe in keyframes.iter_mut() {
-                            let bone = bone_keyframe.bone();
-                            bone_keyframe.set_bone(dual_quat_z_up_right_to_y_up_right(bone));
-                        }
-                    }
+        for (_action_name, action) in self.bone_space_actions.iter_mut() {
+            for keyframes in action.keyframes_mut().values_mut() {
+                for bone_keyframe in keyframes.iter_mut() {
+                    let bone = bone_keyframe.bone();
+                    bone_keyframe.set_bone(convert_bone(bone, &conversion));
                 }
             }
-            _ => unimplemented!(),
         }
 
         self.coordinate_system = system;
     }
 }
 
-fn dual_quat_z_up_right_to_y_up_right(bone: Bone) -> Bone {
+/// Express a bone's transform in another coordinate system.
+///
+/// Matrices are conjugated by the conversion, which works whether they are row or column major
+/// since the conversion is orthogonal.
+///
+/// A dual quaternion's rotation axis is a pseudovector, so when the conversion mirrors the axes
+/// (changes handedness) the axis gets flipped back, and the scalar part of the dual flips along
+/// with it.
+///
+/// A translation, rotation and scale's rotation axis is flipped back the same way.
+fn convert_bone(bone: Bone, conversion: &Matrix3<f32>) -> Bone {
     match bone {
-        Bone::Matrix(_) => unimplemented!(),
+        Bone::Matrix(matrix) => {
+            let conversion = conversion.to_homogeneous();
+            Bone::Matrix(conversion * matrix * conversion.transpose())
+        }
         Bone::DualQuat(mut dq) => {
-            let rot_y = dq.real.j;
-            let rot_z = dq.real.k;
-
-            dq.real.j = rot_z;
-            dq.real.k = -rot_y;
+            let determinant = conversion.determinant();
 
-            let trans_y = dq.dual.j;
-            let trans_z = dq.dual.k;
+            let real = conversion * dq.real.imag() * determinant;
+            dq.real.coords = Vector4::new(real.x, real.y, real.z, dq.real.w);
 
-            dq.dual.j = trans_z;
-            dq.dual.k = -trans_y;
+            let dual = conversion * dq.dual.imag();
+            dq.dual.coords = Vector4::new(dual.x, dual.y, dual.z, dq.dual.w * determinant);
 
             Bone::DualQuat(dq)
         }
+        Bone::Trs(trs) => {
+            let determinant = conversion.determinant();
+
+            let mut rotation = trs.rotation.into_inner();
+            let axis = conversion * rotation.imag() * determinant;
+            rotation.coords = Vector4::new(axis.x, axis.y, axis.z, rotation.w);
+
+            // The conversion only swaps and negates axes, so the scale along each axis moves to
+            // its new axis.
+            Bone::Trs(Trs {
+                translation: conversion * trs.translation,
+                rotation: UnitQuaternion::new_unchecked(rotation),
+                scale: conversion.abs() * trs.scale,
+            })
+        }
     }
 }
 
@@ -108,8 +193,8 @@ mod tests {
     use super::*;
     use crate::interpolate::tests::dq_to_bone;
     use crate::test_util::{action_name, action_with_keyframes, BONE_IDX};
-    use crate::{Action, BlenderArmature, BoneKeyframe, Keyframe};
-    use std::collections::HashMap;
+    use crate::{BlenderArmature, BoneKeyframe};
+    use nalgebra::{Matrix4, UnitQuaternion};
 
     /// Convert from the default Z-up right handed coordinate system to a Y-up right handed
     /// coordinate system.
@@ -120,7 +205,7 @@ mod tests {
         let expected_bone = dq_to_bone([0., 1., 3., -2., 4., 5., 7., -6.]);
 
         let bone = dq_to_bone([0., 1., 2., 3., 4., 5., 6., 7.]);
-        arm.inverse_bind_poses = vec![bone.clone()];
+        arm.inverse_bind_poses = vec![bone];
 
         let keyframes = vec![BoneKeyframe::new(0, bone)];
 
@@ -135,6 +220,38 @@ mod tests {
         );
     }
 
+    /// Verify that converting from Blender's coordinate system to Y up right handed matches the
+    /// swap that we've always done, and that every conversion can be undone.
+    #[test]
+    fn conversion_matrices() {
+        let z_up_right = CoordinateSystem::default();
+        let y_up_right = CoordinateSystem::new(Axis::Y, Hand::Right);
+
+        let converted = z_up_right.conversion_matrix(y_up_right) * Vector3::new(1., 2., 3.);
+        assert_eq!(converted, Vector3::new(1., 3., -2.));
+
+        for from in all_coordinate_systems() {
+            for to in all_coordinate_systems() {
+                let round_trip = to.conversion_matrix(from) * from.conversion_matrix(to);
+                assert_eq!(round_trip, Matrix3::identity());
+
+                let determinant = from.conversion_matrix(to).determinant();
+                let expected = if from.flips_handedness(to) { -1. } else { 1. };
+                assert_eq!(determinant, expected);
+            }
+        }
+    }
+
+    fn all_coordinate_systems() -> Vec<CoordinateSystem> {
+        let mut systems = vec![];
+        for up in [Axis::X, Axis::Y, Axis::Z].iter() {
+            for hand in [Hand::Right, Hand::Left].iter() {
+                systems.push(CoordinateSystem::new(*up, *hand));
+            }
+        }
+        systems
+    }
+
     /// If the armature is already using the coordinate system that we want to change to
     /// then nothing should change
     #[test]
@@ -159,4 +276,119 @@ mod tests {
             &expected_bone
         );
     }
+
+    /// Verify that converting to every coordinate system and back again gives us the bones
+    /// that we started with, for both dual quaternion and matrix bones.
+    #[test]
+    fn round_trip_every_coordinate_system() {
+        for from in all_coordinate_systems() {
+            for to in all_coordinate_systems() {
+                let original = armature_with_bones(from);
+
+                let mut arm = original.clone();
+                arm.change_coordinate_system(to);
+                assert_eq!(arm.coordinate_system, to);
+
+                arm.change_coordinate_system(from);
+
+                for (converted, original) in arm
+                    .inverse_bind_poses
+                    .iter()
+                    .zip(original.inverse_bind_poses.iter())
+                {
+                    assert_bones_approx_eq(converted, original);
+                }
+
+                let keyframes = &arm.bone_space_actions[&action_name()].bone_keyframes()[&BONE_IDX];
+                let original_keyframes =
+                    &original.bone_space_actions[&action_name()].bone_keyframes()[&BONE_IDX];
+                for (converted, original) in keyframes.iter().zip(original_keyframes.iter()) {
+                    assert_bones_approx_eq(&converted.bone(), &original.bone());
+                }
+            }
+        }
+    }
+
+    /// Verify that dual quaternion, translation rotation scale and matrix bones with the same
+    /// transform still have the same transform after being converted to any coordinate system.
+    #[test]
+    fn bone_types_agree() {
+        for to in all_coordinate_systems() {
+            let mut arm = armature_with_bones(CoordinateSystem::default());
+            arm.change_coordinate_system(to);
+
+            for (bone, matrix) in [(0, 1), (2, 3)].iter() {
+                let bone = arm.inverse_bind_poses[*bone].to_matrix();
+                let matrix = arm.inverse_bind_poses[*matrix].to_matrix();
+
+                assert!(
+                    (bone - matrix).abs().max() < 1e-5,
+                    "Converting to {:?}\n{} != {}",
+                    to,
+                    bone,
+                    matrix
+                );
+            }
+        }
+    }
+
+    /// Verify that converting to a left handed coordinate system mirrors translations.
+    #[test]
+    fn convert_matrix_z_up_right_to_y_up_left() {
+        let translation = Matrix4::new_translation(&Vector3::new(1., 2., 3.));
+        let mut arm = BlenderArmature {
+            inverse_bind_poses: vec![Bone::Matrix(translation)],
+            ..BlenderArmature::default()
+        };
+
+        arm.change_coordinate_system(CoordinateSystem::new(Axis::Y, Hand::Left));
+
+        let expected = Bone::Matrix(Matrix4::new_translation(&Vector3::new(1., 3., 2.)));
+        assert_eq!(arm.inverse_bind_poses[0], expected);
+    }
+
+    /// An armature with a dual quaternion bone and a matrix bone that have the same transform,
+    /// followed by a translation rotation scale bone and a matrix bone that have the same scaled
+    /// transform.
+    fn armature_with_bones(coordinate_system: CoordinateSystem) -> BlenderArmature {
+        let rotation = UnitQuaternion::from_euler_angles(0.3, -0.7, 1.1);
+        let matrix =
+            Matrix4::new_translation(&Vector3::new(1., -2., 3.)) * rotation.to_homogeneous();
+        let scaled = matrix * Matrix4::new_nonuniform_scaling(&Vector3::new(2., 1., 0.5));
+
+        let matrix = Bone::Matrix(matrix);
+        let dual_quat = BlenderArmature::matrix_to_dual_quat(&matrix);
+        let scaled = Bone::Matrix(scaled);
+        let trs = Bone::Trs(scaled.to_trs());
+
+        BlenderArmature {
+            inverse_bind_poses: vec![dual_quat, matrix, trs, scaled],
+            bone_space_actions: action_with_keyframes(vec![
+                BoneKeyframe::new(0, dual_quat),
+                BoneKeyframe::new(1, matrix),
+                BoneKeyframe::new(2, trs),
+                BoneKeyframe::new(3, scaled),
+            ]),
+            coordinate_system,
+            ..BlenderArmature::default()
+        }
+    }
+
+    fn assert_bones_approx_eq(left: &Bone, right: &Bone) {
+        let close = match (left, right) {
+            (Bone::Matrix(left), Bone::Matrix(right)) => (left - right).abs().max() < 1e-5,
+            (Bone::DualQuat(left), Bone::DualQuat(right)) => {
+                (left.real.coords - right.real.coords).abs().max() < 1e-5
+                    && (left.dual.coords - right.dual.coords).abs().max() < 1e-5
+            }
+            (Bone::Trs(left), Bone::Trs(right)) => {
+                (left.translation - right.translation).abs().max() < 1e-5
+                    && left.rotation.angle_to(&right.rotation) < 1e-4
+                    && (left.scale - right.scale).abs().max() < 1e-5
+            }
+            _ => false,
+        };
+
+        assert!(close, "{:?} != {:?}", left, right);
+    }
 }
@@ -0,0 +1,239 @@
+//! Turning the bone local poses that you get from sampling an action into model space transforms,
+//! skinning transforms and attachment sockets.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use nalgebra::{DualQuaternion, Matrix4};
+
+use crate::{BlenderArmature, Bone, Trs};
+
+impl BlenderArmature {
+    /// Compute the model space transform of every joint from bone local poses, such as the bones
+    /// returned by [`BlenderArmature::interpolate_bones`].
+    ///
+    /// Like keyframes, the local poses are relative to each joint's rest pose. Parents are visited
+    /// before their children, so a joint's model space transform is its parent's model space
+    /// transform times its rest pose relative to its parent times its local pose. Joints that are
+    /// missing from `local_bones` are posed at rest, so there is an entry for every joint in the
+    /// armature.
+    ///
+    /// Matrix bones need to be column major, so you'll typically call
+    /// [`BlenderArmature::transpose_actions`] before sampling them.
+    ///
+    /// For world space transforms multiply these by the world transform of your model.
+    pub fn model_space_matrices(
+        &self,
+        local_bones: &BTreeMap<u8, Bone>,
+    ) -> BTreeMap<u8, Matrix4<f32>> {
+        let mut joints: BTreeSet<u8> = local_bones
+            .keys()
+            .chain(self.joint_indices.values())
+            .copied()
+            .collect();
+        for joint_idx in joints.clone() {
+            joints.extend(self.ancestors(joint_idx));
+        }
+
+        let mut joints: Vec<u8> = joints.into_iter().collect();
+        joints.sort_by_key(|joint_idx| self.ancestors(*joint_idx).len());
+
+        let mut model_space = BTreeMap::new();
+
+        for joint_idx in joints {
+            let local = self.local_matrix(joint_idx, local_bones);
+
+            let parent = self
+                .bone_child_to_parent
+                .get(&joint_idx)
+                .and_then(|parent| model_space.get(parent));
+
+            let matrix = match parent {
+                Some(parent) => parent * local,
+                None => local,
+            };
+
+            model_space.insert(joint_idx, matrix);
+        }
+
+        model_space
+    }
+
+    /// The transforms that skin a mesh's vertices, which are each joint's model space transform
+    /// times its inverse bind pose.
+    ///
+    /// These are what you'd typically upload to your vertex shader.
+    pub fn skinning_matrices(
+        &self,
+        model_space: &BTreeMap<u8, Matrix4<f32>>,
+    ) -> BTreeMap<u8, Matrix4<f32>> {
+        model_space
+            .iter()
+            .map(|(joint_idx, model)| {
+                let inverse_bind_pose = self
+                    .inverse_bind_poses
+                    .get(*joint_idx as usize)
+                    .map(|bone| bone.to_matrix())
+                    .unwrap_or_else(Matrix4::identity);
+
+                (*joint_idx, model * inverse_bind_pose)
+            })
+            .collect()
+    }
+
+    /// The skinning transforms as dual quaternions, for dual quaternion linear blending in your
+    /// vertex shader.
+    ///
+    /// Dual quaternions are rigid transforms, so any scale is lost.
+    pub fn skinning_dual_quats(
+        &self,
+        model_space: &BTreeMap<u8, Matrix4<f32>>,
+    ) -> BTreeMap<u8, DualQuaternion<f32>> {
+        self.skinning_matrices(model_space)
+            .into_iter()
+            .map(|(joint_idx, skinning)| {
+                let bone = Bone::Trs(Trs::from_matrix(&skinning));
+
+                match BlenderArmature::matrix_to_dual_quat(&bone) {
+                    Bone::DualQuat(dual_quat) => (joint_idx, dual_quat),
+                    Bone::Matrix(_) | Bone::Trs(_) => unreachable!(),
+                }
+            })
+            .collect()
+    }
+
+    /// The model space transform of a single bone, such as a hand bone that you want to attach a
+    /// baseball to.
+    ///
+    /// Only the bone and its ancestors are visited, so this is cheaper than
+    /// [`BlenderArmature::model_space_matrices`] when you only need a few sockets.
+    ///
+    /// Returns `None` if the armature does not have a bone with this name.
+    pub fn socket_matrix(
+        &self,
+        bone_name: &str,
+        local_bones: &BTreeMap<u8, Bone>,
+    ) -> Option<Matrix4<f32>> {
+        let joint_idx = *self.joint_indices.get(bone_name)?;
+
+        let mut matrix = self.local_matrix(joint_idx, local_bones);
+        for ancestor in self.ancestors(joint_idx) {
+            matrix = self.local_matrix(ancestor, local_bones) * matrix;
+        }
+
+        Some(matrix)
+    }
+
+    /// The joint's pose relative to its parent, which is its local pose applied on top of its
+    /// rest pose.
+    fn local_matrix(&self, joint_idx: u8, local_bones: &BTreeMap<u8, Bone>) -> Matrix4<f32> {
+        let rest = self.rest_local_matrix(joint_idx);
+
+        match local_bones.get(&joint_idx) {
+            Some(bone) => rest * bone.to_matrix(),
+            None => rest,
+        }
+    }
+
+    /// The joint's parent, grandparent and so on, ending at the root.
+    fn ancestors(&self, joint_idx: u8) -> Vec<u8> {
+        let mut ancestors = vec![];
+
+        let mut joint = joint_idx;
+        while let Some(parent) = self.bone_child_to_parent.get(&joint) {
+            // Guard against a malformed hierarchy that loops back on itself
+            if *parent == joint_idx || ancestors.contains(parent) {
+                break;
+            }
+
+            ancestors.push(*parent);
+            joint = *parent;
+        }
+
+        ancestors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::{Point3, UnitQuaternion, Vector3};
+    use std::f32::consts::FRAC_PI_2;
+
+    /// Verify that children are posed relative to their parent, even when the parent has a larger
+    /// joint index than the child, that local poses are applied on top of the rest pose and that
+    /// joints without a local pose rest.
+    #[test]
+    fn model_space_matrices_parents_first() {
+        let armature = two_bone_armature();
+
+        let mut local_bones = BTreeMap::new();
+        local_bones.insert(
+            1,
+            Bone::Trs(Trs {
+                translation: Vector3::zeros(),
+                rotation: UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2),
+                scale: Vector3::new(1., 1., 1.),
+            }),
+        );
+
+        let model_space = armature.model_space_matrices(&local_bones);
+
+        let child = model_space[&0].transform_point(&Point3::origin());
+        assert!((child - Point3::new(-2., 1., 0.)).norm() < 1e-5);
+
+        assert_eq!(
+            armature.socket_matrix("Child", &local_bones),
+            Some(model_space[&0])
+        );
+        assert_eq!(armature.socket_matrix("Missing", &local_bones), None);
+    }
+
+    /// Verify that the skinning transforms leave vertices in place when every joint is at rest.
+    #[test]
+    fn skinning_at_rest_is_identity() {
+        let armature = two_bone_armature();
+
+        let model_space = armature.model_space_matrices(&BTreeMap::new());
+
+        for skinning in armature.skinning_matrices(&model_space).values() {
+            assert!((skinning - Matrix4::identity()).norm() < 1e-5);
+        }
+        for skinning in armature.skinning_dual_quats(&model_space).values() {
+            assert!((skinning.real.coords - DualQuaternion::identity().real.coords).norm() < 1e-5);
+            assert!(skinning.dual.coords.norm() < 1e-5);
+        }
+    }
+
+    /// Verify that identity local poses, such as keyframes that don't move a joint away from its
+    /// rest pose, leave vertices in place.
+    #[test]
+    fn skinning_identity_local_poses_is_identity() {
+        let armature = two_bone_armature();
+
+        let mut local_bones = BTreeMap::new();
+        local_bones.insert(0, Bone::Matrix(Matrix4::identity()));
+        local_bones.insert(1, Bone::Trs(Trs::from_matrix(&Matrix4::identity())));
+
+        let model_space = armature.model_space_matrices(&local_bones);
+
+        for skinning in armature.skinning_matrices(&model_space).values() {
+            assert!((skinning - Matrix4::identity()).norm() < 1e-5);
+        }
+    }
+
+    // The root bone has the larger joint index and rests one unit up, with the child resting two
+    // units above it.
+    fn two_bone_armature() -> BlenderArmature {
+        let mut armature = BlenderArmature::default();
+
+        armature.insert_joint_index("Child".to_string(), 0);
+        armature.insert_joint_index("Root".to_string(), 1);
+        armature.insert_child_to_parent(0, 1);
+        armature.set_inverse_bind_poses(vec![
+            Bone::Matrix(Matrix4::new_translation(&Vector3::new(0., -3., 0.))),
+            Bone::Matrix(Matrix4::new_translation(&Vector3::new(0., -1., 0.))),
+        ]);
+
+        armature
+    }
+}
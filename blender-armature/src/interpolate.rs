@@ -1,7 +1,7 @@
 //! Methods and configuration for interpolating keyframed poses, useful for skeletal animation.
 //!
-//! BlenderArmature currently supports dual quaternion interpolation, but could support 4x4 matrix
-//! interpolation if you open an issue/PR.
+//! Dual quaternion bones are interpolated with dual quaternion linear blending. Matrix bones are
+//! decomposed into a translation, rotation and scale that are interpolated separately.
 //!
 //! The initial implementation and tests are based off of [skeletal-animation-system](https://github.com/chinedufn/skeletal-animation-system/blob/master/test/skeletal-animation-system.js)
 //!
@@ -30,9 +30,11 @@ use std::time::Duration;
 use crate::{BlenderArmature, Bone, JointIndicesRef, SampleDesc};
 
 pub use self::interpolated_bones::*;
+pub use self::interpolation_error::*;
 use std::collections::BTreeMap;
 
 mod interpolated_bones;
+mod interpolation_error;
 mod sample_action;
 
 /// Returns 0.0 if no time has elapsed.
@@ -49,17 +51,21 @@ impl BlenderArmature {
     /// We return a map so that you can easily merge the the interpolating bones with other
     /// interpolations. This is useful when you are combining multiple bone groups.
     ///
-    /// # Panics
+    /// Matrix bones need to be column major, so you'll typically call
+    /// [`BlenderArmature::transpose_actions`] before interpolating them.
+    ///
+    /// Keyframes are relative to each joint's rest pose, so joints that have no keyframes in the
+    /// action are left at rest with an identity bone. Sampling [`JointIndicesRef::All`] returns
+    /// one bone for every joint in the armature.
     ///
-    /// We don't currently interpolating matrix bones, so we panic if your bones aren't
-    /// dual quaternions.
+    /// # Panics
     ///
-    /// Panics if you pass in previous actions that do not have the exact same joint indices
-    /// as your current action.
+    /// Panics if the action does not exist, if a joint is neither keyframed nor one of the
+    /// armature's joints or if the action mixes bone types. See
+    /// [`BlenderArmature::try_interpolate_bones`].
     ///
     /// # TODO
     ///
-    /// - [ ] Return Result<HashMap<u8, Bone>, InterpolationError>
     /// - [ ] error if clock time is negative
     pub fn interpolate_bones(
         &self,
@@ -67,6 +73,20 @@ impl BlenderArmature {
         joint_indices: JointIndicesRef,
         sample_desc: SampleDesc,
     ) -> BTreeMap<u8, Bone> {
+        self.try_interpolate_bones(action_name, joint_indices, sample_desc)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Interpolate in between the keyframes of your BlenderArmature, returning an error instead
+    /// of panicking when the bones can't be sampled.
+    ///
+    /// See [`BlenderArmature::interpolate_bones`].
+    pub fn try_interpolate_bones(
+        &self,
+        action_name: &str,
+        joint_indices: JointIndicesRef,
+        sample_desc: SampleDesc,
+    ) -> Result<BTreeMap<u8, Bone>, InterpolationError> {
         self.sample_action(action_name, joint_indices, sample_desc)
     }
 }
@@ -79,8 +99,10 @@ pub(super) mod tests {
     use crate::{Bone, BoneKeyframe, FrameOffset, JointIndicesRef, Keyframe, SampleDesc};
 
     use super::*;
-    use crate::test_util::{action_name, action_with_keyframes, BONE_IDX};
-    use nalgebra::{DualQuaternion, Quaternion};
+    use crate::test_util::{action_name, action_with_keyframes, bone_dual_quat_identity, BONE_IDX};
+    use crate::Trs;
+    use nalgebra::{DualQuaternion, Matrix4, Quaternion, UnitQuaternion, Vector3};
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
 
     struct DualQuatTestCase {
         keyframes: Vec<TestKeyframeDualQuat>,
@@ -262,6 +284,230 @@ pub(super) mod tests {
         .test();
     }
 
+    /// Verify that matrix bones are interpolated by their translation, rotation and scale
+    #[test]
+    fn interpolate_matrices() {
+        let start = Trs {
+            translation: Vector3::new(0., 0., 0.),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::new(1., 1., 1.),
+        };
+        let end = Trs {
+            translation: Vector3::new(2., 4., 6.),
+            rotation: UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2),
+            scale: Vector3::new(3., 1., 1.),
+        };
+
+        let keyframes = vec![
+            BoneKeyframe::new(0, Bone::Matrix(start.to_matrix())),
+            BoneKeyframe::new(2, Bone::Matrix(end.to_matrix())),
+        ];
+        let armature = BlenderArmature {
+            bone_space_actions: action_with_keyframes(keyframes),
+            ..BlenderArmature::default()
+        };
+
+        let interpolated_bones = armature.interpolate_bones(
+            &action_name(),
+            JointIndicesRef::Some(&[BONE_IDX]),
+            SampleDesc {
+                frame_offset: FrameOffset::new_with_elapsed_time_and_frames_per_second(
+                    Duration::from_secs(1),
+                    ONE_FPS,
+                ),
+                should_loop: false,
+            },
+        );
+        let trs = interpolated_bones.get(&BONE_IDX).unwrap().to_trs();
+
+        let expected_rotation = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_4);
+        assert!((trs.translation - Vector3::new(1., 2., 3.)).norm() < 1e-5);
+        assert!(trs.rotation.angle_to(&expected_rotation) < 1e-4);
+        assert!((trs.scale - Vector3::new(2., 1., 1.)).norm() < 1e-5);
+    }
+
+    /// Verify that translation rotation scale bones keep their scale when sampled
+    #[test]
+    fn interpolate_trs() {
+        let trs = |scale: f32| {
+            Bone::Trs(Trs {
+                translation: Vector3::zeros(),
+                rotation: UnitQuaternion::identity(),
+                scale: Vector3::new(1., scale, 1.),
+            })
+        };
+
+        let armature = BlenderArmature {
+            bone_space_actions: action_with_keyframes(vec![
+                BoneKeyframe::new(0, trs(1.)),
+                BoneKeyframe::new(4, trs(3.)),
+            ]),
+            ..BlenderArmature::default()
+        };
+
+        let interpolated_bones = armature.interpolate_bones(
+            &action_name(),
+            JointIndicesRef::Some(&[BONE_IDX]),
+            SampleDesc {
+                frame_offset: FrameOffset::new_with_elapsed_time_and_frames_per_second(
+                    Duration::from_secs(1),
+                    ONE_FPS,
+                ),
+                should_loop: false,
+            },
+        );
+
+        assert_eq!(interpolated_bones.get(&BONE_IDX), Some(&trs(1.5)));
+        assert_eq!(
+            interpolated_bones[&BONE_IDX].to_matrix(),
+            Matrix4::new_nonuniform_scaling(&Vector3::new(1., 1.5, 1.))
+        );
+    }
+
+    /// Verify that sampling all joints returns every joint, with joints that have no keyframes
+    /// left at rest as identity bones of the same kind as the action's keyframes.
+    #[test]
+    fn sample_all_joints() {
+        let trs = |translation: Vector3<f32>| Trs {
+            translation,
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::new(1., 1., 1.),
+        };
+
+        let mut armature = BlenderArmature {
+            bone_space_actions: action_with_keyframes(vec![BoneKeyframe::new(
+                0,
+                Bone::Trs(trs(Vector3::new(5., 0., 0.))),
+            )]),
+            ..BlenderArmature::default()
+        };
+        armature.insert_joint_index("Root".to_string(), 0);
+        armature.insert_joint_index("Child".to_string(), 1);
+        armature.insert_child_to_parent(1, 0);
+        armature.set_inverse_bind_poses(vec![
+            Bone::Matrix(Matrix4::new_translation(&Vector3::new(0., -1., 0.))),
+            Bone::Matrix(Matrix4::new_translation(&Vector3::new(0., -3., 0.))),
+        ]);
+
+        let bones = armature.interpolate_bones(
+            &action_name(),
+            JointIndicesRef::All,
+            SampleDesc {
+                frame_offset: FrameOffset::new_with_elapsed_time_and_frames_per_second(
+                    Duration::from_secs(0),
+                    ONE_FPS,
+                ),
+                should_loop: false,
+            },
+        );
+
+        assert_eq!(
+            bones.keys().copied().collect::<Vec<_>>(),
+            vec![0, 1, BONE_IDX]
+        );
+        assert_eq!(bones[&0], Bone::Trs(trs(Vector3::zeros())));
+        assert_eq!(bones[&1], Bone::Trs(trs(Vector3::zeros())));
+        assert_eq!(bones[&BONE_IDX], Bone::Trs(trs(Vector3::new(5., 0., 0.))));
+    }
+
+    /// Verify that we return an error instead of panicking when the action or joint is missing
+    #[test]
+    fn try_interpolate_missing_action_or_joint() {
+        let armature = BlenderArmature {
+            bone_space_actions: action_with_keyframes(vec![BoneKeyframe::new(
+                0,
+                bone_dual_quat_identity(),
+            )]),
+            ..BlenderArmature::default()
+        };
+        let sample_desc = SampleDesc {
+            frame_offset: FrameOffset::new_with_elapsed_time_and_frames_per_second(
+                Duration::from_secs(0),
+                ONE_FPS,
+            ),
+            should_loop: true,
+        };
+
+        assert_eq!(
+            armature.try_interpolate_bones("Typo", JointIndicesRef::All, sample_desc),
+            Err(InterpolationError::MissingAction {
+                action: "Typo".to_string()
+            })
+        );
+        assert_eq!(
+            armature.try_interpolate_bones(
+                &action_name(),
+                JointIndicesRef::Some(&[BONE_IDX, 5]),
+                sample_desc
+            ),
+            Err(InterpolationError::MissingJoint { joint_idx: 5 })
+        );
+    }
+
+    /// Verify that we return an error instead of panicking when interpolating between different
+    /// types of bones
+    #[test]
+    fn try_interpolate_mixed_bone_types() {
+        let armature = BlenderArmature {
+            bone_space_actions: action_with_keyframes(vec![
+                BoneKeyframe::new(0, bone_dual_quat_identity()),
+                BoneKeyframe::new(2, Bone::Matrix(Matrix4::identity())),
+            ]),
+            ..BlenderArmature::default()
+        };
+
+        let bones = armature.try_interpolate_bones(
+            &action_name(),
+            JointIndicesRef::Some(&[BONE_IDX]),
+            SampleDesc {
+                frame_offset: FrameOffset::new_with_elapsed_time_and_frames_per_second(
+                    Duration::from_secs(1),
+                    ONE_FPS,
+                ),
+                should_loop: false,
+            },
+        );
+
+        assert_eq!(
+            bones,
+            Err(InterpolationError::MixedBoneTypes {
+                start: "DualQuat",
+                end: "Matrix"
+            })
+        );
+    }
+
+    /// Verify that we get an error instead of a panic when blending bones that do not have the
+    /// same joints or that are of different types.
+    #[test]
+    fn try_blend_mismatched_bones() {
+        let start: BTreeMap<u8, Bone> = vec![(0, bone_dual_quat_identity())].into_iter().collect();
+
+        let missing_joint: BTreeMap<u8, Bone> = vec![
+            (0, bone_dual_quat_identity()),
+            (1, bone_dual_quat_identity()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            try_blend_towards_bones(&start, &missing_joint, 0.5),
+            Err(InterpolationError::MismatchedJoints { joint_idx: 1 })
+        );
+
+        let mixed_types: BTreeMap<u8, Bone> = vec![(0, Bone::Matrix(Matrix4::identity()))]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            try_blend_towards_bones(&start, &mixed_types, 0.5),
+            Err(InterpolationError::MixedBoneTypes {
+                start: "DualQuat",
+                end: "Matrix"
+            })
+        );
+
+        assert_eq!(try_blend_towards_bones(&start, &start, 0.5), Ok(start));
+    }
+
     impl DualQuatTestCase {
         fn test(self) {
             let mut keyframes = vec![];
@@ -1,33 +1,54 @@
-use crate::Bone;
+use crate::{Bone, InterpolationError, Trs};
 use nalgebra::DualQuaternion;
 use std::collections::BTreeMap;
 
 /// Blend from the start bones towards the ending bones.
 ///
+/// # Panics
+///
+/// Panics if the start and end bones do not have the same joints or if any of their bones are
+/// not of the same type. See [`try_blend_towards_bones`].
+///
 /// TODO: Delete. We now favor blending once at a time since this makes for a simpler API with
 ///  fewer allocations
+///
+/// [`try_blend_towards_bones`]: fn.try_blend_towards_bones.html
 pub fn blend_towards_bones(
     start: &BTreeMap<u8, Bone>,
     end: &BTreeMap<u8, Bone>,
     interp_param: f32,
 ) -> BTreeMap<u8, Bone> {
-    start
-        .iter()
-        .zip(end.iter())
-        .map(
-            |((prev_joint_idx, prev_action_bone), (cur_joint_idx, cur_action_bone))| {
-                // TODO: We were using a hashmap where the iteration order isn't guaranteed and hence we would hit this condition.
-                //  Really just need to refactor all of landon now that we're much more experienced with Rust.
-                if prev_joint_idx != cur_joint_idx {
-                    panic!("We do not currently support the current action having different joints than the previous action");
-                }
-
+    try_blend_towards_bones(start, end, interp_param).unwrap_or_else(|err| panic!("{}", err))
+}
 
-                let new_bone = interpolate_bone(*prev_action_bone, *cur_action_bone, interp_param);
+/// Blend from the start bones towards the ending bones, returning an error instead of panicking
+/// if they do not have the same joints or if any of their bones are not of the same type.
+///
+/// See [`blend_towards_bones`].
+///
+/// [`blend_towards_bones`]: fn.blend_towards_bones.html
+pub fn try_blend_towards_bones(
+    start: &BTreeMap<u8, Bone>,
+    end: &BTreeMap<u8, Bone>,
+    interp_param: f32,
+) -> Result<BTreeMap<u8, Bone>, InterpolationError> {
+    let mismatched_joint = start
+        .keys()
+        .chain(end.keys())
+        .filter(|joint_idx| !start.contains_key(joint_idx) || !end.contains_key(joint_idx))
+        .min();
+    if let Some(joint_idx) = mismatched_joint {
+        return Err(InterpolationError::MismatchedJoints {
+            joint_idx: *joint_idx,
+        });
+    }
 
-                (*cur_joint_idx, new_bone)
-            },
-        )
+    start
+        .iter()
+        .map(|(joint_idx, start_bone)| {
+            let bone = try_interpolate_bone(*start_bone, end[joint_idx], interp_param)?;
+            Ok((*joint_idx, bone))
+        })
         .collect()
 }
 
@@ -36,19 +57,59 @@ pub fn blend_towards_bones(
 /// When the interpolation parameter is 0.0 the start bone is used.
 /// At 1.0 the end bone is used.
 ///
+/// Matrix bones are decomposed into a translation, rotation and scale which are interpolated
+/// separately and then composed back into a matrix, so they must be column major. See
+/// [`Trs::interpolate`].
+///
 /// # Panics
 ///
 /// Panics of the `amount < 0.0 || amount > 1.0`
+///
+/// Panics if the start and end bones are not of the same type. See [`try_interpolate_bone`].
+///
+/// [`Trs::interpolate`]: struct.Trs.html#method.interpolate
+/// [`try_interpolate_bone`]: fn.try_interpolate_bone.html
 pub fn interpolate_bone(start_bone: Bone, end_bone: Bone, amount: f32) -> Bone {
-    match start_bone {
-        Bone::DualQuat(start) => match end_bone {
-            Bone::DualQuat(mut end) => Bone::DualQuat(interpolate_dual_quats(start, end, amount)),
-            _ => panic!(
-                r#"You may only interpolate bones of the same type. Please convert
-your end bone into a dual quaternion before interpolating"#
-            ),
-        },
-        Bone::Matrix(_matrix) => unimplemented!(),
+    try_interpolate_bone(start_bone, end_bone, amount).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Interpolate from the start to the end bone, returning an error instead of panicking if the
+/// bones are not of the same type.
+///
+/// See [`interpolate_bone`].
+///
+/// [`interpolate_bone`]: fn.interpolate_bone.html
+pub fn try_interpolate_bone(
+    start_bone: Bone,
+    end_bone: Bone,
+    amount: f32,
+) -> Result<Bone, InterpolationError> {
+    let bone = match (start_bone, end_bone) {
+        (Bone::DualQuat(start), Bone::DualQuat(end)) => {
+            Bone::DualQuat(interpolate_dual_quats(start, end, amount))
+        }
+        (Bone::Trs(start), Bone::Trs(end)) => Bone::Trs(start.interpolate(&end, amount)),
+        (Bone::Matrix(start), Bone::Matrix(end)) => Bone::Matrix(
+            Trs::from_matrix(&start)
+                .interpolate(&Trs::from_matrix(&end), amount)
+                .to_matrix(),
+        ),
+        (start, end) => {
+            return Err(InterpolationError::MixedBoneTypes {
+                start: bone_type_name(&start),
+                end: bone_type_name(&end),
+            })
+        }
+    };
+
+    Ok(bone)
+}
+
+fn bone_type_name(bone: &Bone) -> &'static str {
+    match bone {
+        Bone::Matrix(_) => "Matrix",
+        Bone::DualQuat(_) => "DualQuat",
+        Bone::Trs(_) => "Trs",
     }
 }
 
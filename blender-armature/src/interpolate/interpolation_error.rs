@@ -0,0 +1,38 @@
+/// An error when sampling or interpolating an armature's bones.
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum InterpolationError {
+    /// The armature does not have an action with this name.
+    #[error("The armature does not have an action named {action:?}")]
+    MissingAction {
+        /// The name of the action that was requested
+        action: String,
+    },
+    /// The joint has no keyframes to sample and is not one of the armature's joints, so there
+    /// is no rest pose to fall back to either.
+    #[error("Joint {joint_idx} has no keyframes and is not one of the armature's joints")]
+    MissingJoint {
+        /// The index of the joint that was requested
+        joint_idx: u8,
+    },
+    /// The keyframes do not have any frames, so there is nothing to sample.
+    #[error("There are no keyframes to sample")]
+    EmptyFrameRange,
+    /// Only bones of the same type can be interpolated. Convert your bones into the same type,
+    /// such as with [`BlenderArmature::matrices_to_dual_quats`], before interpolating them.
+    ///
+    /// [`BlenderArmature::matrices_to_dual_quats`]: struct.BlenderArmature.html#method.matrices_to_dual_quats
+    #[error("Cannot interpolate between bones of different types: {start} and {end}")]
+    MixedBoneTypes {
+        /// The type of the starting bone
+        start: &'static str,
+        /// The type of the ending bone
+        end: &'static str,
+    },
+    /// Only bones for the same joints can be blended together, but this joint is missing from
+    /// either the start or the end bones.
+    #[error("Joint {joint_idx} is missing from either the start or the end bones")]
+    MismatchedJoints {
+        /// The index of the joint that only one of the sets of bones has
+        joint_idx: u8,
+    },
+}
@@ -1,6 +1,8 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
-use crate::{BlenderArmature, Bone, JointIndicesRef, SampleDesc};
+use nalgebra::{DualQuaternion, Matrix4};
+
+use crate::{BlenderArmature, Bone, InterpolationError, JointIndicesRef, SampleDesc, Trs};
 
 impl BlenderArmature {
     pub(super) fn sample_action(
@@ -8,26 +10,88 @@ impl BlenderArmature {
         action_name: &str,
         joint_indices: JointIndicesRef,
         sample_desc: SampleDesc,
-    ) -> BTreeMap<u8, Bone> {
+    ) -> Result<BTreeMap<u8, Bone>, InterpolationError> {
+        let bone_keyframes = self
+            .bone_space_actions
+            .get(action_name)
+            .ok_or_else(|| InterpolationError::MissingAction {
+                action: action_name.to_string(),
+            })?
+            .bone_keyframes();
+
         let joint_indices = match joint_indices {
-            JointIndicesRef::All => unimplemented!("TODO"),
-            JointIndicesRef::Some(joint_indices) => joint_indices,
+            JointIndicesRef::All => {
+                let all: BTreeSet<u8> = self
+                    .joint_indices
+                    .values()
+                    .chain(bone_keyframes.keys())
+                    .copied()
+                    .collect();
+                all.into_iter().collect()
+            }
+            JointIndicesRef::Some(joint_indices) => joint_indices.to_vec(),
         };
 
+        // Keyframes are relative to the rest pose, so joints without keyframes are left at rest
+        // with an identity bone of the same kind as the rest of the action. This lets the sampled
+        // bones be blended together.
+        let bone_kind = bone_keyframes
+            .values()
+            .find_map(|keyframes| keyframes.first())
+            .map(|keyframe| keyframe.bone())
+            .or_else(|| self.inverse_bind_poses.first().copied());
+
         let mut bones = BTreeMap::new();
 
         for joint_idx in joint_indices {
-            let bone_keyframes = self
-                .bone_space_actions
-                .get(action_name)
-                .unwrap()
-                .bone_keyframes();
+            let bone = match bone_keyframes.get(&joint_idx) {
+                Some(keyframes) if !keyframes.is_empty() => {
+                    bone_keyframes.try_sample(joint_idx, sample_desc)?
+                }
+                _ => {
+                    if !self.has_joint(joint_idx) {
+                        return Err(InterpolationError::MissingJoint { joint_idx });
+                    }
 
-            let bone = bone_keyframes.sample(*joint_idx, sample_desc);
+                    match bone_kind {
+                        Some(Bone::DualQuat(_)) => Bone::DualQuat(DualQuaternion::identity()),
+                        Some(Bone::Trs(_)) => Bone::Trs(Trs::from_matrix(&Matrix4::identity())),
+                        Some(Bone::Matrix(_)) | None => Bone::Matrix(Matrix4::identity()),
+                    }
+                }
+            };
 
-            bones.insert(*joint_idx, bone);
+            bones.insert(joint_idx, bone);
         }
 
-        bones
+        Ok(bones)
+    }
+
+    fn has_joint(&self, joint_idx: u8) -> bool {
+        (joint_idx as usize) < self.inverse_bind_poses.len()
+            || self.joint_indices.values().any(|idx| *idx == joint_idx)
+    }
+
+    /// A joint's rest pose relative to its parent, as a column major matrix.
+    ///
+    /// The bind pose is the inverse of the inverse bind pose, so the local rest pose is the
+    /// parent's inverse bind pose times the joint's bind pose. Joints without an inverse bind
+    /// pose rest at the identity.
+    pub(crate) fn rest_local_matrix(&self, joint_idx: u8) -> Matrix4<f32> {
+        let inverse_bind_pose = |joint_idx: u8| {
+            self.inverse_bind_poses
+                .get(joint_idx as usize)
+                .map(|bone| bone.to_matrix())
+                .unwrap_or_else(Matrix4::identity)
+        };
+
+        let bind = inverse_bind_pose(joint_idx)
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity);
+
+        match self.bone_child_to_parent.get(&joint_idx) {
+            Some(parent) => inverse_bind_pose(*parent) * bind,
+            None => bind,
+        }
     }
 }
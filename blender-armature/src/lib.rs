@@ -22,6 +22,7 @@ mod bone;
 mod convert;
 mod coordinate_system;
 mod export;
+mod forward_kinematics;
 mod interpolate;
 mod serde;
 
@@ -44,9 +45,9 @@ pub enum BlenderError {
 /// A BlenderArmature should have all of the data that you need to implement skeletal
 /// animation.
 ///
-/// If you have other needs, such as a way to know the model space position of any bone at any
-/// time so that you can, say, render a baseball in on top of your hand bone.. Open an issue.
-/// (I plan to support this specific example in the future)
+/// To know the model space position of any bone at any time so that you can, say, render a
+/// baseball on top of your hand bone, see [`BlenderArmature::model_space_matrices`] and
+/// [`BlenderArmature::socket_matrix`]. If you have other needs.. Open an issue.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 // TODO: BlenderArmature<T: Bone> for DQ and matrix
 pub struct BlenderArmature {
@@ -290,15 +291,36 @@ impl BlenderArmature {
             *bone = BlenderArmature::matrix_to_dual_quat(bone);
         }
     }
+
+    /// Convert your action matrices into translations, rotations and scales so that scaled bones
+    /// keep their scale when interpolated.
+    ///
+    /// The matrices need to be column major, so you'll typically call
+    /// [`BlenderArmature::transpose_actions`] first.
+    pub fn matrices_to_trs(&mut self) {
+        for (_, keyframes) in self.bone_space_actions.iter_mut() {
+            for keyframes in keyframes.keyframes_mut().values_mut() {
+                for bone_keyframe in keyframes.iter_mut() {
+                    bone_keyframe.set_bone(Bone::Trs(bone_keyframe.bone().to_trs()));
+                }
+            }
+        }
+
+        for bone in self.inverse_bind_poses.iter_mut() {
+            *bone = Bone::Trs(bone.to_trs());
+        }
+    }
 }
 
 impl Bone {
+    // Dual quaternions and translation, rotation and scales don't have a row or column major
+    // layout, so they're left as is.
     fn transpose(&mut self) {
         match self {
             Bone::Matrix(ref mut matrix) => {
                 matrix.transpose_mut();
             }
-            Bone::DualQuat(_) => unimplemented!(),
+            Bone::DualQuat(_) | Bone::Trs(_) => {}
         };
     }
 
@@ -310,9 +332,9 @@ impl Bone {
                     //
                     *self = Bone::Matrix(rhs_matrix * *lhs_matrix)
                 }
-                Bone::DualQuat(_) => {}
+                Bone::DualQuat(_) | Bone::Trs(_) => {}
             },
-            Bone::DualQuat(_) => {}
+            Bone::DualQuat(_) | Bone::Trs(_) => {}
         };
     }
 }
@@ -347,6 +369,26 @@ mod tests {
     use crate::test_util::action_with_keyframes;
     use nalgebra::Matrix4;
 
+    /// Verify that transposing leaves dual quaternion bones alone instead of panicking
+    #[test]
+    fn transpose_dual_quat_actions() {
+        let keyframes = vec![BoneKeyframe::new(
+            1,
+            dq_to_bone([1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0]),
+        )];
+
+        let mut armature = BlenderArmature {
+            bone_space_actions: action_with_keyframes(keyframes),
+            inverse_bind_poses: vec![dq_to_bone([1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0])],
+            ..BlenderArmature::default()
+        };
+        let original = armature.clone();
+
+        armature.transpose_actions();
+
+        assert_eq!(armature, original);
+    }
+
     #[test]
     fn convert_actions_to_dual_quats() {
         let mut keyframes = vec![];
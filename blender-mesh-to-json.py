@@ -47,9 +47,11 @@ class MeshToJSON(bpy.types.Operator):
                 'min_corner': [], 'max_corner': []
             },
             'materials': {},
+            'material_slots': [],
             'custom_properties': {},
             'attribs': {
                 'vertices_in_each_face': [],
+                'material_indices': [],
                 'positions': {
                     'indices': [],
                     'attribute': {
@@ -76,7 +78,8 @@ class MeshToJSON(bpy.types.Operator):
                         'NonUniform': []
                     },
                     'bone_indices': [],
-                    'bone_weights': []
+                    'bone_weights': [],
+                    'bone_names': []
                 }
             }
         }
@@ -94,6 +97,10 @@ class MeshToJSON(bpy.types.Operator):
             for poseBone in parentArmature.pose.bones:
                 allBoneNames.append(poseBone.name)
 
+            # Exported alongside the bone indices so that the mesh can later be matched up with a
+            # re-exported armature by bone name, even if its bones were reordered.
+            mesh_json['attribs']['bone_influences']['bone_names'] = allBoneNames
+
         # TODO: Handle triangular polygons, not just quads
         # cube.data.polygons[1].vertices[0]. Check if length
         # of face is 4... Use a triangular face in Blender to unit test.
@@ -102,6 +109,10 @@ class MeshToJSON(bpy.types.Operator):
             num_vertices_in_face = len(face.vertices)
             mesh_json['attribs']['vertices_in_each_face'].append(num_vertices_in_face)
 
+            # Each face's material_index points into the mesh's material slots
+            if mesh.material_slots:
+                mesh_json['attribs']['material_indices'].append(face.material_index)
+
             for i in range(num_vertices_in_face):
                 mesh_json['attribs']['positions']['indices'].append(face.vertices[i])
                 # TODO: Maintain a dictionary with (x, y, z) => normal index
@@ -126,13 +137,15 @@ class MeshToJSON(bpy.types.Operator):
             mesh_json['attribs']['positions']['attribute']['data'].append(vert.co.y)
             mesh_json['attribs']['positions']['attribute']['data'].append(vert.co.z)
 
-            num_groups = len(list(vert.groups))
+            num_groups = 0
             for group in vert.groups:
                 groupName = mesh.vertex_groups[group.group].name
 
                 if groupName not in allBoneNames:
                     continue
 
+                num_groups += 1
+
                 boneIndex = allBoneNames.index(groupName)
 
                 mesh_json['attribs']['bone_influences']['bone_indices'].append(boneIndex)
@@ -146,6 +159,31 @@ class MeshToJSON(bpy.types.Operator):
                 mesh_json['attribs']['uvs']['attribute']['data'].append(loop.uv.x)
                 mesh_json['attribs']['uvs']['attribute']['data'].append(loop.uv.y)
 
+            mesh_json['attribs']['active_uv_layer'] = mesh.data.uv_layers.active.name
+
+        # Every other UV layer, such as a lightmap UV. These are indexed by loop, just like the
+        # active UV layer.
+        mesh_json['attribs']['uv_layers'] = {}
+        for uv_layer in mesh.data.uv_layers:
+            if uv_layer.name == mesh.data.uv_layers.active.name:
+                continue
+
+            uv_data = []
+            for loop in uv_layer.data:
+                uv_data.append(loop.uv.x)
+                uv_data.append(loop.uv.y)
+
+            mesh_json['attribs']['uv_layers'][uv_layer.name] = {
+                'indices': mesh_json['attribs']['uvs']['indices'],
+                'attribute': {
+                    'data': uv_data,
+                    'attribute_size': 2
+                }
+            }
+
+        mesh_json['attribs']['colors'] = export_colors(mesh.data)
+        mesh_json['attribs']['custom_attributes'] = export_custom_attributes(mesh.data)
+
         if not mesh_json['armature_name']:
             mesh_json['attribs']['bone_influences'] = None
 
@@ -191,6 +229,10 @@ class MeshToJSON(bpy.types.Operator):
         mesh_json['bounding_box']['min_corner'] = min_corner
         mesh_json['bounding_box']['max_corner'] = max_corner
 
+        # The name of the material in each slot, or None for slots without a material
+        for slot in mesh.material_slots:
+            mesh_json['material_slots'].append(slot.material.name if slot.material else None)
+
         for material in mesh.data.materials:
             if material.node_tree == None:
                 continue;
@@ -292,7 +334,8 @@ class MeshToJSON(bpy.types.Operator):
                         'base_color': baseColor,
                         'roughness': roughness,
                         'metallic': metallic,
-                        'normal_map': normalMap
+                        'normal_map': normalMap,
+                        'texture_uv_layers': texture_uv_layers(material)
                     }
 
         for property in mesh.keys():
@@ -349,6 +392,158 @@ class MeshToJSON(bpy.types.Operator):
 
         return {'FINISHED'}
 
+# Image textures that are fed by a UV Map node sample from that node's UV layer.
+# Every other image texture samples from the active UV layer.
+#
+# Returns a dictionary of image name -> UV layer name
+def texture_uv_layers(material):
+    uv_layers = {}
+
+    for node in material.node_tree.nodes:
+        if node.type != 'TEX_IMAGE' or node.image is None:
+            continue
+
+        if len(node.inputs['Vector'].links) == 0:
+            continue
+
+        from_node = node.inputs['Vector'].links[0].from_node
+        if from_node.type == 'UVMAP' and from_node.uv_map:
+            uv_layers[node.image.name] = from_node.uv_map
+
+    return uv_layers
+
+# The number of floats in each of the generic attribute types that we export
+CUSTOM_ATTRIBUTE_SIZES = {
+    'FLOAT': 1,
+    'FLOAT2': 2,
+    'FLOAT_VECTOR': 3,
+}
+
+# Attributes that Blender creates for its own data, such as edge creases and flat shading, rather than
+# ones that a user authored
+BLENDER_ATTRIBUTE_NAMES = [
+    'position',
+    'normal',
+    'material_index',
+    'sharp_face',
+    'sharp_edge',
+    'crease_vert',
+    'crease_edge',
+    'bevel_weight_vert',
+    'bevel_weight_edge',
+    'freestyle_edge',
+    'freestyle_face',
+]
+
+# Export every user authored float attribute that isn't already exported some other way (such as uvs)
+# along with one index per face vertex.
+#
+# Point domain attributes are indexed by vertex, corner domain attributes by loop and face domain
+# attributes by polygon.
+def export_custom_attributes(mesh_data):
+    custom_attributes = {}
+
+    # Blender stores uv layers, as well as some of its own data, as generic attributes
+    builtin_names = BLENDER_ATTRIBUTE_NAMES + [uv_layer.name for uv_layer in mesh_data.uv_layers]
+
+    if not hasattr(mesh_data, 'attributes'):
+        return custom_attributes
+
+    for attribute in mesh_data.attributes:
+        if attribute.name.startswith('.') or attribute.name in builtin_names:
+            continue
+
+        # Internal attributes aren't shown in Blender's UI. Older versions of Blender don't have this
+        # property.
+        if getattr(attribute, 'is_internal', False):
+            continue
+
+        if attribute.data_type not in CUSTOM_ATTRIBUTE_SIZES:
+            continue
+
+        if attribute.domain not in ['POINT', 'CORNER', 'FACE']:
+            continue
+
+        attribute_size = CUSTOM_ATTRIBUTE_SIZES[attribute.data_type]
+
+        data = []
+        for item in attribute.data:
+            if attribute_size == 1:
+                data.append(item.value)
+            else:
+                data.extend(list(item.vector))
+
+        custom_attributes[attribute.name] = {
+            'indices': attribute_indices(mesh_data, attribute.domain),
+            'attribute': {
+                'data': data,
+                'attribute_size': attribute_size
+            }
+        }
+
+    return custom_attributes
+
+# Export the active color attribute as linear RGBA, along with one index per face vertex.
+#
+# Both point and corner domain colors are supported, stored as either bytes or floats.
+# Older versions of Blender only have corner domain byte colors, which we read from vertex_colors.
+# These are sRGB so we convert them to linear.
+def export_colors(mesh_data):
+    if hasattr(mesh_data, 'color_attributes'):
+        color_attribute = mesh_data.color_attributes.active_color
+        if color_attribute is None and len(mesh_data.color_attributes) > 0:
+            color_attribute = mesh_data.color_attributes[0]
+
+        if color_attribute is None or color_attribute.domain not in ['POINT', 'CORNER']:
+            return None
+
+        domain = color_attribute.domain
+        is_srgb = False
+    else:
+        color_attribute = mesh_data.vertex_colors.active
+        if color_attribute is None:
+            return None
+
+        domain = 'CORNER'
+        is_srgb = True
+
+    data = []
+    for item in color_attribute.data:
+        color = list(item.color)
+        if is_srgb:
+            color = [srgb_to_linear(channel) for channel in color[0:3]] + color[3:]
+        data.extend(color)
+
+    return {
+        'indices': attribute_indices(mesh_data, domain),
+        'attribute': {
+            'data': data,
+            'attribute_size': 4
+        }
+    }
+
+# Convert an sRGB color channel between 0 and 1 to linear. Alpha is already linear.
+def srgb_to_linear(channel):
+    if channel <= 0.04045:
+        return channel / 12.92
+
+    return ((channel + 0.055) / 1.055) ** 2.4
+
+# One index into an attribute's data per face vertex, based on the domain that the attribute is stored on
+def attribute_indices(mesh_data, domain):
+    indices = []
+
+    for face in mesh_data.polygons:
+        for i in range(len(face.vertices)):
+            if domain == 'POINT':
+                indices.append(face.vertices[i])
+            elif domain == 'CORNER':
+                indices.append(face.loop_indices[i])
+            else:
+                indices.append(face.index)
+
+    return indices
+
 def register():
     bpy.utils.register_class(MeshToJSON)
 
@@ -1,9 +1,13 @@
 use crate::vertex_attributes::{MultiIndexedVertexAttributes, VertexBoneInfluences};
+use crate::BlenderMesh;
+use blender_armature::BlenderArmature;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
 
 /// The number of bones that influence each uniform.
 ///
 /// When exported from Blender this is non uniform, but becomes uniform when
-/// we call `.set_groups_per_vertex` to make every vertex have the same number
+/// we call `.uniform_bone_influences` to make every vertex have the same number
 /// of influences.
 ///
 /// TODO: Remove this and use VertexAttribute with something like attribute_size: Varies(vec![])
@@ -34,73 +38,206 @@ impl MultiIndexedVertexAttributes {
     /// When passing data to the GPU, each vertex needs the same number of bone attributes, so
     /// we must add/remove bones from each vertex to get them equal.
     ///
-    /// Say we're setting 3 groups per vertex:
+    /// Say we're using 3 influences per vertex:
     ///  - If a vertex has one vertex group (bone) we will create two fake bones with 0.0 weight.
-    ///  - If a vertex has 5 bones we'll remove the one with the smallest weighting (influence).
+    ///  - If a vertex has 5 bones we'll remove the two with the smallest weighting (influence).
     ///
-    /// TODO: I wrote landon when I was a Rust noob and so a lot of things are just wrong. This
-    /// method doesn't work if you try to set the bone influences to two different constants in
-    /// a row - for example.
-    ///       Need to eventually rewrite a lot of the library with TDD - but we can clean up over
-    ///       time.
-    pub(crate) fn set_bone_influences_per_vertex(&mut self, count: u8) {
-        let mut normalized_group_indices = vec![];
-        let mut normalized_group_weights = vec![];
-
-        let mut current_index: u32 = 0;
-
-        // TODO: Error handling
-        if self.bone_influences.is_none() {
-            return;
+    /// Influences with a weight below `min_weight` are removed as well, although every vertex
+    /// keeps its largest influence. The remaining weights are then renormalized to sum to one.
+    ///
+    /// Bones with equal weights are ordered by their bone index so that the result doesn't
+    /// depend on the order that the vertex groups were exported in.
+    ///
+    /// The mesh's bone influences are left untouched, so this can be called again with a
+    /// different count.
+    pub(crate) fn uniform_bone_influences(
+        &self,
+        count: u8,
+        min_weight: f32,
+    ) -> Option<VertexBoneInfluences> {
+        let VertexBoneInfluences {
+            bones_per_vertex,
+            bone_indices,
+            bone_weights,
+            bone_names,
+        } = self.bone_influences.as_ref()?;
+
+        let influences_per_vertex: Vec<u8> = match bones_per_vertex {
+            BoneInfluencesPerVertex::NonUniform(influences) => influences.clone(),
+            BoneInfluencesPerVertex::Uniform(0) => vec![0; self.positions.attribute.data.len() / 3],
+            BoneInfluencesPerVertex::Uniform(influences) => {
+                vec![*influences; bone_indices.len() / *influences as usize]
+            }
+        };
+
+        let mut uniform_indices = Vec::with_capacity(influences_per_vertex.len() * count as usize);
+        let mut uniform_weights = Vec::with_capacity(influences_per_vertex.len() * count as usize);
+
+        let mut current_index = 0;
+
+        for group_count in influences_per_vertex.iter() {
+            let group_range = current_index..current_index + *group_count as usize;
+            current_index = group_range.end;
+
+            let mut influences: Vec<(u8, f32)> = bone_indices[group_range.clone()]
+                .iter()
+                .copied()
+                .zip(bone_weights[group_range].iter().copied())
+                .collect();
+
+            influences.sort_by(|(a_idx, a_weight), (b_idx, b_weight)| {
+                b_weight
+                    .partial_cmp(a_weight)
+                    .unwrap_or(Ordering::Equal)
+                    .then(a_idx.cmp(b_idx))
+            });
+
+            let kept = influences
+                .iter()
+                .skip(1)
+                .take_while(|(_, weight)| *weight >= min_weight)
+                .count()
+                + 1;
+            influences.truncate(kept.min(count as usize));
+
+            let total: f32 = influences.iter().map(|(_, weight)| weight).sum();
+            if total > 0.0 {
+                for (_, weight) in influences.iter_mut() {
+                    *weight /= total;
+                }
+            }
+
+            influences.resize(count as usize, (0, 0.0));
+
+            for (bone_idx, weight) in influences {
+                uniform_indices.push(bone_idx);
+                uniform_weights.push(weight);
+            }
         }
 
+        Some(VertexBoneInfluences {
+            bones_per_vertex: BoneInfluencesPerVertex::Uniform(count),
+            bone_indices: uniform_indices,
+            bone_weights: uniform_weights,
+            bone_names: bone_names.clone(),
+        })
+    }
+}
+
+/// An error while remapping a mesh's bone influences onto an armature's bones
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum RemapBonesError {
+    /// The mesh was exported without the names of its bones, so there's no way to match its bone
+    /// indices up with the armature's bones. Re-exporting the mesh will include them.
+    #[error("Mesh {mesh} does not have the names of the bones that influence it")]
+    MissingBoneNames {
+        /// The name of the mesh
+        mesh: String,
+    },
+    /// A bone index points past the end of the mesh's bone names.
+    #[error("Mesh {mesh} is influenced by bone {bone_index} but only has {bone_count} bone names")]
+    BoneIndexOutOfRange {
+        /// The name of the mesh
+        mesh: String,
+        /// The bone index without a name
+        bone_index: u8,
+        /// The number of bone names that the mesh has
+        bone_count: usize,
+    },
+    /// Bones that influence the mesh but that the armature doesn't have, such as after the bones
+    /// were renamed or removed.
+    #[error("Armature {armature} does not have the bones {bones:?} that influence mesh {mesh}")]
+    MissingBones {
+        /// The name of the mesh
+        mesh: String,
+        /// The name of the armature
+        armature: String,
+        /// The names of every missing bone, sorted alphabetically
+        bones: Vec<String>,
+    },
+}
+
+impl BlenderMesh {
+    /// Point the mesh's bone influences at the bones in the given armature's [`joint_indices`],
+    /// matching bones up by name.
+    ///
+    /// Bone indices are exported in the order of the parent armature's bones at the time of
+    /// export, so they go stale when bones are added, removed or reordered and the armature is
+    /// re-exported without the mesh. This also lets you use the mesh with a different armature
+    /// that has bones with the same names.
+    ///
+    /// The mesh's armature name is set to the armature's name. Nothing is changed if an error is
+    /// returned.
+    ///
+    /// [`joint_indices`]: ../blender_armature/struct.BlenderArmature.html#method.joint_indices
+    pub fn remap_bone_influences(
+        &mut self,
+        armature: &BlenderArmature,
+    ) -> Result<(), RemapBonesError> {
+        let mesh_name = &self.name;
+        let influences = match self
+            .multi_indexed_vertex_attributes
+            .bone_influences
+            .as_mut()
         {
-            let VertexBoneInfluences {
-                bones_per_vertex,
-                bone_indices,
-                bone_weights,
-            } = self.bone_influences.as_mut().unwrap();
-
-            if let BoneInfluencesPerVertex::NonUniform(bone_influences_per_vertex) =
-                &bones_per_vertex
-            {
-                for group_count in bone_influences_per_vertex.iter() {
-                    let mut vertex_indices = vec![];
-                    let mut vertex_weights = vec![];
-
-                    for index in current_index..(current_index + *group_count as u32) {
-                        vertex_indices.push(index);
-                        vertex_weights.push(bone_weights[index as usize]);
-                    }
-
-                    vertex_weights.sort_by(|a, b| b.partial_cmp(a).unwrap());
-                    vertex_indices.sort_by(|a, b| {
-                        bone_weights[*b as usize]
-                            .partial_cmp(&bone_weights[*a as usize])
-                            .unwrap()
-                    });
-
-                    let mut vertex_indices: Vec<u8> = vertex_indices
-                        .iter()
-                        .map(|i| bone_indices[*i as usize])
-                        .collect();
-
-                    vertex_indices.resize(count as usize, 0);
-                    vertex_weights.resize(count as usize, 0.0);
-
-                    normalized_group_indices.append(&mut vertex_indices);
-                    normalized_group_weights.append(&mut vertex_weights);
-
-                    current_index += *group_count as u32;
-                }
+            Some(influences) => influences,
+            None => {
+                self.armature_name = Some(armature.name().clone());
+                return Ok(());
             }
+        };
+
+        if influences.bone_names.is_empty() && !influences.bone_indices.is_empty() {
+            return Err(RemapBonesError::MissingBoneNames {
+                mesh: mesh_name.clone(),
+            });
+        }
+
+        let mut missing_bones = BTreeSet::new();
+        let mut remapped = Vec::with_capacity(influences.bone_indices.len());
+
+        for bone_index in influences.bone_indices.iter() {
+            let bone_name = influences
+                .bone_names
+                .get(*bone_index as usize)
+                .ok_or_else(|| RemapBonesError::BoneIndexOutOfRange {
+                    mesh: mesh_name.clone(),
+                    bone_index: *bone_index,
+                    bone_count: influences.bone_names.len(),
+                })?;
+
+            match armature.joint_indices().get(bone_name) {
+                Some(joint_index) => remapped.push(*joint_index),
+                None => {
+                    missing_bones.insert(bone_name.clone());
+                }
+            };
+        }
+
+        if !missing_bones.is_empty() {
+            return Err(RemapBonesError::MissingBones {
+                mesh: mesh_name.clone(),
+                armature: armature.name().clone(),
+                bones: missing_bones.into_iter().collect(),
+            });
         }
 
-        let mut bone_influences = self.bone_influences.as_mut().unwrap();
+        let bone_count = armature
+            .joint_indices()
+            .values()
+            .map(|joint_index| *joint_index as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mut bone_names = vec![String::new(); bone_count];
+        for (bone_name, joint_index) in armature.joint_indices() {
+            bone_names[*joint_index as usize] = bone_name.clone();
+        }
+
+        influences.bone_indices = remapped;
+        influences.bone_names = bone_names;
+        self.armature_name = Some(armature.name().clone());
 
-        bone_influences.bones_per_vertex = BoneInfluencesPerVertex::Uniform(count);
-        bone_influences.bone_indices = normalized_group_indices;
-        bone_influences.bone_weights = normalized_group_weights;
+        Ok(())
     }
 }
 
@@ -110,35 +247,176 @@ mod tests {
     use crate::combine_indices::tests::TodoDeleteMeMultiConverter;
     use crate::BlenderMesh;
 
+    /// Verify that we keep the largest influences, order ties by bone index, pad with zero
+    /// weights and renormalize the weights
     #[test]
     fn set_joints_per_vert() {
-        let mut start_mesh = BlenderMesh {
-            multi_indexed_vertex_attributes: TodoDeleteMeMultiConverter {
-                vertex_group_indices: Some(vec![0, 2, 3, 4, 0, 1, 3, 2]),
-                bone_influences_per_vertex: Some(vec![1, 3, 4].into()),
-                vertex_group_weights: Some(vec![1.0, 0.5, 0.2, 0.3, 0.6, 0.15, 0.1, 0.15]),
-                ..TodoDeleteMeMultiConverter::default()
-            }
-            .into(),
-            ..BlenderMesh::default()
-        };
+        let mesh = mesh_with_bone_influences();
 
-        start_mesh
+        let three_joints_per_vert = mesh
             .multi_indexed_vertex_attributes
-            .set_bone_influences_per_vertex(3);
-        let three_joints_per_vert = start_mesh;
+            .uniform_bone_influences(3, 0.0)
+            .unwrap();
+
+        assert_eq!(
+            three_joints_per_vert.bones_per_vertex,
+            BoneInfluencesPerVertex::Uniform(3)
+        );
+        assert_eq!(
+            three_joints_per_vert.bone_indices,
+            vec![0, 0, 0, 2, 4, 3, 0, 1, 2]
+        );
+        assert_weights_eq(
+            &three_joints_per_vert.bone_weights,
+            &[
+                1.0,
+                0.0,
+                0.0,
+                0.5,
+                0.3,
+                0.2,
+                0.6 / 0.9,
+                0.15 / 0.9,
+                0.15 / 0.9,
+            ],
+        );
+    }
+
+    /// Verify that influences below the minimum weight are removed, except for the largest
+    /// influence of each vertex
+    #[test]
+    fn prune_small_weights() {
+        let mesh = mesh_with_bone_influences();
+
+        let pruned = mesh
+            .multi_indexed_vertex_attributes
+            .uniform_bone_influences(2, 0.4)
+            .unwrap();
+
+        assert_eq!(pruned.bone_indices, vec![0, 0, 2, 0, 0, 0]);
+        assert_weights_eq(&pruned.bone_weights, &[1.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+    }
+
+    /// Verify that changing the number of influences always starts from the exported
+    /// influences, so going back to a previous count gives the same result
+    #[test]
+    fn idempotent() {
+        let mut mesh = mesh_with_bone_influences();
+        let multi = &mut mesh.multi_indexed_vertex_attributes;
+
+        let three = multi.uniform_bone_influences(3, 0.0);
+
+        let one = multi.uniform_bone_influences(1, 0.0).unwrap();
+        assert_eq!(one.bone_indices, vec![0, 2, 0]);
+        assert_weights_eq(&one.bone_weights, &[1.0, 1.0, 1.0]);
+
+        assert_eq!(multi.uniform_bone_influences(3, 0.0), three);
+
+        let eight = multi.uniform_bone_influences(8, 0.0).unwrap();
+        assert_eq!(eight.bone_indices.len(), 24);
+
+        let three = three.unwrap();
+        multi.bone_influences = Some(three.clone());
+        let rederived = multi.uniform_bone_influences(3, 0.0).unwrap();
+        assert_eq!(rederived.bone_indices, three.bone_indices);
+        assert_weights_eq(&rederived.bone_weights, &three.bone_weights);
+    }
+
+    /// Verify that bone indices are pointed at the armature's bones with the same names
+    #[test]
+    fn remap_bone_influences_by_name() {
+        let mut mesh = mesh_with_bone_names();
+
+        let mut armature = BlenderArmature::default();
+        armature.set_name("Rig".to_string());
+        armature.insert_joint_index("Hips".to_string(), 0);
+        armature.insert_joint_index("Spine".to_string(), 1);
+        armature.insert_joint_index("Head".to_string(), 2);
 
-        let expected_mesh = BlenderMesh {
+        mesh.remap_bone_influences(&armature).unwrap();
+
+        let multi = &mesh.multi_indexed_vertex_attributes;
+        assert_eq!(
+            multi.bone_influences.as_ref().unwrap().bone_indices,
+            vec![2, 0, 1]
+        );
+        assert_eq!(
+            multi.bone_names().unwrap(),
+            &["Hips".to_string(), "Spine".to_string(), "Head".to_string()]
+        );
+        assert_eq!(mesh.armature_name(), Some(&"Rig".to_string()));
+
+        let remapped = mesh.clone();
+        mesh.remap_bone_influences(&armature).unwrap();
+        assert_eq!(mesh, remapped);
+    }
+
+    /// Verify that we list the bones that the armature is missing and leave the mesh untouched
+    #[test]
+    fn remap_bone_influences_missing_bones() {
+        let mut mesh = mesh_with_bone_names();
+        let original = mesh.clone();
+
+        let mut armature = BlenderArmature::default();
+        armature.set_name("Rig".to_string());
+        armature.insert_joint_index("Spine".to_string(), 0);
+
+        assert_eq!(
+            mesh.remap_bone_influences(&armature),
+            Err(RemapBonesError::MissingBones {
+                mesh: "Mesh".to_string(),
+                armature: "Rig".to_string(),
+                bones: vec!["Head".to_string(), "Hips".to_string()],
+            })
+        );
+        assert_eq!(mesh, original);
+
+        let mut mesh = mesh_with_bone_influences();
+        mesh.name = "Mesh".to_string();
+        assert_eq!(
+            mesh.remap_bone_influences(&armature),
+            Err(RemapBonesError::MissingBoneNames {
+                mesh: "Mesh".to_string()
+            })
+        );
+    }
+
+    fn mesh_with_bone_names() -> BlenderMesh {
+        let mut mesh = BlenderMesh {
+            name: "Mesh".to_string(),
+            ..BlenderMesh::default()
+        };
+        mesh.multi_indexed_vertex_attributes.bone_influences = Some(VertexBoneInfluences {
+            bones_per_vertex: BoneInfluencesPerVertex::Uniform(1),
+            bone_indices: vec![0, 1, 2],
+            bone_weights: vec![1.0, 1.0, 1.0],
+            bone_names: vec!["Head".to_string(), "Hips".to_string(), "Spine".to_string()],
+        });
+        mesh
+    }
+
+    fn mesh_with_bone_influences() -> BlenderMesh {
+        BlenderMesh {
             multi_indexed_vertex_attributes: TodoDeleteMeMultiConverter {
-                vertex_group_indices: Some(vec![0, 0, 0, 2, 4, 3, 0, 1, 2]),
-                bone_influences_per_vertex: Some(BoneInfluencesPerVertex::Uniform(3)),
-                vertex_group_weights: Some(vec![1.0, 0.0, 0.0, 0.5, 0.3, 0.2, 0.6, 0.15, 0.15]),
+                vertex_group_indices: Some(vec![0, 2, 3, 4, 0, 1, 3, 2]),
+                bone_influences_per_vertex: Some(vec![1, 3, 4].into()),
+                vertex_group_weights: Some(vec![1.0, 0.5, 0.2, 0.3, 0.6, 0.15, 0.1, 0.15]),
                 ..TodoDeleteMeMultiConverter::default()
             }
             .into(),
             ..BlenderMesh::default()
-        };
+        }
+    }
 
-        assert_eq!(three_joints_per_vert, expected_mesh);
+    fn assert_weights_eq(weights: &[f32], expected: &[f32]) {
+        assert_eq!(weights.len(), expected.len());
+        for (weight, expected_weight) in weights.iter().zip(expected.iter()) {
+            assert!(
+                (weight - expected_weight).abs() < 1e-6,
+                "{:?} != {:?}",
+                weights,
+                expected
+            );
+        }
     }
 }
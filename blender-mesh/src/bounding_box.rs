@@ -21,3 +21,30 @@ impl Default for BoundingBox {
         }
     }
 }
+
+impl BoundingBox {
+    /// The smallest box that contains every position in a tightly packed list of x, y, z
+    /// positions.
+    ///
+    /// Empty positions lead to the default bounding box.
+    pub(crate) fn from_positions(positions: &[f32]) -> Self {
+        if positions.len() < 3 {
+            return BoundingBox::default();
+        }
+
+        let mut min_corner = Point3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max_corner = Point3::new(f32::MIN, f32::MIN, f32::MIN);
+
+        for position in positions.chunks_exact(3) {
+            for axis in 0..3 {
+                min_corner[axis] = min_corner[axis].min(position[axis]);
+                max_corner[axis] = max_corner[axis].max(position[axis]);
+            }
+        }
+
+        BoundingBox {
+            min_corner,
+            max_corner,
+        }
+    }
+}
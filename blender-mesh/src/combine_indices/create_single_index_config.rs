@@ -5,8 +5,26 @@ pub struct CreateSingleIndexConfig {
     ///
     /// If unset then no bone influences will be included in the final single indexed VertexData.
     pub bone_influences_per_vertex: Option<u8>,
+    /// Bone influences with a smaller weight than this are removed before the remaining weights
+    /// are renormalized. Every vertex keeps its largest influence.
+    ///
+    /// Defaults to 0.0, which keeps every influence that fits into `bone_influences_per_vertex`.
+    pub min_bone_weight: f32,
     /// Whether or not to calculate the tangents for each vertex.
     ///
     /// You'll want to do this when you plan to use normal mapping in your rendering pipeline.
     pub calculate_face_tangents: bool,
+    /// Whether or not to calculate a MikkTSpace tangent for each vertex, with the handedness of
+    /// its bitangent in the w component.
+    ///
+    /// These match the tangents that Blender uses when baking normal maps, so prefer them over
+    /// face tangents when using normal maps that were baked in Blender.
+    ///
+    /// Requires the mesh to have uvs and normals.
+    pub calculate_vertex_tangents: bool,
+    /// Whether or not to always use u32 indices.
+    ///
+    /// If unset then u16 indices are used unless the mesh has more vertices than a u16 can
+    /// point to.
+    pub force_u32_indices: bool,
 }
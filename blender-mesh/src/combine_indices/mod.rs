@@ -1,9 +1,13 @@
 pub use self::create_single_index_config::CreateSingleIndexConfig;
-use crate::face_tangents::face_tangent_at_idx;
-use crate::vertex_attributes::{BoneAttributes, SingleIndexedVertexAttributes, VertexAttribute};
-use crate::{BlenderMesh, BoneInfluence, Vertex};
-use std::collections::HashMap;
-use std::collections::HashSet;
+pub use self::weighted_normals::WeightedNormalsError;
+use crate::face_tangents::{face_tangent_at_idx, TangentError};
+use crate::vertex_attributes::{
+    BoneAttributes, IndexFormat, IndexedAttribute, MaterialRange, SingleIndexedVertexAttributes,
+    VertexAttribute, VertexAttributeError,
+};
+use crate::vertex_tangents::{TangentSpaceFace, VertexTangentError};
+use crate::{BlenderMesh, BoneInfluence, MeshProblem, Vertex};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ops::{Deref, DerefMut};
 
 mod create_single_index_config;
@@ -18,6 +22,53 @@ mod weighted_normals;
 /// to more easily notice any errors.
 const EASILY_RECOGNIZABLE_NUMBER: f32 = 123456789.;
 
+/// An error while combining a mesh's vertex indices, along with the name of the mesh and the
+/// stage of combining that failed.
+#[derive(Debug, thiserror::Error)]
+pub enum CombineIndicesError {
+    /// There is nothing to combine.
+    #[error("Mesh {mesh} has no faces")]
+    NoFaces {
+        /// The name of the mesh
+        mesh: String,
+    },
+    /// The indices or data don't line up, which would have us read past the end of the data.
+    /// [`BlenderMesh.validate`] will tell you about any other problems with the mesh.
+    ///
+    /// [`BlenderMesh.validate`]: struct.BlenderMesh.html#method.validate
+    #[error("Mesh {mesh} has invalid vertex data: {problems:?}")]
+    InvalidVertexData {
+        /// The name of the mesh
+        mesh: String,
+        /// The problems with the mesh's data
+        problems: Vec<MeshProblem>,
+    },
+    /// Calculating face tangents was enabled in the config but they couldn't be calculated.
+    #[error("Could not calculate the face tangents of mesh {mesh}: {error}")]
+    FaceTangents {
+        /// The name of the mesh
+        mesh: String,
+        /// Why the tangents couldn't be calculated
+        error: TangentError,
+    },
+    /// Calculating vertex tangents was enabled in the config but they couldn't be calculated.
+    #[error("Could not calculate the vertex tangents of mesh {mesh}: {error}")]
+    VertexTangents {
+        /// The name of the mesh
+        mesh: String,
+        /// Why the tangents couldn't be calculated
+        error: VertexTangentError,
+    },
+    /// The bone influences couldn't be split into the configured number of influences per vertex.
+    #[error("Could not create the bone influences of mesh {mesh}: {error}")]
+    BoneInfluences {
+        /// The name of the mesh
+        mesh: String,
+        /// Why the bone influences couldn't be created
+        error: VertexAttributeError,
+    },
+}
+
 impl BlenderMesh {
     /// We store our exported Blender mesh with indices for positions, normals and uvs because
     ///
@@ -43,25 +94,57 @@ impl BlenderMesh {
     ///
     /// TODO: There are unexpected (based on the method's name) mutations in here such as
     /// triangulation. Lot's to refactor in this crate.
+    ///
+    /// Meshes without faces, or whose indices point outside of their data, can't be combined.
+    /// Neither can meshes without uvs when the config asks for tangents.
     pub fn combine_vertex_indices(
         &mut self,
         config: &CreateSingleIndexConfig,
-    ) -> SingleIndexedVertexAttributes {
-        let mut face_tangents = None;
+    ) -> Result<SingleIndexedVertexAttributes, CombineIndicesError> {
+        if self
+            .multi_indexed_vertex_attributes
+            .vertices_in_each_face
+            .is_empty()
+            || self
+                .multi_indexed_vertex_attributes
+                .positions
+                .indices
+                .is_empty()
+        {
+            return Err(CombineIndicesError::NoFaces {
+                mesh: self.name.clone(),
+            });
+        }
 
-        if let Some(bone_influences_per_vertex) = config.bone_influences_per_vertex {
-            self.multi_indexed_vertex_attributes
-                .set_bone_influences_per_vertex(bone_influences_per_vertex);
+        let problems: Vec<MeshProblem> = self
+            .validate()
+            .into_iter()
+            .filter(MeshProblem::prevents_combining_indices)
+            .collect();
+        if !problems.is_empty() {
+            return Err(CombineIndicesError::InvalidVertexData {
+                mesh: self.name.clone(),
+                problems,
+            });
         }
 
+        let mut face_tangents = None;
+
         // Important to calculate face tangents before we modify / weight the normals
         if config.calculate_face_tangents {
-            face_tangents = Some(self.calculate_face_tangents().unwrap());
+            let tangents = self.calculate_face_tangents().map_err(|error| {
+                CombineIndicesError::FaceTangents {
+                    mesh: self.name.clone(),
+                    error,
+                }
+            })?;
+            face_tangents = Some(tangents);
         }
 
         let multi = &self.multi_indexed_vertex_attributes;
 
-        let mut largest_vert_id = *multi.positions.indices.iter().max().unwrap() as usize;
+        let mut largest_vert_id =
+            multi.positions.indices.iter().copied().max().unwrap_or(0) as usize;
 
         let mut encountered_vert_data = EncounteredIndexCombinations::default();
 
@@ -76,16 +159,36 @@ impl BlenderMesh {
         let mut expanded_uvs = vec![];
         expanded_uvs.resize((largest_vert_id + 1) * 2, EASILY_RECOGNIZABLE_NUMBER);
 
+        // Sorted by name so that the order of each vertex's named attribute indices is consistent.
+        let colors = multi
+            .colors
+            .iter()
+            .map(|indexed| ((NamedAttributeKind::Color, ""), indexed));
+        let uv_layers = multi
+            .uv_layers
+            .iter()
+            .map(|(name, indexed)| ((NamedAttributeKind::UvLayer, name.as_str()), indexed));
+        let custom_attributes = multi
+            .custom_attributes
+            .iter()
+            .map(|(name, indexed)| ((NamedAttributeKind::Custom, name.as_str()), indexed));
+        let mut named_attributes: Vec<ExpandedNamedAttribute> = colors
+            .chain(uv_layers)
+            .chain(custom_attributes)
+            .collect::<BTreeMap<_, _>>()
+            .into_iter()
+            .map(|((kind, name), indexed)| {
+                ExpandedNamedAttribute::new(kind, name, indexed, largest_vert_id)
+            })
+            .collect();
+
         let mut expanded_pos_indices = vec![];
 
-        let mut new_group_indices = multi
-            .bone_influences
-            .as_ref()
-            .map(|b| b.bone_indices.clone());
-        let mut new_group_weights = multi
-            .bone_influences
-            .as_ref()
-            .map(|b| b.bone_weights.clone());
+        let bone_influences = config
+            .bone_influences_per_vertex
+            .and_then(|count| multi.uniform_bone_influences(count, config.min_bone_weight));
+        let mut new_group_indices = bone_influences.as_ref().map(|b| b.bone_indices.clone());
+        let mut new_group_weights = bone_influences.map(|b| b.bone_weights);
 
         expanded_pos_indices.resize(multi.positions.indices.len(), 0);
 
@@ -108,12 +211,21 @@ impl BlenderMesh {
                 None => None,
             };
 
-            let vert_id_to_reuse =
-                encountered_vert_data.get(&(start_vert_id, normal_index, uv_index));
+            let combination = IndexCombination {
+                position: start_vert_id,
+                normal: normal_index,
+                uv: uv_index,
+                named: named_attributes
+                    .iter()
+                    .map(|named| named.indexed.indices[elem_array_index])
+                    .collect(),
+            };
+
+            let vert_id_to_reuse = encountered_vert_data.get(&combination).copied();
 
             // If we've already seen this combination of vertex indices we'll re-use the index
-            if vert_id_to_reuse.is_some() {
-                expanded_pos_indices[elem_array_index] = *vert_id_to_reuse.unwrap();
+            if let Some(vert_id_to_reuse) = vert_id_to_reuse {
+                expanded_pos_indices[elem_array_index] = vert_id_to_reuse;
 
                 if let Some(face_tangents) = &face_tangents {
                     if face_tangents.len() > 0 {
@@ -121,7 +233,7 @@ impl BlenderMesh {
                         // TODO: Should we weight these based on the surface area of the face /
                         // the angle of the vertex and it's two edges on the face? Do some research
                         // on what other people do.
-                        let vert_id_to_reuse = *vert_id_to_reuse.unwrap() as usize;
+                        let vert_id_to_reuse = vert_id_to_reuse as usize;
                         expanded_tangents[vert_id_to_reuse * 3] += x;
                         expanded_tangents[vert_id_to_reuse * 3 + 1] += y;
                         expanded_tangents[vert_id_to_reuse * 3 + 2] += z;
@@ -139,14 +251,13 @@ impl BlenderMesh {
                     &face_tangents,
                     &mut encountered_vert_data,
                     &mut expanded_pos_indices,
-                    start_vert_id,
+                    combination,
                     elem_array_index,
                     &mut expanded_positions,
                     &mut expanded_normals,
                     &mut expanded_uvs,
                     &mut expanded_tangents,
-                    normal_index,
-                    uv_index,
+                    &mut named_attributes,
                     face_idx,
                 );
             } else {
@@ -156,13 +267,11 @@ impl BlenderMesh {
 
                 largest_vert_id += 1;
 
-                expanded_pos_indices[elem_array_index] = largest_vert_id as u16;
+                expanded_pos_indices[elem_array_index] = largest_vert_id as u32;
 
                 self.push_generated_vertex_data(
-                    start_vert_id,
-                    normal_index,
+                    &combination,
                     &face_tangents,
-                    uv_index,
                     config.bone_influences_per_vertex,
                     new_group_indices.as_mut(),
                     new_group_weights.as_mut(),
@@ -170,13 +279,11 @@ impl BlenderMesh {
                     &mut expanded_normals,
                     &mut expanded_uvs,
                     &mut expanded_tangents,
+                    &mut named_attributes,
                     face_idx,
                 );
 
-                encountered_vert_data.insert(
-                    (start_vert_id as u16, normal_index, uv_index),
-                    largest_vert_id as u16,
-                );
+                encountered_vert_data.insert(combination, largest_vert_id as u32);
             }
 
             if face_idx + 1 < multi.vertices_in_each_face.len() {
@@ -201,38 +308,193 @@ impl BlenderMesh {
         };
 
         let bones = match (
-            &self.multi_indexed_vertex_attributes.bone_influences,
+            new_group_indices,
+            new_group_weights,
             config.bone_influences_per_vertex,
         ) {
-            (Some(_bone_attributes), Some(bone_influences_per_vertex)) => Some((
-                BoneAttributes {
-                    bone_influencers: VertexAttribute::new(
-                        new_group_indices.unwrap(),
-                        bone_influences_per_vertex,
-                    )
-                    .unwrap(),
-                    bone_weights: VertexAttribute::new(
-                        new_group_weights.unwrap(),
-                        bone_influences_per_vertex,
-                    )
-                    .unwrap(),
-                },
-                bone_influences_per_vertex,
-            )),
+            (Some(group_indices), Some(group_weights), Some(bone_influences_per_vertex)) => {
+                let bone_attributes =
+                    VertexAttribute::new(group_indices, bone_influences_per_vertex)
+                        .and_then(|bone_influencers| {
+                            Ok(BoneAttributes {
+                                bone_influencers,
+                                bone_weights: VertexAttribute::new(
+                                    group_weights,
+                                    bone_influences_per_vertex,
+                                )?,
+                            })
+                        })
+                        .map_err(|error| CombineIndicesError::BoneInfluences {
+                            mesh: self.name.clone(),
+                            error,
+                        })?;
+
+                Some((bone_attributes, bone_influences_per_vertex))
+            }
             _ => None,
         };
 
         let tangents = face_tangents.map(|_| expanded_tangents);
 
+        let mut colors = None;
+        let mut uv_layers = HashMap::new();
+        let mut custom_attributes = HashMap::new();
+        for named in named_attributes {
+            let attribute = VertexAttribute {
+                data: named.expanded,
+                attribute_size: named.indexed.attribute.attribute_size,
+            };
+
+            match named.kind {
+                NamedAttributeKind::Color => {
+                    colors = Some(attribute);
+                }
+                NamedAttributeKind::UvLayer => {
+                    uv_layers.insert(named.name.to_string(), attribute);
+                }
+                NamedAttributeKind::Custom => {
+                    custom_attributes.insert(named.name.to_string(), attribute);
+                }
+            };
+        }
+
         let mut single_indexed_vertex_attributes = SingleIndexedVertexAttributes {
             indices: expanded_pos_indices,
-            vertices: make_vertices(expanded_positions, normals, uvs, tangents, bones),
+            vertices: make_vertices(expanded_positions, normals, uvs, colors, tangents, bones),
+            active_uv_layer: self.multi_indexed_vertex_attributes.active_uv_layer.clone(),
+            uv_layers,
+            custom_attributes,
+            triangulation_warnings: vec![],
+            index_format: IndexFormat::default(),
+            material_ranges: vec![],
+            coordinate_system: self.coordinate_system,
         };
 
-        let indices = self.triangulate(&single_indexed_vertex_attributes.indices);
+        let (indices, triangulation_warnings) = self.triangulate(
+            &single_indexed_vertex_attributes.indices,
+            &single_indexed_vertex_attributes.vertices,
+        );
+        // Tangents are calculated on the faces before they were triangulated, so we need to hold
+        // on to them.
+        let tangent_space_faces = match config.calculate_vertex_tangents {
+            true => {
+                Some(self.tangent_space_faces(&single_indexed_vertex_attributes.indices, &indices))
+            }
+            false => None,
+        };
         single_indexed_vertex_attributes.indices = indices;
+        single_indexed_vertex_attributes.triangulation_warnings = triangulation_warnings;
+
+        if let Some(tangent_space_faces) = tangent_space_faces {
+            single_indexed_vertex_attributes
+                .calculate_vertex_tangents(&tangent_space_faces)
+                .map_err(|error| CombineIndicesError::VertexTangents {
+                    mesh: self.name.clone(),
+                    error,
+                })?;
+        }
+
+        // Grouping by material comes after the vertex tangents since the tangent space faces
+        // point into the triangulated indices.
+        single_indexed_vertex_attributes.material_ranges =
+            self.group_triangles_by_material(&mut single_indexed_vertex_attributes.indices);
 
-        single_indexed_vertex_attributes
+        single_indexed_vertex_attributes.index_format = match config.force_u32_indices {
+            true => IndexFormat::U32,
+            false => IndexFormat::for_vertex_count(single_indexed_vertex_attributes.vertices.len()),
+        };
+
+        Ok(single_indexed_vertex_attributes)
+    }
+
+    /// Reorder the triangulated indices so that the triangles of each material slot are next to
+    /// each other, and return the range that each material slot's triangles ended up in.
+    ///
+    /// Faces without a material index use the first slot, just like in Blender.
+    fn group_triangles_by_material(&self, triangulated: &mut Vec<u32>) -> Vec<MaterialRange> {
+        let multi = &self.multi_indexed_vertex_attributes;
+        if self.material_slots.is_empty() && multi.material_indices.is_empty() {
+            return vec![];
+        }
+
+        let mut material_triangles: BTreeMap<u16, Vec<u32>> = BTreeMap::new();
+        let mut triangle_pointer = 0;
+
+        for (face_idx, num_verts_in_face) in multi.vertices_in_each_face.iter().enumerate() {
+            let num_triangle_indices = (*num_verts_in_face as usize - 2) * 3;
+            let material_index = multi.material_indices.get(face_idx).copied().unwrap_or(0);
+
+            material_triangles
+                .entry(material_index)
+                .or_default()
+                .extend_from_slice(
+                    &triangulated[triangle_pointer..triangle_pointer + num_triangle_indices],
+                );
+
+            triangle_pointer += num_triangle_indices;
+        }
+
+        triangulated.clear();
+
+        material_triangles
+            .into_iter()
+            .map(|(material_index, triangles)| {
+                let start = triangulated.len();
+                triangulated.extend(triangles);
+
+                MaterialRange {
+                    material_index,
+                    material_name: self
+                        .material_slots
+                        .get(material_index as usize)
+                        .cloned()
+                        .flatten(),
+                    indices: start..triangulated.len(),
+                }
+            })
+            .collect()
+    }
+
+    /// The faces that we pass to MikkTSpace. Quads stay quads and every other face is split into
+    /// the triangles that it was triangulated into.
+    fn tangent_space_faces(
+        &self,
+        face_indices: &[u32],
+        triangulated: &[u32],
+    ) -> Vec<TangentSpaceFace> {
+        let mut faces = vec![];
+
+        let mut face_pointer = 0;
+        let mut triangle_pointer = 0;
+
+        for num_verts_in_face in self
+            .multi_indexed_vertex_attributes
+            .vertices_in_each_face
+            .iter()
+        {
+            let num_verts_in_face = *num_verts_in_face as usize;
+            let num_triangle_indices = (num_verts_in_face - 2) * 3;
+
+            if num_verts_in_face == 4 {
+                faces.push(TangentSpaceFace {
+                    corners: face_indices[face_pointer..face_pointer + 4].to_vec(),
+                    triangulated: triangle_pointer..triangle_pointer + num_triangle_indices,
+                });
+            } else {
+                for start in (triangle_pointer..triangle_pointer + num_triangle_indices).step_by(3)
+                {
+                    faces.push(TangentSpaceFace {
+                        corners: triangulated[start..start + 3].to_vec(),
+                        triangulated: start..start + 3,
+                    });
+                }
+            }
+
+            face_pointer += num_verts_in_face;
+            triangle_pointer += num_triangle_indices;
+        }
+
+        faces
     }
 
     // TODO: Way too many parameters - just working on splitting things up into smaller functions..
@@ -240,25 +502,28 @@ impl BlenderMesh {
         &self,
         face_tangents: &Option<Vec<f32>>,
         encountered_vert_data: &mut EncounteredIndexCombinations,
-        expanded_pos_indices: &mut Vec<u16>,
-        start_vert_id: u16,
+        expanded_pos_indices: &mut Vec<u32>,
+        combination: IndexCombination,
         elem_array_index: usize,
         expanded_positions: &mut Vec<f32>,
         expanded_normals: &mut Vec<f32>,
         expanded_uvs: &mut Vec<f32>,
         expanded_tangents: &mut Vec<f32>,
-        normal_index: Option<u16>,
-        uv_index: Option<u16>,
+        named_attributes: &mut [ExpandedNamedAttribute],
         face_idx: usize,
     ) {
         let multi = &self.multi_indexed_vertex_attributes;
 
+        let start_vert_id = combination.position;
+        let normal_index = combination.normal;
+        let uv_index = combination.uv;
+
         expanded_pos_indices[elem_array_index] = start_vert_id;
 
         let start_vert_id = start_vert_id as usize;
 
         // TODO: Six methods to get and set the normal, pos, and uv for a vertex_num
-        if let &[x, y, z] = multi.positions.attribute.data_at_idx(start_vert_id as u16) {
+        if let &[x, y, z] = multi.positions.attribute.data_at_idx(start_vert_id as u32) {
             expanded_positions[start_vert_id * 3] = x;
             expanded_positions[start_vert_id * 3 + 1] = y;
             expanded_positions[start_vert_id * 3 + 2] = z;
@@ -294,18 +559,20 @@ impl BlenderMesh {
             }
         }
 
-        let start_vert_id = start_vert_id as u16;
+        for (named, named_idx) in named_attributes.iter_mut().zip(combination.named.iter()) {
+            named.set(combination.position, *named_idx);
+        }
+
+        let start_vert_id = start_vert_id as u32;
 
-        encountered_vert_data.insert((start_vert_id, normal_index, uv_index), start_vert_id);
+        encountered_vert_data.insert(combination, start_vert_id);
     }
 
     // TODO: Way too many parameters - just working on splitting things up into smaller functions..
     fn push_generated_vertex_data(
         &self,
-        pos_idx: u16,
-        normal_idx: Option<u16>,
+        combination: &IndexCombination,
         face_tangents: &Option<Vec<f32>>,
-        uv_idx: Option<u16>,
         bone_influences_per_vertex: Option<u8>,
         new_group_indices: Option<&mut Vec<u8>>,
         new_group_weights: Option<&mut Vec<f32>>,
@@ -313,10 +580,15 @@ impl BlenderMesh {
         expanded_normals: &mut Vec<f32>,
         expanded_uvs: &mut Vec<f32>,
         expanded_tangents: &mut Vec<f32>,
+        named_attributes: &mut [ExpandedNamedAttribute],
         face_idx: usize,
     ) {
         let multi = &self.multi_indexed_vertex_attributes;
 
+        let pos_idx = combination.position;
+        let normal_idx = combination.normal;
+        let uv_idx = combination.uv;
+
         if let &[x, y, z] = multi.positions.attribute.data_at_idx(pos_idx) {
             expanded_positions.push(x);
             expanded_positions.push(y);
@@ -337,8 +609,7 @@ impl BlenderMesh {
             }
         }
 
-        if let Some(uvs) = &multi.uvs {
-            let uv_index = uv_idx.unwrap();
+        if let (Some(uvs), Some(uv_index)) = (&multi.uvs, uv_idx) {
             if let &[u, v] = uvs.attribute.data_at_idx(uv_index) {
                 expanded_uvs.push(u);
                 expanded_uvs.push(v);
@@ -354,14 +625,22 @@ impl BlenderMesh {
             }
         }
 
+        for (named, named_idx) in named_attributes.iter_mut().zip(combination.named.iter()) {
+            named.push(*named_idx);
+        }
+
         // If the mesh has bone influences append bone data to the end of the bone vectors
         // to account for this newly generated vertex.
-        if let Some(bone_influences_per_vertex) = bone_influences_per_vertex {
+        if let (Some(bone_influences_per_vertex), Some(group_indices), Some(group_weights)) = (
+            bone_influences_per_vertex,
+            new_group_indices,
+            new_group_weights,
+        ) {
             self.push_bone_data_for_generated_vertex(
                 pos_idx as usize,
                 bone_influences_per_vertex,
-                new_group_indices.unwrap(),
-                new_group_weights.unwrap(),
+                group_indices,
+                group_weights,
             );
         }
     }
@@ -388,16 +667,29 @@ impl BlenderMesh {
     }
 }
 
-type PosIndex = u16;
-type NormalIndex = Option<u16>;
-type UvIndex = Option<u16>;
+type PosIndex = u32;
+
+/// The indices into each of a face vertex's attributes.
+///
+/// Face vertices with the same combination of indices share the same data, so they can share
+/// the same single index.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct IndexCombination {
+    position: PosIndex,
+    normal: Option<u32>,
+    uv: Option<u32>,
+    /// One index for each of the vertex colors, non-active UV layers and custom attributes, in the
+    /// same order as the `ExpandedNamedAttribute`s.
+    named: Vec<u32>,
+}
+
 #[derive(Debug, Default)]
 struct EncounteredIndexCombinations {
-    encountered: HashMap<(PosIndex, NormalIndex, UvIndex), PosIndex>,
+    encountered: HashMap<IndexCombination, PosIndex>,
 }
 
 impl Deref for EncounteredIndexCombinations {
-    type Target = HashMap<(PosIndex, NormalIndex, UvIndex), PosIndex>;
+    type Target = HashMap<IndexCombination, PosIndex>;
 
     fn deref(&self) -> &Self::Target {
         &self.encountered
@@ -410,11 +702,61 @@ impl DerefMut for EncounteredIndexCombinations {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum NamedAttributeKind {
+    Color,
+    UvLayer,
+    Custom,
+}
+
+/// The data for a vertex color, non-active UV layer or custom attribute, expanded so that there
+/// is one entry per single indexed vertex.
+struct ExpandedNamedAttribute<'a> {
+    kind: NamedAttributeKind,
+    name: &'a str,
+    indexed: &'a IndexedAttribute,
+    expanded: Vec<f32>,
+}
+
+impl<'a> ExpandedNamedAttribute<'a> {
+    fn new(
+        kind: NamedAttributeKind,
+        name: &'a str,
+        indexed: &'a IndexedAttribute,
+        largest_vert_id: usize,
+    ) -> Self {
+        let attribute_size = indexed.attribute.attribute_size as usize;
+
+        ExpandedNamedAttribute {
+            kind,
+            name,
+            indexed,
+            expanded: vec![EASILY_RECOGNIZABLE_NUMBER; (largest_vert_id + 1) * attribute_size],
+        }
+    }
+
+    /// Set the data for a vertex that keeps its position index.
+    fn set(&mut self, vert_id: u32, data_idx: u32) {
+        let attribute_size = self.indexed.attribute.attribute_size as usize;
+        let start = vert_id as usize * attribute_size;
+
+        self.expanded[start..start + attribute_size]
+            .copy_from_slice(self.indexed.attribute.data_at_idx(data_idx));
+    }
+
+    /// Push the data for a newly generated vertex.
+    fn push(&mut self, data_idx: u32) {
+        self.expanded
+            .extend_from_slice(self.indexed.attribute.data_at_idx(data_idx));
+    }
+}
+
 // TODO: We're just throwing things around as we work to refactor this crate ...
 fn make_vertices(
     vertex_positions: Vec<f32>,
     vertex_normals: Option<Vec<f32>>,
     vertex_uvs: Option<Vec<f32>>,
+    vertex_colors: Option<VertexAttribute<f32>>,
     tangents: Option<Vec<f32>>,
     bones: Option<(BoneAttributes, u8)>,
 ) -> Vec<Vertex> {
@@ -431,6 +773,14 @@ fn make_vertices(
         let uv = vertex_uvs
             .as_ref()
             .map(|uvs| [uvs[idx * 2], uvs[idx * 2 + 1]]);
+        // Colors with fewer than four values per vertex, such as RGB colors, are opaque.
+        let color = vertex_colors.as_ref().map(|c| {
+            let mut color = [0.0, 0.0, 0.0, 1.0];
+            for (channel, value) in color.iter_mut().zip(c.data_at_idx(idx as u32)) {
+                *channel = *value;
+            }
+            color
+        });
         let face_tangent = tangents.as_ref().map(|face_tangents| {
             [
                 face_tangents[idx * 3],
@@ -439,26 +789,22 @@ fn make_vertices(
             ]
         });
         let bones = bones.as_ref().map(|(b, influences_per_vertex)| {
-            let count = *influences_per_vertex;
-
-            let mut bones = [BoneInfluence {
-                bone_idx: 0,
-                weight: 0.0,
-            }; 4];
-            for bone_idx in 0..count as usize {
-                bones[bone_idx] = BoneInfluence {
-                    bone_idx: b.bone_influencers[idx * count as usize + bone_idx],
-                    weight: b.bone_weights[idx * count as usize + bone_idx],
-                };
-            }
+            let count = *influences_per_vertex as usize;
 
-            bones
+            (idx * count..(idx + 1) * count)
+                .map(|data_idx| BoneInfluence {
+                    bone_idx: b.bone_influencers[data_idx],
+                    weight: b.bone_weights[data_idx],
+                })
+                .collect()
         });
         vertices.push(Vertex {
             position,
             normal,
             face_tangent,
+            tangent: None,
             uv,
+            color,
             bones,
         });
     }
@@ -489,11 +835,18 @@ pub mod tests {
 
     impl CombineIndicesTest {
         fn test(mut self) {
-            let combined = self.mesh_to_combine.combine_vertex_indices(
-                self.create_single_idx_config
-                    .as_ref()
-                    .unwrap_or(&CreateSingleIndexConfig::default()),
-            );
+            let mut combined = self
+                .mesh_to_combine
+                .combine_vertex_indices(
+                    self.create_single_idx_config
+                        .as_ref()
+                        .unwrap_or(&CreateSingleIndexConfig::default()),
+                )
+                .unwrap();
+
+            // Our test positions all lie on one line, so every face would be reported as
+            // degenerate. Triangulation is tested on its own.
+            combined.triangulation_warnings.clear();
 
             assert_eq!(combined, self.expected_combined_mesh);
         }
@@ -623,6 +976,280 @@ pub mod tests {
         .test();
     }
 
+    /// Verify that face vertices that share a position but not a custom attribute index get
+    /// their own vertex, and that custom attributes get expanded to one entry per vertex.
+    #[test]
+    fn combine_custom_attribute_indices() {
+        let mut custom_attributes = HashMap::new();
+        custom_attributes.insert(
+            "wind".to_string(),
+            IndexedAttribute::new(vec![0, 0, 0, 0, 0, 1], (vec![0.25, 0.75], 1).into()),
+        );
+
+        let mut mesh_to_combine = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![3, 3],
+                positions: IndexedAttribute::new(
+                    vec![0, 1, 2, 0, 1, 2],
+                    (concat_vecs!(v(0), v(1), v(2)), 3).into(),
+                ),
+                custom_attributes,
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        };
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        assert_eq!(combined.indices(), &vec![0, 1, 2, 0, 1, 3]);
+        assert_eq!(combined.vertices().len(), 4);
+        assert_eq!(combined.vertices()[3].position(), [2.0, 2.0, 2.0]);
+
+        let wind = &combined.custom_attributes()["wind"];
+        assert_eq!(wind.attribute_size(), 1);
+        assert_eq!(wind.data(), &vec![0.25, 0.25, 0.25, 0.75]);
+    }
+
+    /// Verify that face vertices that share a position but not a color get their own vertex, and
+    /// that every vertex gets its color.
+    #[test]
+    fn combine_color_indices() {
+        let mut mesh_to_combine = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![3, 3],
+                positions: IndexedAttribute::new(
+                    vec![0, 1, 2, 0, 1, 2],
+                    (concat_vecs!(v(0), v(1), v(2)), 3).into(),
+                ),
+                colors: Some(IndexedAttribute::new(
+                    vec![0, 0, 0, 1, 1, 0],
+                    (vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.5], 4).into(),
+                )),
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        };
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        assert_eq!(combined.indices(), &vec![0, 1, 2, 3, 4, 2]);
+        assert_eq!(combined.vertices()[0].color(), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(combined.vertices()[4].color(), Some([0.0, 1.0, 0.0, 0.5]));
+        assert_eq!(combined.vertices()[4].position(), [1.0, 1.0, 1.0]);
+        assert_eq!(combined.colors().unwrap().data().len(), 20);
+    }
+
+    /// Verify that RGB colors become opaque RGBA colors
+    #[test]
+    fn combine_rgb_color_indices() {
+        let mut mesh_to_combine = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![3],
+                positions: IndexedAttribute::new(
+                    vec![0, 1, 2],
+                    (concat_vecs!(v(0), v(1), v(2)), 3).into(),
+                ),
+                colors: Some(IndexedAttribute::new(
+                    vec![0, 1, 1],
+                    (vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0], 3).into(),
+                )),
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        };
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        assert_eq!(combined.vertices()[0].color(), Some([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(combined.vertices()[2].color(), Some([0.0, 1.0, 0.0, 1.0]));
+    }
+
+    /// Verify that meshes with more vertices than a u16 can point to get u32 indices
+    #[test]
+    fn u32_indices_for_large_meshes() {
+        let mut mesh_to_combine = many_triangles(22_000);
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        assert_eq!(combined.vertices().len(), 66_000);
+        assert_eq!(combined.index_format(), IndexFormat::U32);
+        assert_eq!(combined.indices()[65_999], 65_999);
+        assert!(combined.u16_indices().is_none());
+    }
+
+    /// Verify that meshes that can't be combined return an error naming the mesh and the
+    /// stage that failed instead of panicking
+    #[test]
+    fn combine_errors() {
+        let mut no_faces = BlenderMesh::default();
+        no_faces.set_name("Empty".to_string());
+        match no_faces.combine_vertex_indices(&CreateSingleIndexConfig::default()) {
+            Err(CombineIndicesError::NoFaces { mesh }) => assert_eq!(mesh, "Empty"),
+            other => panic!("{:?}", other),
+        };
+
+        let mut no_uvs = many_triangles(1);
+        no_uvs.set_name("NoUvs".to_string());
+        match no_uvs.combine_vertex_indices(&CreateSingleIndexConfig {
+            calculate_face_tangents: true,
+            ..CreateSingleIndexConfig::default()
+        }) {
+            Err(CombineIndicesError::FaceTangents {
+                mesh,
+                error: TangentError::NoVertexUvs,
+            }) => assert_eq!(mesh, "NoUvs"),
+            other => panic!("{:?}", other),
+        };
+
+        let mut out_of_range = many_triangles(1);
+        out_of_range
+            .multi_indexed_vertex_attributes
+            .positions
+            .indices[2] = 100;
+        match out_of_range.combine_vertex_indices(&CreateSingleIndexConfig::default()) {
+            Err(CombineIndicesError::InvalidVertexData { problems, .. }) => {
+                assert!(matches!(
+                    problems[0],
+                    MeshProblem::IndexOutOfRange { index: 100, .. }
+                ))
+            }
+            other => panic!("{:?}", other),
+        };
+    }
+
+    /// Verify that small meshes get u16 indices unless u32 indices are requested
+    #[test]
+    fn force_u32_indices() {
+        let mut mesh_to_combine = many_triangles(1);
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+        assert_eq!(combined.index_format(), IndexFormat::U16);
+        assert_eq!(combined.u16_indices(), Some(vec![0, 1, 2]));
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig {
+                force_u32_indices: true,
+                ..CreateSingleIndexConfig::default()
+            })
+            .unwrap();
+        assert_eq!(combined.index_format(), IndexFormat::U32);
+    }
+
+    /// Verify that the triangles of each material slot are grouped into a contiguous range
+    #[test]
+    fn group_triangles_by_material() {
+        let mut mesh_to_combine = many_triangles(4);
+        mesh_to_combine.material_slots =
+            vec![Some("Metal".to_string()), Some("Paint".to_string()), None];
+        mesh_to_combine
+            .multi_indexed_vertex_attributes
+            .material_indices = vec![1, 0, 1, 2];
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        assert_eq!(
+            combined.indices(),
+            &vec![3, 4, 5, 0, 1, 2, 6, 7, 8, 9, 10, 11]
+        );
+        assert_eq!(
+            combined.material_ranges(),
+            &vec![
+                MaterialRange {
+                    material_index: 0,
+                    material_name: Some("Metal".to_string()),
+                    indices: 0..3,
+                },
+                MaterialRange {
+                    material_index: 1,
+                    material_name: Some("Paint".to_string()),
+                    indices: 3..9,
+                },
+                MaterialRange {
+                    material_index: 2,
+                    material_name: None,
+                    indices: 9..12,
+                },
+            ]
+        );
+    }
+
+    /// A mesh with separate positions for every triangle
+    fn many_triangles(triangle_count: u32) -> BlenderMesh {
+        let mut positions = vec![];
+        for idx in 0..triangle_count {
+            let x = idx as f32;
+            positions.extend_from_slice(&[x, 0., 0., x + 1., 0., 0., x, 1., 0.]);
+        }
+
+        BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![3; triangle_count as usize],
+                positions: IndexedAttribute::new(
+                    (0..triangle_count * 3).collect(),
+                    (positions, 3).into(),
+                ),
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        }
+    }
+
+    /// Verify that face vertices that share their active UV but not the UV in another layer get
+    /// their own vertex.
+    #[test]
+    fn combine_uv_layer_indices() {
+        let mut uv_layers = HashMap::new();
+        uv_layers.insert(
+            "Lightmap".to_string(),
+            IndexedAttribute::new(
+                vec![0, 1, 2, 0, 1, 3],
+                (concat_vecs!(v2(4), v2(5), v2(6), v2(7)), 2).into(),
+            ),
+        );
+
+        let mut mesh_to_combine = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![3, 3],
+                positions: IndexedAttribute::new(
+                    vec![0, 1, 2, 0, 1, 2],
+                    (concat_vecs!(v(0), v(1), v(2)), 3).into(),
+                ),
+                uvs: Some(IndexedAttribute::new(
+                    vec![0, 1, 2, 0, 1, 2],
+                    (concat_vecs!(v2(0), v2(1), v2(2)), 2).into(),
+                )),
+                active_uv_layer: Some("Texture".to_string()),
+                uv_layers,
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        };
+
+        let combined = mesh_to_combine
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        assert_eq!(combined.indices(), &vec![0, 1, 2, 0, 1, 3]);
+        assert_eq!(combined.vertices()[3].uv(), Some([2.0, 2.0]));
+
+        assert_eq!(combined.vertex_uv(3, "Texture"), Some([2.0, 2.0]));
+        assert_eq!(combined.vertex_uv(2, "Lightmap"), Some([6.0, 6.0]));
+        assert_eq!(combined.vertex_uv(3, "Lightmap"), Some([7.0, 7.0]));
+        assert_eq!(combined.vertex_uv(3, "Missing"), None);
+    }
+
     fn mesh_to_combine_pos_norm_uv_indices() -> BlenderMesh {
         BlenderMesh {
             multi_indexed_vertex_attributes: TodoDeleteMeMultiConverter {
@@ -781,7 +1408,7 @@ pub mod tests {
                 bone_influences_per_vertex: Some(vec![3, 2, 5, 1].into()),
                 vertex_group_indices: Some(vec![0, 1, 2, 0, 3, 4, 5, 6, 7, 8, 11]),
                 vertex_group_weights: Some(vec![
-                    0.05, 0.8, 0.15, 0.5, 0.5, 0.1, 0.2, 0.2, 0.2, 0.3, 0.999,
+                    0.0625, 0.75, 0.1875, 0.5, 0.5, 0.0625, 0.125, 0.125, 0.125, 0.25, 0.999,
                 ]),
             }
             .into(),
@@ -803,10 +1430,10 @@ pub mod tests {
             vertex_group_indices: Some(vec![
                 1, 2, 0, 0, 3, 0, 8, 5, 6, 11, 0, 0, 1, 2, 0, 0, 3, 0, 8, 5, 6, 11, 0, 0,
             ]),
-            // Config.bone_influences_per_vertex = 3
+            // Config.bone_influences_per_vertex = 3, renormalized
             vertex_group_weights: Some(vec![
-                0.8, 0.15, 0.05, 0.5, 0.5, 0.0, 0.3, 0.2, 0.2, 0.999, 0.0, 0.0, 0.8, 0.15, 0.05,
-                0.5, 0.5, 0.0, 0.3, 0.2, 0.2, 0.999, 0.0, 0.0,
+                0.75, 0.1875, 0.0625, 0.5, 0.5, 0.0, 0.5, 0.25, 0.25, 1.0, 0.0, 0.0, 0.75, 0.1875,
+                0.0625, 0.5, 0.5, 0.0, 0.5, 0.25, 0.25, 1.0, 0.0, 0.0,
             ]),
             tangents: None,
             vertex_uvs: None,
@@ -820,11 +1447,11 @@ pub mod tests {
     #[derive(Default)]
     pub struct TodoDeleteMeMultiConverter {
         pub vertex_positions: Vec<f32>,
-        pub vertex_position_indices: Vec<u16>,
+        pub vertex_position_indices: Vec<u32>,
         pub num_vertices_in_each_face: Vec<u8>,
         pub vertex_normals: Vec<f32>,
-        pub vertex_normal_indices: Vec<u16>,
-        pub vertex_uv_indices: Option<Vec<u16>>,
+        pub vertex_normal_indices: Vec<u32>,
+        pub vertex_uv_indices: Option<Vec<u32>>,
         pub vertex_uvs: Option<Vec<f32>>,
         pub(crate) bone_influences_per_vertex: Option<BoneInfluencesPerVertex>,
         // Config.bone_influences_per_vertex = 3
@@ -855,6 +1482,7 @@ pub mod tests {
                     bones_per_vertex: bone_influences_per_vertex,
                     bone_indices: self.vertex_group_indices.unwrap(),
                     bone_weights: self.vertex_group_weights.unwrap(),
+                    bone_names: vec![],
                 })
             }
 
@@ -867,6 +1495,7 @@ pub mod tests {
                 normals,
                 uvs,
                 bone_influences: parent_armature_bone_influences,
+                ..MultiIndexedVertexAttributes::default()
             }
         }
     }
@@ -875,7 +1504,7 @@ pub mod tests {
     #[derive(Default)]
     pub struct TodoDeleteMeSingleConverter {
         pub vertex_positions: Vec<f32>,
-        pub vertex_position_indices: Vec<u16>,
+        pub vertex_position_indices: Vec<u32>,
         pub num_vertices_in_each_face: Vec<u8>,
         pub vertex_normals: Vec<f32>,
         pub vertex_uvs: Option<Vec<f32>>,
@@ -911,9 +1540,11 @@ pub mod tests {
                     self.vertex_positions,
                     Some(self.vertex_normals),
                     self.vertex_uvs,
+                    None,
                     self.tangents,
                     bones,
                 ),
+                ..SingleIndexedVertexAttributes::default()
             }
         }
     }
@@ -6,6 +6,7 @@ use std::collections::HashMap;
 /// An error when blending normals
 #[derive(Debug, thiserror::Error)]
 pub enum WeightedNormalsError {
+    /// Every vertex needs a normal in order to blend the normals of its faces.
     #[error("There were no normals to weight")]
     NoNormals,
 }
@@ -31,12 +32,12 @@ impl SingleIndexedVertexAttributes {
         for (vertex_num, pos_norm_data_idx) in self.indices.iter().enumerate() {
             let pos_norm_data_idx = *pos_norm_data_idx as usize;
 
-            let vertex = self.vertices()[pos_norm_data_idx];
+            let vertex = &self.vertices()[pos_norm_data_idx];
 
             let pos = vertex.position;
             let pos_point = Point3::new(pos[0], pos[1], pos[2]);
 
-            let face_normal = vertex.normal.unwrap();
+            let face_normal = vertex.normal.ok_or(WeightedNormalsError::NoNormals)?;
             let face_normal = Vector3::new(face_normal[0], face_normal[1], face_normal[2]);
 
             let (connected_vertex_1, connected_vertex_2) = match vertex_num % 3 {
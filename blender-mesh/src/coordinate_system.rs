@@ -0,0 +1,296 @@
+use crate::bounding_box::BoundingBox;
+use crate::vertex_attributes::{IndexedAttribute, MultiIndexedVertexAttributes};
+use crate::{BlenderMesh, SingleIndexedVertexAttributes};
+use blender_armature::{Axis, CoordinateSystem, Hand};
+use nalgebra::{Matrix3, Point3, Vector3};
+
+impl BlenderMesh {
+    /// Blender meshes get exported with a Z up coordinate system.
+    /// Here we flip our coordinate system to be y up
+    ///
+    /// The same as calling [`BlenderMesh.change_coordinate_system`] with a Y up right handed
+    /// coordinate system, so calling it more than once is fine.
+    ///
+    /// @see https://gamedev.stackexchange.com/a/7932
+    ///
+    /// [`BlenderMesh.change_coordinate_system`]: #method.change_coordinate_system
+    pub fn y_up(&mut self) {
+        self.change_coordinate_system(CoordinateSystem::new(Axis::Y, Hand::Right));
+    }
+
+    /// The coordinate system that the mesh's data is in.
+    ///
+    /// Meshes that are exported from Blender start off Z up and right handed.
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        self.coordinate_system
+    }
+
+    /// Shift around the data in the mesh to a new coordinate system.
+    ///
+    /// Positions, normals and the bounding box are converted using
+    /// [`CoordinateSystem.conversion_matrix`], the same conversion that
+    /// [`BlenderArmature.change_coordinate_system`] uses, so a mesh and its armature stay
+    /// consistent as long as they're changed to the same coordinate system.
+    ///
+    /// When the handedness changes the winding order of every face is reversed, so that faces
+    /// keep facing the same way.
+    ///
+    /// Vertex colors are left as they are, as are custom attributes since we don't know what their
+    /// data represents.
+    ///
+    /// Does nothing if the mesh is already in the coordinate system.
+    ///
+    /// [`CoordinateSystem.conversion_matrix`]: https://docs.rs/blender-armature
+    /// [`BlenderArmature.change_coordinate_system`]: https://docs.rs/blender-armature
+    pub fn change_coordinate_system(&mut self, system: CoordinateSystem) {
+        if self.coordinate_system == system {
+            return;
+        }
+
+        let conversion = self.coordinate_system.conversion_matrix(system);
+        let multi = &mut self.multi_indexed_vertex_attributes;
+
+        convert_vectors(&mut multi.positions.attribute.data, &conversion);
+        if let Some(normals) = multi.normals.as_mut() {
+            convert_vectors(&mut normals.attribute.data, &conversion);
+        }
+
+        if self.coordinate_system.flips_handedness(system) {
+            multi.reverse_face_winding();
+        }
+
+        self.bounding_box = convert_bounding_box(&self.bounding_box, &conversion);
+        self.coordinate_system = system;
+    }
+}
+
+impl SingleIndexedVertexAttributes {
+    /// The coordinate system that the vertices are in, which is the coordinate system that
+    /// the [`BlenderMesh`] was in when its vertex indices were combined.
+    ///
+    /// [`BlenderMesh`]: struct.BlenderMesh.html
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        self.coordinate_system
+    }
+
+    /// Shift around the vertex data to a new coordinate system.
+    ///
+    /// Works just like [`BlenderMesh.change_coordinate_system`], and also converts the face and
+    /// vertex tangents. When the handedness changes the w component of the vertex tangents is
+    /// negated since the bitangents are mirrored.
+    ///
+    /// [`BlenderMesh.change_coordinate_system`]: struct.BlenderMesh.html#method.change_coordinate_system
+    pub fn change_coordinate_system(&mut self, system: CoordinateSystem) {
+        if self.coordinate_system == system {
+            return;
+        }
+
+        let conversion = self.coordinate_system.conversion_matrix(system);
+        let flips_handedness = self.coordinate_system.flips_handedness(system);
+
+        let convert =
+            |vector: [f32; 3]| -> [f32; 3] { (conversion * Vector3::from(vector)).into() };
+
+        for vertex in self.vertices.iter_mut() {
+            vertex.position = convert(vertex.position);
+            vertex.normal = vertex.normal.map(convert);
+            vertex.face_tangent = vertex.face_tangent.map(convert);
+            vertex.tangent = vertex.tangent.map(|[x, y, z, w]| {
+                let [x, y, z] = convert([x, y, z]);
+                [x, y, z, if flips_handedness { -w } else { w }]
+            });
+        }
+
+        if flips_handedness {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+
+        self.coordinate_system = system;
+    }
+}
+
+fn convert_vectors(data: &mut [f32], conversion: &Matrix3<f32>) {
+    for vector in data.chunks_exact_mut(3) {
+        let converted = conversion * Vector3::new(vector[0], vector[1], vector[2]);
+        vector.copy_from_slice(converted.as_slice());
+    }
+}
+
+impl MultiIndexedVertexAttributes {
+    /// Reverse the winding order of every face, so that the faces keep facing the same way after
+    /// their positions have been mirrored.
+    pub(crate) fn reverse_face_winding(&mut self) {
+        let vertices_in_each_face = &self.vertices_in_each_face;
+
+        for attribute in std::iter::once(&mut self.positions)
+            .chain(self.normals.as_mut())
+            .chain(self.uvs.as_mut())
+            .chain(self.colors.as_mut())
+            .chain(self.uv_layers.values_mut())
+            .chain(self.custom_attributes.values_mut())
+        {
+            reverse_attribute_winding(attribute, vertices_in_each_face);
+        }
+    }
+}
+
+/// Reverse the order of each face's vertices, keeping the first vertex in place.
+fn reverse_attribute_winding(attribute: &mut IndexedAttribute, vertices_in_each_face: &[u8]) {
+    let mut face_pointer = 0;
+
+    for num_verts_in_face in vertices_in_each_face {
+        let num_verts_in_face = *num_verts_in_face as usize;
+        attribute.indices[face_pointer + 1..face_pointer + num_verts_in_face].reverse();
+
+        face_pointer += num_verts_in_face;
+    }
+}
+
+/// Axes can be negated, so the converted corners need to be sorted back into min and max.
+fn convert_bounding_box(bounding_box: &BoundingBox, conversion: &Matrix3<f32>) -> BoundingBox {
+    let min_corner = conversion * bounding_box.min_corner.coords;
+    let max_corner = conversion * bounding_box.max_corner.coords;
+
+    BoundingBox {
+        min_corner: Point3::from(min_corner.inf(&max_corner)),
+        max_corner: Point3::from(min_corner.sup(&max_corner)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed;
+    use crate::Vertex;
+
+    #[test]
+    fn z_up_to_y_up() {
+        let mut start_mesh = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                positions: indexed((vec![0.0, 1.0, 2.0, 0.0, 1.0, 2.0], 3).into()),
+                normals: Some(indexed((vec![0.0, 1.0, 2.0], 3).into())),
+                ..MultiIndexedVertexAttributes::default()
+            },
+            bounding_box: BoundingBox {
+                min_corner: Point3::new(1.0, 2.0, 3.0),
+                max_corner: Point3::new(5.0, 6.0, 7.0),
+            },
+            ..BlenderMesh::default()
+        };
+
+        start_mesh.y_up();
+        let y_up_mesh = start_mesh;
+
+        let expected_mesh = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                positions: indexed((vec![0.0, 2.0, -1.0, 0.0, 2.0, -1.0], 3).into()),
+                normals: Some(indexed((vec![0.0, 2.0, -1.0], 3).into())),
+                ..MultiIndexedVertexAttributes::default()
+            },
+            bounding_box: BoundingBox {
+                min_corner: Point3::new(1.0, 3.0, -6.0),
+                max_corner: Point3::new(5.0, 7.0, -2.0),
+            },
+            coordinate_system: CoordinateSystem::new(Axis::Y, Hand::Right),
+            ..BlenderMesh::default()
+        };
+
+        assert_eq!(y_up_mesh, expected_mesh);
+    }
+
+    /// Verify that changing to the coordinate system that the mesh is already in does nothing
+    #[test]
+    fn idempotent() {
+        let mut mesh = BlenderMesh::pbr_cube_without_textures();
+        mesh.y_up();
+        let y_up_once = mesh.clone();
+
+        mesh.y_up();
+
+        assert_eq!(mesh, y_up_once);
+    }
+
+    /// Verify that changing handedness reverses the winding of each face, and that changing back
+    /// restores the original mesh
+    #[test]
+    fn change_handedness() {
+        let original = BlenderMesh::pbr_cube_without_textures();
+        let mut mesh = original.clone();
+
+        mesh.change_coordinate_system(CoordinateSystem::new(Axis::Y, Hand::Left));
+
+        let positions = &mesh.multi_indexed_vertex_attributes.positions.indices;
+        let original_positions = &original.multi_indexed_vertex_attributes.positions.indices;
+        assert_eq!(positions[0], original_positions[0]);
+        assert_eq!(positions[1], original_positions[3]);
+        assert_eq!(positions[3], original_positions[1]);
+
+        mesh.change_coordinate_system(CoordinateSystem::default());
+        assert_eq!(mesh, original);
+    }
+
+    /// Verify that tangents are converted, and their handedness flipped when the coordinate
+    /// system's handedness changes
+    #[test]
+    fn single_indexed_tangents() {
+        let mut single_indexed = SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 2],
+            vertices: vec![
+                Vertex {
+                    position: [0.0, 1.0, 2.0],
+                    tangent: Some([1.0, 0.0, 0.0, 1.0]),
+                    face_tangent: Some([0.0, 1.0, 0.0]),
+                    ..Vertex::default()
+                };
+                3
+            ],
+            ..SingleIndexedVertexAttributes::default()
+        };
+
+        single_indexed.change_coordinate_system(CoordinateSystem::new(Axis::Y, Hand::Left));
+
+        let vertex = &single_indexed.vertices()[0];
+        assert_eq!(vertex.position(), [0.0, 2.0, 1.0]);
+        assert_eq!(vertex.face_tangent(), Some([0.0, 0.0, 1.0]));
+        assert_eq!(vertex.tangent(), Some([1.0, 0.0, 0.0, -1.0]));
+        assert_eq!(single_indexed.indices(), &vec![0, 2, 1]);
+    }
+
+    /// Verify that we leave vertex colors alone
+    #[test]
+    fn colors_unchanged() {
+        let mut mesh = BlenderMesh::default();
+        mesh.multi_indexed_vertex_attributes_mut()
+            .set_colors(Some(indexed((vec![0.0, 1.0, 2.0, 1.0], 4).into())));
+
+        mesh.y_up();
+
+        assert_eq!(
+            mesh.multi_indexed_vertex_attributes()
+                .colors()
+                .unwrap()
+                .attribute()
+                .data(),
+            &vec![0.0, 1.0, 2.0, 1.0]
+        );
+    }
+
+    /// Verify that we leave custom attributes alone
+    #[test]
+    fn custom_attributes_unchanged() {
+        let mut mesh = BlenderMesh::default();
+        mesh.multi_indexed_vertex_attributes_mut()
+            .insert_custom_attribute("wind".to_string(), indexed((vec![0.0, 1.0, 2.0], 3).into()));
+
+        mesh.y_up();
+
+        assert_eq!(
+            mesh.multi_indexed_vertex_attributes().custom_attributes()["wind"]
+                .attribute()
+                .data(),
+            &vec![0.0, 1.0, 2.0]
+        );
+    }
+}
@@ -18,6 +18,7 @@ impl BlenderMesh {
                 roughness: MaterialInput::Uniform(0.2),
                 metallic: MaterialInput::Uniform(0.3),
                 normal_map: None,
+                texture_uv_layers: HashMap::new(),
             },
         );
 
@@ -50,6 +51,7 @@ impl BlenderMesh {
             )),
             uvs: None,
             bone_influences: None,
+            ..MultiIndexedVertexAttributes::default()
         };
 
         Self {
@@ -61,7 +63,9 @@ impl BlenderMesh {
             },
             multi_indexed_vertex_attributes,
             materials,
+            material_slots: vec![Some("Default".to_string())],
             custom_properties: Default::default(),
+            coordinate_system: Default::default(),
         }
     }
 }
@@ -78,6 +82,8 @@ mod tests {
         mesh.combine_vertex_indices(&CreateSingleIndexConfig {
             bone_influences_per_vertex: None,
             calculate_face_tangents: false,
-        });
+            ..CreateSingleIndexConfig::default()
+        })
+        .unwrap();
     }
 }
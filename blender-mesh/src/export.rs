@@ -1,5 +1,4 @@
 use crate::BlenderMesh;
-use failure::Fail;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
@@ -124,9 +123,9 @@ fn validate_no_duplicates(
 
 /// An error when trying to flatten your exported data across multiple files into one HashMap of
 /// mesh name to mesh data.
-#[derive(Debug, Fail)]
+#[derive(Debug, thiserror::Error)]
 pub enum FlattenMeshError {
-    #[fail(display = "Duplicate meshes found: {:#?}", duplicates)]
+    #[error("Duplicate meshes found: {:#?}", duplicates)]
     DuplicateMeshNamesAcrossFiles {
         // HashMap<MeshName, Vec<FilesThatItAppearsIn>>
         duplicates: HashMap<String, Vec<String>>,
@@ -2,9 +2,10 @@ use crate::vertex_attributes::MultiIndexedVertexAttributes;
 use crate::BlenderMesh;
 
 /// Indicates an error while calculating the tangents for a mesh's verticies
-#[derive(Debug, Fail)]
+#[derive(Debug, thiserror::Error)]
 pub enum TangentError {
-    #[fail(display = "Cannot calculate vertex tangents for a mesh with no uvs")]
+    /// Tangents follow the direction that the uvs increase in.
+    #[error("Cannot calculate vertex tangents for a mesh with no uvs")]
     NoVertexUvs,
 }
 
@@ -31,10 +32,10 @@ impl BlenderMesh {
             ..
         } = multi;
 
-        if uvs.is_none() {
-            return Err(TangentError::NoVertexUvs)?;
-        }
-        let uvs = uvs.as_ref().unwrap();
+        let uvs = match uvs.as_ref() {
+            Some(uvs) => uvs,
+            None => return Err(TangentError::NoVertexUvs),
+        };
 
         let mut total_indices_processed = 0;
 
@@ -80,7 +81,7 @@ impl BlenderMesh {
             face_tangents.push(tangent_y);
             face_tangents.push(tangent_z);
 
-            total_indices_processed += vertices_in_face as u16;
+            total_indices_processed += vertices_in_face as u32;
         }
 
         Ok(face_tangents)
@@ -0,0 +1,902 @@
+//! Write a mesh, along with the armature that skins it, as [glTF 2.0].
+//!
+//! glTF is supported by most off the shelf viewers and engines, so this lets you load the same
+//! assets that you export with landon into tools that know nothing about landon's own JSON format.
+//!
+//! glTF uses a right handed Y up coordinate system, so you'll typically call
+//! [`BlenderMesh.change_coordinate_system`] and [`BlenderArmature.change_coordinate_system`]
+//! before writing your data.
+//!
+//! [glTF 2.0]: https://github.com/KhronosGroup/glTF/tree/master/specification/2.0
+//! [`BlenderMesh.change_coordinate_system`]: ../struct.BlenderMesh.html#method.change_coordinate_system
+//! [`BlenderArmature.change_coordinate_system`]: https://docs.rs/blender-armature
+
+use self::document::*;
+use crate::{BlenderMesh, SingleIndexedVertexAttributes};
+use blender_armature::BlenderArmature;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+
+mod document;
+mod glb;
+mod material;
+mod skin;
+
+/// An error while writing glTF
+#[derive(Debug, thiserror::Error)]
+pub enum GltfError {
+    /// glTF reads roughness from the green channel and metalness from the blue channel of a single
+    /// metallicRoughnessTexture, so other combinations of texture inputs can't be represented.
+    #[error(
+        "Material {material} needs roughness in the green and metallic in the blue channel of the same texture"
+    )]
+    UnsupportedMetallicRoughness {
+        /// The name of the material
+        material: String,
+    },
+    /// Every joint that has keyframes needs an inverse bind pose in order to be part of the skin.
+    #[error("Action {action} has keyframes for joint {joint} which has no inverse bind pose")]
+    JointWithoutInverseBindPose {
+        /// The name of the action
+        action: String,
+        /// The joint index
+        joint: u8,
+    },
+    #[allow(missing_docs)]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[allow(missing_docs)]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// Configuration for writing glTF
+#[derive(Debug)]
+pub struct GltfConfig<'a> {
+    /// The armature that skins the mesh.
+    ///
+    /// Its inverse bind poses become a glTF skin and each of its actions becomes a glTF animation.
+    pub armature: Option<&'a BlenderArmature>,
+    /// Used to convert keyframe numbers into the seconds that glTF animations are keyed by.
+    pub frames_per_second: u8,
+}
+
+impl<'a> Default for GltfConfig<'a> {
+    fn default() -> Self {
+        GltfConfig {
+            armature: None,
+            frames_per_second: 24,
+        }
+    }
+}
+
+/// A glTF document along with the binary buffer that its accessors point into.
+#[derive(Debug)]
+pub struct Gltf {
+    root: Root,
+    binary: Vec<u8>,
+}
+
+impl BlenderMesh {
+    /// Create a glTF document for this mesh.
+    ///
+    /// Each [`MaterialRange`] becomes a primitive that uses its material. The primitives share
+    /// POSITION, NORMAL, TANGENT, TEXCOORD_0, COLOR_0, JOINTS_n and WEIGHTS_n attributes
+    /// (whichever ones the vertices have).
+    ///
+    /// TEXCOORD_0 is the active UV layer, followed by the mesh's other UV layers sorted by name.
+    ///
+    /// Every material becomes a metallic roughness material. A mesh without material ranges
+    /// becomes a single primitive that uses the first material (sorted by name).
+    ///
+    /// [`MaterialRange`]: struct.MaterialRange.html
+    ///
+    /// Matrix bones are expected to be column major, see `BlenderArmature.transpose_actions`.
+    pub fn to_gltf(
+        &self,
+        single_indexed: &SingleIndexedVertexAttributes,
+        config: &GltfConfig,
+    ) -> Result<Gltf, GltfError> {
+        let mut builder = GltfBuilder {
+            tex_coords: tex_coords(single_indexed),
+            ..GltfBuilder::default()
+        };
+
+        let materials = builder.push_materials(&self.materials)?;
+
+        let attributes = builder.push_vertex_attributes(single_indexed);
+
+        let primitives = if single_indexed.material_ranges().is_empty() {
+            vec![Primitive {
+                attributes,
+                indices: builder.push_indices(single_indexed, 0..single_indexed.indices().len()),
+                material: materials.values().next().copied(),
+            }]
+        } else {
+            single_indexed
+                .material_ranges()
+                .iter()
+                .map(|range| Primitive {
+                    attributes: attributes.clone(),
+                    indices: builder.push_indices(single_indexed, range.indices()),
+                    material: range
+                        .material_name()
+                        .and_then(|name| materials.get(name))
+                        .copied(),
+                })
+                .collect()
+        };
+
+        builder.root.meshes.push(Mesh {
+            name: self.name.clone(),
+            primitives,
+        });
+
+        let mesh_node = builder.root.nodes.len() as u32;
+        builder.root.nodes.push(Node {
+            name: self.name.clone(),
+            mesh: Some(0),
+            ..Node::default()
+        });
+
+        let mut scene_nodes = vec![mesh_node];
+
+        if let Some(armature) = config.armature {
+            let skin = builder.push_skin(armature);
+            builder.root.nodes[mesh_node as usize].skin = Some(skin.skin);
+            builder.push_animations(armature, &skin.joint_nodes, config.frames_per_second)?;
+
+            scene_nodes.extend(skin.root_joint_nodes);
+        }
+
+        builder.root.scenes.push(Scene { nodes: scene_nodes });
+
+        Ok(builder.finish())
+    }
+}
+
+impl Gltf {
+    /// The JSON for a `.gltf` file whose binary data lives in an external file at `buffer_uri`.
+    pub fn to_json(&self, buffer_uri: &str) -> Result<String, GltfError> {
+        Ok(serde_json::to_string(
+            &self.root_with_buffer_uri(Some(buffer_uri.to_string())),
+        )?)
+    }
+
+    /// The binary data that the document's accessors point into.
+    ///
+    /// When writing a `.gltf` file this gets written to the `buffer_uri` that was passed to
+    /// [`Gltf.to_json`].
+    ///
+    /// [`Gltf.to_json`]: #method.to_json
+    pub fn binary_buffer(&self) -> &[u8] {
+        &self.binary
+    }
+
+    /// The bytes of a binary `.glb` file that holds both the JSON and the binary buffer.
+    pub fn to_glb(&self) -> Result<Vec<u8>, GltfError> {
+        let json = serde_json::to_string(&self.root_with_buffer_uri(None))?;
+
+        Ok(glb::encode(json.as_bytes(), &self.binary))
+    }
+
+    /// Write a `.gltf` file to the given path, along with a `.bin` file next to it that holds
+    /// the binary buffer.
+    pub fn write_gltf(&self, gltf_path: &Path) -> Result<(), GltfError> {
+        let bin_path = gltf_path.with_extension("bin");
+        let buffer_uri = bin_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        std::fs::write(gltf_path, self.to_json(&buffer_uri)?)?;
+        std::fs::write(bin_path, &self.binary)?;
+
+        Ok(())
+    }
+
+    /// Write a `.glb` file to the given path.
+    pub fn write_glb(&self, glb_path: &Path) -> Result<(), GltfError> {
+        std::fs::write(glb_path, self.to_glb()?)?;
+
+        Ok(())
+    }
+
+    // A `.glb` file's buffer has no uri since it is stored in the file's binary chunk.
+    fn root_with_buffer_uri(&self, uri: Option<String>) -> Root {
+        let mut root = self.root.clone();
+        root.buffers = vec![Buffer {
+            byte_length: self.binary.len(),
+            uri,
+        }];
+
+        root
+    }
+}
+
+/// Accumulates the glTF JSON and the binary buffer as we add data to the document.
+#[derive(Debug)]
+struct GltfBuilder {
+    root: Root,
+    binary: Vec<u8>,
+    /// The name of the UV layer behind each TEXCOORD_n attribute.
+    tex_coords: Vec<Option<String>>,
+}
+
+impl Default for GltfBuilder {
+    fn default() -> Self {
+        GltfBuilder {
+            root: Root {
+                asset: Asset::default(),
+                scene: 0,
+                scenes: vec![],
+                nodes: vec![],
+                meshes: vec![],
+                materials: vec![],
+                textures: vec![],
+                images: vec![],
+                accessors: vec![],
+                buffer_views: vec![],
+                buffers: vec![],
+                skins: vec![],
+                animations: vec![],
+            },
+            binary: vec![],
+            tex_coords: vec![],
+        }
+    }
+}
+
+impl GltfBuilder {
+    fn finish(self) -> Gltf {
+        Gltf {
+            root: self.root,
+            binary: self.binary,
+        }
+    }
+
+    /// Append data to the binary buffer and create an accessor that points to it.
+    ///
+    /// `with_bounds` should be true for accessors that glTF requires a min and max for, such as
+    /// POSITION attributes and animation sampler inputs.
+    fn push_accessor<C: Component>(
+        &mut self,
+        data: &[C],
+        accessor_type: AccessorType,
+        target: Option<u32>,
+        with_bounds: bool,
+    ) -> u32 {
+        // Buffer views must be aligned to their component size. Aligning everything to 4 bytes
+        // satisfies every component type.
+        while !self.binary.len().is_multiple_of(4) {
+            self.binary.push(0);
+        }
+
+        let byte_offset = self.binary.len();
+        for component in data {
+            component.write_le(&mut self.binary);
+        }
+
+        let buffer_view = self.root.buffer_views.len() as u32;
+        self.root.buffer_views.push(BufferView {
+            buffer: 0,
+            byte_offset,
+            byte_length: self.binary.len() - byte_offset,
+            target,
+        });
+
+        let components = accessor_type.components();
+        let (min, max) = if with_bounds {
+            let mut min = vec![f32::MAX; components];
+            let mut max = vec![f32::MIN; components];
+
+            for element in data.chunks(components) {
+                for (idx, component) in element.iter().enumerate() {
+                    min[idx] = min[idx].min(component.to_f32());
+                    max[idx] = max[idx].max(component.to_f32());
+                }
+            }
+
+            (Some(min), Some(max))
+        } else {
+            (None, None)
+        };
+
+        let accessor = self.root.accessors.len() as u32;
+        self.root.accessors.push(Accessor {
+            buffer_view,
+            component_type: C::COMPONENT_TYPE,
+            normalized: false,
+            count: data.len() / components,
+            accessor_type: accessor_type.as_str(),
+            min,
+            max,
+        });
+
+        accessor
+    }
+
+    fn push_vertex_attributes(
+        &mut self,
+        single_indexed: &SingleIndexedVertexAttributes,
+    ) -> BTreeMap<String, u32> {
+        let vertices = single_indexed.vertices();
+        let mut attributes: BTreeMap<String, u32> = BTreeMap::new();
+
+        let positions: Vec<f32> = vertices.iter().flat_map(|v| v.position.to_vec()).collect();
+        attributes.insert(
+            "POSITION".to_string(),
+            self.push_accessor(
+                &positions,
+                AccessorType::Vec3,
+                Some(TARGET_ARRAY_BUFFER),
+                true,
+            ),
+        );
+
+        if vertices.iter().all(|v| v.normal.is_some()) && !vertices.is_empty() {
+            let normals: Vec<f32> = vertices
+                .iter()
+                .flat_map(|v| normalize(v.normal.unwrap()).to_vec())
+                .collect();
+            attributes.insert(
+                "NORMAL".to_string(),
+                self.push_accessor(
+                    &normals,
+                    AccessorType::Vec3,
+                    Some(TARGET_ARRAY_BUFFER),
+                    false,
+                ),
+            );
+        }
+
+        let has_vertex_tangents = vertices.iter().all(|v| v.tangent.is_some());
+        let has_face_tangents = vertices.iter().all(|v| v.face_tangent.is_some());
+        if (has_vertex_tangents || has_face_tangents) && !vertices.is_empty() {
+            // glTF tangents have a w component that holds the bitangent's handedness. Face
+            // tangents don't know their handedness so we assume that it is positive.
+            let tangents: Vec<f32> = vertices
+                .iter()
+                .flat_map(|v| match v.tangent {
+                    Some(tangent) if has_vertex_tangents => tangent.to_vec(),
+                    _ => {
+                        let [x, y, z] = normalize(v.face_tangent.unwrap());
+                        vec![x, y, z, 1.0]
+                    }
+                })
+                .collect();
+            attributes.insert(
+                "TANGENT".to_string(),
+                self.push_accessor(
+                    &tangents,
+                    AccessorType::Vec4,
+                    Some(TARGET_ARRAY_BUFFER),
+                    false,
+                ),
+            );
+        }
+
+        if vertices.iter().all(|v| v.uv.is_some()) && !vertices.is_empty() {
+            // Blender's uv origin is the bottom left of the image, glTF's is the top left.
+            let uvs: Vec<f32> = vertices
+                .iter()
+                .flat_map(|v| {
+                    let [u, v] = v.uv.unwrap();
+                    vec![u, 1.0 - v]
+                })
+                .collect();
+            attributes.insert(
+                "TEXCOORD_0".to_string(),
+                self.push_accessor(&uvs, AccessorType::Vec2, Some(TARGET_ARRAY_BUFFER), false),
+            );
+        }
+
+        for (tex_coord, uv_layer) in self.tex_coords.clone().into_iter().enumerate().skip(1) {
+            let uv_layer = &single_indexed.uv_layers()[uv_layer.as_ref().unwrap()];
+            let uvs: Vec<f32> = uv_layer
+                .chunks(2)
+                .flat_map(|uv| vec![uv[0], 1.0 - uv[1]])
+                .collect();
+            attributes.insert(
+                format!("TEXCOORD_{}", tex_coord),
+                self.push_accessor(&uvs, AccessorType::Vec2, Some(TARGET_ARRAY_BUFFER), false),
+            );
+        }
+
+        if let Some(colors) = single_indexed.colors().filter(|_| !vertices.is_empty()) {
+            attributes.insert(
+                "COLOR_0".to_string(),
+                self.push_accessor(
+                    &colors.data,
+                    AccessorType::Vec4,
+                    Some(TARGET_ARRAY_BUFFER),
+                    false,
+                ),
+            );
+        }
+
+        if let Some(count) = single_indexed.bone_influences_per_vertex() {
+            // glTF stores four influences per JOINTS_n / WEIGHTS_n set, so the last set is padded
+            // with zero weights.
+            let sets = count.div_ceil(4);
+            let mut joints: Vec<Vec<u8>> = vec![vec![]; sets];
+            let mut weights: Vec<Vec<f32>> = vec![vec![]; sets];
+
+            for vertex in vertices {
+                let bones = vertex.bones().unwrap_or_default();
+
+                // glTF requires the weights of each vertex to sum to one across all of its sets.
+                let total: f32 = bones.iter().map(|b| b.weight).sum();
+                let total = if total > 0.0 { total } else { 1.0 };
+
+                for set in 0..sets {
+                    for bone in set * 4..set * 4 + 4 {
+                        let bone = bones.get(bone);
+                        joints[set].push(bone.map_or(0, |b| b.bone_idx));
+                        weights[set].push(bone.map_or(0.0, |b| b.weight / total));
+                    }
+                }
+            }
+
+            for (set, (joints, weights)) in joints.iter().zip(weights.iter()).enumerate() {
+                attributes.insert(
+                    format!("JOINTS_{}", set),
+                    self.push_accessor(
+                        joints,
+                        AccessorType::Vec4,
+                        Some(TARGET_ARRAY_BUFFER),
+                        false,
+                    ),
+                );
+                attributes.insert(
+                    format!("WEIGHTS_{}", set),
+                    self.push_accessor(
+                        weights,
+                        AccessorType::Vec4,
+                        Some(TARGET_ARRAY_BUFFER),
+                        false,
+                    ),
+                );
+            }
+        }
+
+        attributes
+    }
+
+    fn push_indices(
+        &mut self,
+        single_indexed: &SingleIndexedVertexAttributes,
+        range: Range<usize>,
+    ) -> u32 {
+        match single_indexed.u16_indices() {
+            Some(indices) => self.push_accessor(
+                &indices[range],
+                AccessorType::Scalar,
+                Some(TARGET_ELEMENT_ARRAY_BUFFER),
+                false,
+            ),
+            None => self.push_accessor(
+                &single_indexed.indices()[range],
+                AccessorType::Scalar,
+                Some(TARGET_ELEMENT_ARRAY_BUFFER),
+                false,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum AccessorType {
+    Scalar,
+    Vec2,
+    Vec3,
+    Vec4,
+    Mat4,
+}
+
+impl AccessorType {
+    fn components(&self) -> usize {
+        match self {
+            AccessorType::Scalar => 1,
+            AccessorType::Vec2 => 2,
+            AccessorType::Vec3 => 3,
+            AccessorType::Vec4 => 4,
+            AccessorType::Mat4 => 16,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            AccessorType::Scalar => "SCALAR",
+            AccessorType::Vec2 => "VEC2",
+            AccessorType::Vec3 => "VEC3",
+            AccessorType::Vec4 => "VEC4",
+            AccessorType::Mat4 => "MAT4",
+        }
+    }
+}
+
+/// A type that can be stored in an accessor
+trait Component: Copy {
+    const COMPONENT_TYPE: u32;
+
+    fn write_le(&self, buffer: &mut Vec<u8>);
+
+    fn to_f32(&self) -> f32;
+}
+
+impl Component for f32 {
+    const COMPONENT_TYPE: u32 = COMPONENT_TYPE_FLOAT;
+
+    fn write_le(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn to_f32(&self) -> f32 {
+        *self
+    }
+}
+
+impl Component for u16 {
+    const COMPONENT_TYPE: u32 = COMPONENT_TYPE_UNSIGNED_SHORT;
+
+    fn write_le(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn to_f32(&self) -> f32 {
+        *self as f32
+    }
+}
+
+impl Component for u32 {
+    const COMPONENT_TYPE: u32 = COMPONENT_TYPE_UNSIGNED_INT;
+
+    fn write_le(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn to_f32(&self) -> f32 {
+        *self as f32
+    }
+}
+
+impl Component for u8 {
+    const COMPONENT_TYPE: u32 = COMPONENT_TYPE_UNSIGNED_BYTE;
+
+    fn write_le(&self, buffer: &mut Vec<u8>) {
+        buffer.push(*self);
+    }
+
+    fn to_f32(&self) -> f32 {
+        *self as f32
+    }
+}
+
+/// The name of the UV layer behind each TEXCOORD_n attribute, starting with the active layer
+/// followed by the rest sorted by name.
+///
+/// If there is no active layer then we don't write any UV layers.
+fn tex_coords(single_indexed: &SingleIndexedVertexAttributes) -> Vec<Option<String>> {
+    let vertices = single_indexed.vertices();
+    if vertices.is_empty() || vertices.iter().any(|v| v.uv.is_none()) {
+        return vec![];
+    }
+
+    let mut other_layers: Vec<&String> = single_indexed.uv_layers().keys().collect();
+    other_layers.sort();
+
+    let mut tex_coords = vec![single_indexed.active_uv_layer().cloned()];
+    tex_coords.extend(other_layers.into_iter().map(|name| Some(name.clone())));
+
+    tex_coords
+}
+
+fn normalize([x, y, z]: [f32; 3]) -> [f32; 3] {
+    let length = (x * x + y * y + z * z).sqrt();
+    if length == 0.0 {
+        return [x, y, z];
+    }
+
+    [x / length, y / length, z / length]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoneInfluence, IndexFormat, MaterialInput, MaterialRange, PrincipledBSDF, Vertex};
+    use blender_armature::{Action, Bone, BoneKeyframe};
+    use nalgebra::Matrix4;
+    use serde_json::Value;
+
+    /// Verify that every vertex attribute becomes a primitive attribute with an accessor
+    #[test]
+    fn primitive_attributes() {
+        let mesh = mesh_with_material();
+        let gltf = mesh.to_gltf(&triangle(), &GltfConfig::default()).unwrap();
+        let json: Value = serde_json::from_str(&gltf.to_json("mesh.bin").unwrap()).unwrap();
+
+        let primitive = &json["meshes"][0]["primitives"][0];
+        for attribute in &[
+            "POSITION",
+            "NORMAL",
+            "TANGENT",
+            "TEXCOORD_0",
+            "COLOR_0",
+            "JOINTS_0",
+            "WEIGHTS_0",
+        ] {
+            let accessor =
+                &json["accessors"][primitive["attributes"][attribute].as_u64().unwrap() as usize];
+            assert_eq!(accessor["count"], 3, "{}", attribute);
+        }
+
+        let position =
+            &json["accessors"][primitive["attributes"]["POSITION"].as_u64().unwrap() as usize];
+        assert_eq!(position["min"], serde_json::json!([0.0, 0.0, 0.0]));
+        assert_eq!(position["max"], serde_json::json!([1.0, 1.0, 0.0]));
+
+        let indices = &json["accessors"][primitive["indices"].as_u64().unwrap() as usize];
+        assert_eq!(indices["count"], 3);
+        assert_eq!(indices["componentType"], COMPONENT_TYPE_UNSIGNED_SHORT);
+
+        assert_eq!(primitive["material"], 0);
+        assert_eq!(json["materials"][0]["name"], "Material");
+        assert_eq!(json["buffers"][0]["uri"], "mesh.bin");
+        assert_eq!(json["buffers"][0]["byteLength"], gltf.binary_buffer().len());
+    }
+
+    /// Verify that meshes with u32 indices get an unsigned int indices accessor
+    #[test]
+    fn u32_indices() {
+        let mesh = mesh_with_material();
+        let single_indexed = SingleIndexedVertexAttributes {
+            index_format: IndexFormat::U32,
+            ..triangle()
+        };
+
+        let gltf = mesh
+            .to_gltf(&single_indexed, &GltfConfig::default())
+            .unwrap();
+        let json: Value = serde_json::from_str(&gltf.to_json("mesh.bin").unwrap()).unwrap();
+
+        let primitive = &json["meshes"][0]["primitives"][0];
+        let indices = &json["accessors"][primitive["indices"].as_u64().unwrap() as usize];
+        assert_eq!(indices["componentType"], COMPONENT_TYPE_UNSIGNED_INT);
+    }
+
+    /// Verify that each material range becomes a primitive that uses its material and shares the
+    /// vertex attributes
+    #[test]
+    fn primitive_per_material_range() {
+        let mut mesh = mesh_with_material();
+        mesh.materials
+            .insert("Blue".to_string(), mesh.materials["Material"].clone());
+
+        let mut single_indexed = triangle();
+        single_indexed.indices = vec![0, 1, 2, 2, 1, 0];
+        single_indexed.material_ranges = vec![
+            MaterialRange {
+                material_index: 0,
+                material_name: Some("Material".to_string()),
+                indices: 0..3,
+            },
+            MaterialRange {
+                material_index: 1,
+                material_name: Some("Blue".to_string()),
+                indices: 3..6,
+            },
+        ];
+
+        let gltf = mesh
+            .to_gltf(&single_indexed, &GltfConfig::default())
+            .unwrap();
+        let json: Value = serde_json::from_str(&gltf.to_json("mesh.bin").unwrap()).unwrap();
+
+        let primitives = json["meshes"][0]["primitives"].as_array().unwrap();
+        assert_eq!(primitives.len(), 2);
+        assert_eq!(primitives[0]["attributes"], primitives[1]["attributes"]);
+
+        // Materials are sorted by name
+        assert_eq!(primitives[0]["material"], 1);
+        assert_eq!(primitives[1]["material"], 0);
+
+        for primitive in primitives {
+            let indices = &json["accessors"][primitive["indices"].as_u64().unwrap() as usize];
+            assert_eq!(indices["count"], 3);
+        }
+    }
+
+    /// Verify that keyframes are applied on top of each joint's rest pose, so an identity
+    /// keyframe animates the joint to its rest translation
+    #[test]
+    fn identity_keyframe_keeps_rest_pose() {
+        let mesh = mesh_with_material();
+        let armature = armature();
+
+        let config = GltfConfig {
+            armature: Some(&armature),
+            ..GltfConfig::default()
+        };
+        let gltf = mesh.to_gltf(&triangle(), &config).unwrap();
+        let json: Value = serde_json::from_str(&gltf.to_json("mesh.bin").unwrap()).unwrap();
+
+        assert_eq!(json["skins"][0]["joints"], serde_json::json!([1, 2]));
+        assert_eq!(
+            json["nodes"][2]["translation"],
+            serde_json::json!([0.0, 2.0, 0.0])
+        );
+
+        let animation = &json["animations"][0];
+        assert_eq!(animation["channels"][0]["target"]["node"], 2);
+        assert_eq!(animation["channels"][0]["target"]["path"], "translation");
+
+        let translations = accessor_f32s(
+            &json,
+            gltf.binary_buffer(),
+            animation["samplers"][0]["output"].as_u64().unwrap(),
+        );
+        assert_eq!(translations, vec![0.0, 2.0, 0.0, 0.0, 2.0, 0.0]);
+    }
+
+    /// Verify that we return an error if an action animates a joint that isn't in the skin
+    #[test]
+    fn error_if_action_joint_not_in_skin() {
+        let mesh = mesh_with_material();
+
+        let mut armature = armature();
+        let mut action = Action::new();
+        action.insert_bone_keyframe(5, BoneKeyframe::new(1, Bone::Matrix(Matrix4::identity())));
+        armature.insert_bone_space_action("Broken".to_string(), action);
+
+        let config = GltfConfig {
+            armature: Some(&armature),
+            ..GltfConfig::default()
+        };
+
+        match mesh.to_gltf(&triangle(), &config) {
+            Err(GltfError::JointWithoutInverseBindPose { action, joint }) => {
+                assert_eq!(action, "Broken");
+                assert_eq!(joint, 5);
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    /// Verify that other UV layers become TEXCOORD_n attributes that textures can sample from
+    #[test]
+    fn uv_layers() {
+        let mut mesh = mesh_with_material();
+        let mut material = PrincipledBSDF::new(
+            MaterialInput::ImageTexture("lightmap.png".to_string()),
+            MaterialInput::Uniform(0.5),
+            MaterialInput::Uniform(0.0),
+            None,
+        );
+        material.set_texture_uv_layer("lightmap.png".to_string(), "Lightmap".to_string());
+        mesh.materials.insert("Material".to_string(), material);
+
+        let mut single_indexed = triangle();
+        single_indexed.active_uv_layer = Some("Texture".to_string());
+        single_indexed.uv_layers.insert(
+            "Lightmap".to_string(),
+            (vec![0.0, 0.0, 0.5, 0.0, 0.0, 0.5], 2).into(),
+        );
+
+        let gltf = mesh
+            .to_gltf(&single_indexed, &GltfConfig::default())
+            .unwrap();
+        let json: Value = serde_json::from_str(&gltf.to_json("mesh.bin").unwrap()).unwrap();
+
+        let primitive = &json["meshes"][0]["primitives"][0];
+        assert!(primitive["attributes"]["TEXCOORD_1"].is_u64());
+
+        let base_color_texture = &json["materials"][0]["pbrMetallicRoughness"]["baseColorTexture"];
+        assert_eq!(base_color_texture["texCoord"], 1);
+    }
+
+    /// Verify that the glb holds the JSON chunk followed by the binary buffer
+    #[test]
+    fn glb_holds_json_and_binary() {
+        let mesh = mesh_with_material();
+        let gltf = mesh.to_gltf(&triangle(), &GltfConfig::default()).unwrap();
+
+        let glb = gltf.to_glb().unwrap();
+
+        assert_eq!(&glb[0..4], b"glTF");
+        let binary = gltf.binary_buffer();
+        assert!(glb.windows(binary.len()).any(|chunk| chunk == binary));
+    }
+
+    fn mesh_with_material() -> BlenderMesh {
+        let mut mesh = BlenderMesh {
+            name: "Triangle".to_string(),
+            ..BlenderMesh::default()
+        };
+        mesh.materials.insert(
+            "Material".to_string(),
+            PrincipledBSDF::new(
+                MaterialInput::Uniform([1.0, 0.0, 0.0]),
+                MaterialInput::Uniform(0.5),
+                MaterialInput::Uniform(0.0),
+                None,
+            ),
+        );
+
+        mesh
+    }
+
+    fn triangle() -> SingleIndexedVertexAttributes {
+        let vertex = |position: [f32; 3], uv: [f32; 2]| Vertex {
+            position,
+            normal: Some([0.0, 0.0, 2.0]),
+            face_tangent: Some([1.0, 0.0, 0.0]),
+            tangent: None,
+            uv: Some(uv),
+            color: Some([1.0, 0.5, 0.25, 1.0]),
+            bones: Some(vec![
+                BoneInfluence {
+                    bone_idx: 0,
+                    weight: 0.5,
+                },
+                BoneInfluence {
+                    bone_idx: 1,
+                    weight: 0.5,
+                },
+                BoneInfluence {
+                    bone_idx: 0,
+                    weight: 0.0,
+                },
+                BoneInfluence {
+                    bone_idx: 0,
+                    weight: 0.0,
+                },
+            ]),
+        };
+
+        SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 2],
+            vertices: vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [1.0, 0.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 1.0]),
+            ],
+            ..SingleIndexedVertexAttributes::default()
+        }
+    }
+
+    // The floats in the buffer view that an accessor points to.
+    fn accessor_f32s(json: &Value, binary: &[u8], accessor: u64) -> Vec<f32> {
+        let accessor = &json["accessors"][accessor as usize];
+        let buffer_view = &json["bufferViews"][accessor["bufferView"].as_u64().unwrap() as usize];
+
+        let start = buffer_view["byteOffset"].as_u64().unwrap() as usize;
+        let end = start + buffer_view["byteLength"].as_u64().unwrap() as usize;
+
+        binary[start..end]
+            .chunks(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect()
+    }
+
+    // A root joint at the origin with a child joint two units above it.
+    fn armature() -> BlenderArmature {
+        let mut armature = BlenderArmature::default();
+        armature.set_name("Armature".to_string());
+        armature.insert_joint_index("Root".to_string(), 0);
+        armature.insert_joint_index("Child".to_string(), 1);
+        armature.insert_child_to_parent(1, 0);
+        armature.set_inverse_bind_poses(vec![
+            Bone::Matrix(Matrix4::identity()),
+            Bone::Matrix(Matrix4::new_translation(&[0.0, -2.0, 0.0].into())),
+        ]);
+
+        let mut action = Action::new();
+        action.insert_bone_keyframe(1, BoneKeyframe::new(10, Bone::Matrix(Matrix4::identity())));
+        action.insert_bone_keyframe(1, BoneKeyframe::new(34, Bone::Matrix(Matrix4::identity())));
+        armature.insert_bone_space_action("Wave".to_string(), action);
+
+        armature
+    }
+}
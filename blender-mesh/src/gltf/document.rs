@@ -0,0 +1,195 @@
+//! The subset of the glTF 2.0 JSON schema that we write.
+//!
+//! @see https://github.com/KhronosGroup/glTF/tree/master/specification/2.0
+
+use std::collections::BTreeMap;
+
+pub(super) const COMPONENT_TYPE_UNSIGNED_BYTE: u32 = 5121;
+pub(super) const COMPONENT_TYPE_UNSIGNED_SHORT: u32 = 5123;
+pub(super) const COMPONENT_TYPE_UNSIGNED_INT: u32 = 5125;
+pub(super) const COMPONENT_TYPE_FLOAT: u32 = 5126;
+
+pub(super) const TARGET_ARRAY_BUFFER: u32 = 34962;
+pub(super) const TARGET_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Root {
+    pub asset: Asset,
+    pub scene: u32,
+    pub scenes: Vec<Scene>,
+    pub nodes: Vec<Node>,
+    pub meshes: Vec<Mesh>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub materials: Vec<Material>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub textures: Vec<Texture>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<Image>,
+    pub accessors: Vec<Accessor>,
+    pub buffer_views: Vec<BufferView>,
+    pub buffers: Vec<Buffer>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skins: Vec<Skin>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub animations: Vec<Animation>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct Asset {
+    pub version: &'static str,
+    pub generator: &'static str,
+}
+
+impl Default for Asset {
+    fn default() -> Self {
+        Asset {
+            version: "2.0",
+            generator: "landon",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub(super) struct Scene {
+    pub nodes: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub(super) struct Node {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mesh: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skin: Option<u32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub translation: Option<[f32; 3]>,
+    /// [x, y, z, w]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<[f32; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<[f32; 3]>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct Mesh {
+    pub name: String,
+    pub primitives: Vec<Primitive>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct Primitive {
+    pub attributes: BTreeMap<String, u32>,
+    pub indices: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub material: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Material {
+    pub name: String,
+    pub pbr_metallic_roughness: PbrMetallicRoughness,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal_texture: Option<TextureInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct PbrMetallicRoughness {
+    pub base_color_factor: [f32; 4],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_color_texture: Option<TextureInfo>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metallic_roughness_texture: Option<TextureInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct TextureInfo {
+    pub index: u32,
+    /// The TEXCOORD_n attribute to sample with. Defaults to 0.
+    #[serde(rename = "texCoord", skip_serializing_if = "Option::is_none")]
+    pub tex_coord: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct Texture {
+    pub source: u32,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct Image {
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Accessor {
+    pub buffer_view: u32,
+    pub component_type: u32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub normalized: bool,
+    pub count: usize,
+    #[serde(rename = "type")]
+    pub accessor_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct BufferView {
+    pub buffer: u32,
+    pub byte_offset: usize,
+    pub byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Buffer {
+    pub byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct Skin {
+    pub name: String,
+    pub inverse_bind_matrices: u32,
+    pub joints: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct Animation {
+    pub name: String,
+    pub channels: Vec<AnimationChannel>,
+    pub samplers: Vec<AnimationSampler>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct AnimationChannel {
+    pub sampler: u32,
+    pub target: AnimationTarget,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct AnimationTarget {
+    pub node: u32,
+    pub path: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(super) struct AnimationSampler {
+    pub input: u32,
+    pub output: u32,
+    pub interpolation: &'static str,
+}
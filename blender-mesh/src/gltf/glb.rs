@@ -0,0 +1,80 @@
+//! The binary glTF container.
+//!
+//! @see https://github.com/KhronosGroup/glTF/tree/master/specification/2.0#glb-file-format-specification
+
+const MAGIC: u32 = 0x4654_6C67;
+const VERSION: u32 = 2;
+const CHUNK_TYPE_JSON: u32 = 0x4E4F_534A;
+const CHUNK_TYPE_BIN: u32 = 0x004E_4942;
+
+const HEADER_BYTES: usize = 12;
+const CHUNK_HEADER_BYTES: usize = 8;
+
+/// Encode the JSON and binary buffer into a `.glb` file.
+///
+/// Both chunks get padded to a multiple of four bytes. The JSON with trailing spaces and the
+/// binary buffer with trailing zeros.
+pub(super) fn encode(json: &[u8], binary: &[u8]) -> Vec<u8> {
+    let json_padding = padding(json.len());
+    let binary_padding = padding(binary.len());
+
+    let mut total_length = HEADER_BYTES + CHUNK_HEADER_BYTES + json.len() + json_padding;
+    if !binary.is_empty() {
+        total_length += CHUNK_HEADER_BYTES + binary.len() + binary_padding;
+    }
+
+    let mut glb = Vec::with_capacity(total_length);
+
+    glb.extend_from_slice(&MAGIC.to_le_bytes());
+    glb.extend_from_slice(&VERSION.to_le_bytes());
+    glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+
+    glb.extend_from_slice(&((json.len() + json_padding) as u32).to_le_bytes());
+    glb.extend_from_slice(&CHUNK_TYPE_JSON.to_le_bytes());
+    glb.extend_from_slice(json);
+    glb.resize(glb.len() + json_padding, b' ');
+
+    if !binary.is_empty() {
+        glb.extend_from_slice(&((binary.len() + binary_padding) as u32).to_le_bytes());
+        glb.extend_from_slice(&CHUNK_TYPE_BIN.to_le_bytes());
+        glb.extend_from_slice(binary);
+        glb.resize(glb.len() + binary_padding, 0);
+    }
+
+    glb
+}
+
+fn padding(len: usize) -> usize {
+    (4 - len % 4) % 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], idx: usize) -> u32 {
+        let mut word = [0; 4];
+        word.copy_from_slice(&bytes[idx..idx + 4]);
+        u32::from_le_bytes(word)
+    }
+
+    /// Verify that we write the header and pad both chunks to four bytes
+    #[test]
+    fn header_and_padded_chunks() {
+        let glb = encode(b"{}", &[1, 2, 3, 4, 5]);
+
+        assert_eq!(u32_at(&glb, 0), MAGIC);
+        assert_eq!(u32_at(&glb, 4), VERSION);
+        assert_eq!(u32_at(&glb, 8) as usize, glb.len());
+
+        assert_eq!(u32_at(&glb, 12), 4);
+        assert_eq!(u32_at(&glb, 16), CHUNK_TYPE_JSON);
+        assert_eq!(&glb[20..24], b"{}  ");
+
+        assert_eq!(u32_at(&glb, 24), 8);
+        assert_eq!(u32_at(&glb, 28), CHUNK_TYPE_BIN);
+        assert_eq!(&glb[32..40], &[1, 2, 3, 4, 5, 0, 0, 0]);
+
+        assert_eq!(glb.len(), 40);
+    }
+}
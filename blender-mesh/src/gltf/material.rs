@@ -0,0 +1,198 @@
+use super::document::{Material, PbrMetallicRoughness, Texture, TextureInfo};
+use super::{GltfBuilder, GltfError};
+use crate::{Channel, MaterialInput, PrincipledBSDF};
+use std::collections::{BTreeMap, HashMap};
+
+impl GltfBuilder {
+    /// Add every material to the document, sorted by name so that the output is deterministic.
+    ///
+    /// Returns the index of each material in the same order.
+    pub(super) fn push_materials(
+        &mut self,
+        materials: &HashMap<String, PrincipledBSDF>,
+    ) -> Result<BTreeMap<String, u32>, GltfError> {
+        let materials: BTreeMap<_, _> = materials.iter().collect();
+
+        let mut indices = BTreeMap::new();
+        for (name, material) in materials {
+            indices.insert(name.to_string(), self.push_material(name, material)?);
+        }
+
+        Ok(indices)
+    }
+
+    fn push_material(&mut self, name: &str, material: &PrincipledBSDF) -> Result<u32, GltfError> {
+        let (base_color_factor, base_color_texture) = match material.base_color() {
+            MaterialInput::Uniform([r, g, b]) => ([*r, *g, *b, 1.0], None),
+            MaterialInput::ImageTexture(image) => (
+                [1.0, 1.0, 1.0, 1.0],
+                Some(self.texture_info(image, material)),
+            ),
+        };
+
+        let (metallic_factor, roughness_factor, metallic_roughness_texture) =
+            match (material.metallic(), material.roughness()) {
+                (MaterialInput::Uniform(metallic), MaterialInput::Uniform(roughness)) => {
+                    (*metallic, *roughness, None)
+                }
+                (
+                    MaterialInput::ImageTexture((metallic_image, Channel::Blue)),
+                    MaterialInput::ImageTexture((roughness_image, Channel::Green)),
+                ) if metallic_image == roughness_image => {
+                    (1.0, 1.0, Some(self.texture_info(metallic_image, material)))
+                }
+                _ => {
+                    return Err(GltfError::UnsupportedMetallicRoughness {
+                        material: name.to_string(),
+                    })
+                }
+            };
+
+        let normal_texture = material
+            .normal_map()
+            .map(|image| self.texture_info(image, material));
+
+        let idx = self.root.materials.len() as u32;
+        self.root.materials.push(Material {
+            name: name.to_string(),
+            pbr_metallic_roughness: PbrMetallicRoughness {
+                base_color_factor,
+                base_color_texture,
+                metallic_factor,
+                roughness_factor,
+                metallic_roughness_texture,
+            },
+            normal_texture,
+        });
+
+        Ok(idx)
+    }
+
+    /// Re-use the texture for an image if we've already added one.
+    ///
+    /// Textures that sample from one of the mesh's other UV layers point to that layer's
+    /// TEXCOORD_n attribute.
+    fn texture_info(&mut self, image: &str, material: &PrincipledBSDF) -> TextureInfo {
+        let existing = self
+            .root
+            .images
+            .iter()
+            .position(|existing| existing.uri == image);
+
+        let source = match existing {
+            Some(source) => source as u32,
+            None => {
+                self.root.images.push(super::document::Image {
+                    uri: image.to_string(),
+                });
+                self.root.images.len() as u32 - 1
+            }
+        };
+
+        let texture = match self.root.textures.iter().position(|t| t.source == source) {
+            Some(texture) => texture as u32,
+            None => {
+                self.root.textures.push(Texture { source });
+                self.root.textures.len() as u32 - 1
+            }
+        };
+
+        let tex_coord = material.texture_uv_layer(image).and_then(|uv_layer| {
+            self.tex_coords
+                .iter()
+                .position(|tex_coord| tex_coord.as_ref() == Some(uv_layer))
+                .filter(|tex_coord| *tex_coord > 0)
+                .map(|tex_coord| tex_coord as u32)
+        });
+
+        TextureInfo {
+            index: texture,
+            tex_coord,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that uniform inputs become factors
+    #[test]
+    fn uniform_inputs() {
+        let mut builder = GltfBuilder::default();
+
+        let material = PrincipledBSDF::new(
+            MaterialInput::Uniform([0.1, 0.2, 0.3]),
+            MaterialInput::Uniform(0.4),
+            MaterialInput::Uniform(0.5),
+            None,
+        );
+        builder.push_material("Mat", &material).unwrap();
+
+        let pbr = &builder.root.materials[0].pbr_metallic_roughness;
+        assert_eq!(pbr.base_color_factor, [0.1, 0.2, 0.3, 1.0]);
+        assert_eq!(pbr.roughness_factor, 0.4);
+        assert_eq!(pbr.metallic_factor, 0.5);
+        assert!(builder.root.textures.is_empty());
+    }
+
+    /// Verify that the metallic and roughness channels of the same image share one texture
+    #[test]
+    fn metallic_roughness_texture() {
+        let mut builder = GltfBuilder::default();
+
+        let material = PrincipledBSDF::new(
+            MaterialInput::ImageTexture("color.png".to_string()),
+            MaterialInput::ImageTexture(("mr.png".to_string(), Channel::Green)),
+            MaterialInput::ImageTexture(("mr.png".to_string(), Channel::Blue)),
+            Some("normal.png".to_string()),
+        );
+        builder.push_material("Mat", &material).unwrap();
+
+        let gltf_material = &builder.root.materials[0];
+        assert_eq!(
+            gltf_material.pbr_metallic_roughness.base_color_texture,
+            Some(TextureInfo {
+                index: 0,
+                tex_coord: None
+            })
+        );
+        assert_eq!(
+            gltf_material
+                .pbr_metallic_roughness
+                .metallic_roughness_texture,
+            Some(TextureInfo {
+                index: 1,
+                tex_coord: None
+            })
+        );
+        assert_eq!(
+            gltf_material.normal_texture,
+            Some(TextureInfo {
+                index: 2,
+                tex_coord: None
+            })
+        );
+        assert_eq!(builder.root.images.len(), 3);
+    }
+
+    /// glTF can only read roughness from the green channel
+    #[test]
+    fn error_if_roughness_not_in_green_channel() {
+        let mut builder = GltfBuilder::default();
+
+        let material = PrincipledBSDF::new(
+            MaterialInput::Uniform([0.1, 0.2, 0.3]),
+            MaterialInput::ImageTexture(("mr.png".to_string(), Channel::Red)),
+            MaterialInput::ImageTexture(("mr.png".to_string(), Channel::Blue)),
+            None,
+        );
+
+        match builder.push_material("Mat", &material) {
+            Err(GltfError::UnsupportedMetallicRoughness { material }) => {
+                assert_eq!(material, "Mat")
+            }
+            _ => unreachable!(),
+        };
+    }
+}
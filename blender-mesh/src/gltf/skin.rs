@@ -0,0 +1,230 @@
+use super::document::{Animation, AnimationChannel, AnimationSampler, AnimationTarget, Node, Skin};
+use super::{AccessorType, GltfBuilder, GltfError};
+use blender_armature::{BlenderArmature, Bone, Trs};
+use nalgebra::Matrix4;
+use std::collections::{BTreeMap, HashMap};
+
+/// The nodes that were created for an armature's skin.
+pub(super) struct PushedSkin {
+    /// The index of the skin
+    pub skin: u32,
+    /// The node for each joint, indexed by joint index
+    pub joint_nodes: Vec<u32>,
+    /// The joint nodes that have no parent. These get added to the scene.
+    pub root_joint_nodes: Vec<u32>,
+}
+
+impl GltfBuilder {
+    /// Add a node for every joint that has an inverse bind pose along with the skin that uses them.
+    ///
+    /// Each node starts off in its rest pose relative to its parent.
+    pub(super) fn push_skin(&mut self, armature: &BlenderArmature) -> PushedSkin {
+        let inverse_bind_poses = armature.inverse_bind_poses();
+        let parents = armature.bone_child_to_parent();
+
+        let joint_names: HashMap<u8, &String> = armature
+            .joint_indices()
+            .iter()
+            .map(|(name, idx)| (*idx, name))
+            .collect();
+
+        let first_joint_node = self.root.nodes.len() as u32;
+        let joint_nodes: Vec<u32> = (0..inverse_bind_poses.len() as u32)
+            .map(|joint| first_joint_node + joint)
+            .collect();
+
+        for joint in 0..inverse_bind_poses.len() {
+            let joint_idx = joint as u8;
+
+            let parent = parents
+                .get(&joint_idx)
+                .filter(|parent| (**parent as usize) < inverse_bind_poses.len());
+
+            let rest = rest_local_matrix(inverse_bind_poses, joint_idx, parent.copied());
+            let Trs {
+                translation,
+                rotation,
+                scale,
+            } = Bone::Matrix(rest).to_trs();
+
+            let mut children: Vec<u32> = parents
+                .iter()
+                .filter(|(_, parent)| **parent == joint_idx)
+                .map(|(child, _)| *child)
+                .filter(|child| (*child as usize) < inverse_bind_poses.len())
+                .map(|child| joint_nodes[child as usize])
+                .collect();
+            children.sort();
+
+            self.root.nodes.push(Node {
+                name: joint_names
+                    .get(&joint_idx)
+                    .map(|name| name.to_string())
+                    .unwrap_or(format!("Joint{}", joint_idx)),
+                children,
+                translation: Some([translation.x, translation.y, translation.z]),
+                rotation: Some(quaternion_xyzw(&rotation)),
+                scale: Some([scale.x, scale.y, scale.z]),
+                ..Node::default()
+            });
+        }
+
+        let root_joint_nodes = (0..inverse_bind_poses.len())
+            .filter(|joint| {
+                parents
+                    .get(&(*joint as u8))
+                    .filter(|parent| (**parent as usize) < inverse_bind_poses.len())
+                    .is_none()
+            })
+            .map(|joint| joint_nodes[joint])
+            .collect();
+
+        let inverse_bind_matrices: Vec<f32> = inverse_bind_poses
+            .iter()
+            .flat_map(|bone| bone.to_matrix().as_slice().to_vec())
+            .collect();
+        let inverse_bind_matrices =
+            self.push_accessor(&inverse_bind_matrices, AccessorType::Mat4, None, false);
+
+        let skin = self.root.skins.len() as u32;
+        self.root.skins.push(Skin {
+            name: armature.name().to_string(),
+            inverse_bind_matrices,
+            joints: joint_nodes.clone(),
+        });
+
+        PushedSkin {
+            skin,
+            joint_nodes,
+            root_joint_nodes,
+        }
+    }
+
+    /// Add an animation for each of the armature's bone space actions, sorted by name.
+    ///
+    /// Every joint with keyframes gets a translation, rotation and scale channel.
+    ///
+    /// Keyframes are relative to the joint's rest pose, but glTF channels replace the node's
+    /// local transform, so each keyframe is applied on top of the rest pose before it is written.
+    pub(super) fn push_animations(
+        &mut self,
+        armature: &BlenderArmature,
+        joint_nodes: &[u32],
+        frames_per_second: u8,
+    ) -> Result<(), GltfError> {
+        let actions: BTreeMap<_, _> = armature.bone_space_actions().iter().collect();
+
+        let inverse_bind_poses = armature.inverse_bind_poses();
+        let parents = armature.bone_child_to_parent();
+
+        for (name, action) in actions {
+            let bone_keyframes = action.bone_keyframes();
+            let first_frame = bone_keyframes.smallest_frame().unwrap_or(0);
+
+            let mut joints: Vec<&u8> = bone_keyframes.keys().collect();
+            joints.sort();
+
+            let mut channels = vec![];
+            let mut samplers = vec![];
+
+            for joint in joints {
+                let node = *joint_nodes.get(*joint as usize).ok_or_else(|| {
+                    GltfError::JointWithoutInverseBindPose {
+                        action: name.to_string(),
+                        joint: *joint,
+                    }
+                })?;
+
+                let keyframes = &bone_keyframes[joint];
+                if keyframes.is_empty() {
+                    continue;
+                }
+
+                let times: Vec<f32> = keyframes
+                    .iter()
+                    .map(|k| (k.frame() - first_frame) as f32 / frames_per_second as f32)
+                    .collect();
+                let input = self.push_accessor(&times, AccessorType::Scalar, None, true);
+
+                let parent = parents
+                    .get(joint)
+                    .filter(|parent| (**parent as usize) < inverse_bind_poses.len());
+                let rest = rest_local_matrix(inverse_bind_poses, *joint, parent.copied());
+
+                let poses: Vec<Trs> = keyframes
+                    .iter()
+                    .map(|k| Trs::from_matrix(&(rest * k.bone().to_matrix())))
+                    .collect();
+
+                let translations: Vec<f32> = poses
+                    .iter()
+                    .flat_map(|pose| pose.translation.as_slice().to_vec())
+                    .collect();
+                let rotations: Vec<f32> = poses
+                    .iter()
+                    .flat_map(|pose| quaternion_xyzw(&pose.rotation).to_vec())
+                    .collect();
+                let scales: Vec<f32> = poses
+                    .iter()
+                    .flat_map(|pose| pose.scale.as_slice().to_vec())
+                    .collect();
+
+                let outputs = [
+                    (
+                        "translation",
+                        self.push_accessor(&translations, AccessorType::Vec3, None, false),
+                    ),
+                    (
+                        "rotation",
+                        self.push_accessor(&rotations, AccessorType::Vec4, None, false),
+                    ),
+                    (
+                        "scale",
+                        self.push_accessor(&scales, AccessorType::Vec3, None, false),
+                    ),
+                ];
+
+                for (path, output) in &outputs {
+                    channels.push(AnimationChannel {
+                        sampler: samplers.len() as u32,
+                        target: AnimationTarget { node, path },
+                    });
+                    samplers.push(AnimationSampler {
+                        input,
+                        output: *output,
+                        interpolation: "LINEAR",
+                    });
+                }
+            }
+
+            self.root.animations.push(Animation {
+                name: name.to_string(),
+                channels,
+                samplers,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// A joint's rest pose relative to its parent.
+///
+/// The bind pose is the inverse of the inverse bind pose, so the local rest pose is the parent's
+/// inverse bind pose times the joint's bind pose.
+fn rest_local_matrix(inverse_bind_poses: &[Bone], joint: u8, parent: Option<u8>) -> Matrix4<f32> {
+    let bind = inverse_bind_poses[joint as usize]
+        .to_matrix()
+        .try_inverse()
+        .unwrap_or(Matrix4::identity());
+
+    match parent {
+        Some(parent) => inverse_bind_poses[parent as usize].to_matrix() * bind,
+        None => bind,
+    }
+}
+
+fn quaternion_xyzw(rotation: &nalgebra::UnitQuaternion<f32>) -> [f32; 4] {
+    let coords = rotation.quaternion().coords;
+    [coords.x, coords.y, coords.z, coords.w]
+}
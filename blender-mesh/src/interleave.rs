@@ -1,7 +1,779 @@
-use crate::BlenderMesh;
+//! Pack vertices into a single buffer that can be uploaded to the GPU, with a configurable
+//! layout and quantized formats for each attribute.
 
-impl BlenderMesh {
-    pub fn interleave_vertex_data(&self) -> Vec<u8> {
-        unimplemented!()
+use crate::{SingleIndexedVertexAttributes, VertexAttribute};
+
+/// Describes how each vertex is packed into an interleaved vertex buffer.
+///
+/// ```
+/// use blender_mesh::{AttributeSource, LayoutAttribute, VertexFormat, VertexLayout};
+///
+/// let layout = VertexLayout {
+///     attributes: vec![
+///         LayoutAttribute::new(AttributeSource::Position, VertexFormat::Float32),
+///         LayoutAttribute::new(AttributeSource::Normal, VertexFormat::Octahedral16),
+///         LayoutAttribute::new(AttributeSource::Uv, VertexFormat::Float16),
+///         LayoutAttribute::new(AttributeSource::Joints, VertexFormat::Uint8),
+///         LayoutAttribute::new(AttributeSource::Weights, VertexFormat::Unorm8),
+///     ],
+///     ..VertexLayout::default()
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexLayout {
+    /// The attributes in the order that they appear within each vertex.
+    pub attributes: Vec<LayoutAttribute>,
+    /// The offset of every attribute, as well as the stride, is rounded up to a multiple of this
+    /// many bytes. Must be a power of two.
+    ///
+    /// Defaults to 4, which is what WebGPU and most GPUs expect.
+    pub alignment: usize,
+}
+
+impl Default for VertexLayout {
+    fn default() -> Self {
+        VertexLayout {
+            attributes: vec![],
+            alignment: 4,
+        }
+    }
+}
+
+/// One attribute within a [`VertexLayout`].
+///
+/// [`VertexLayout`]: struct.VertexLayout.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutAttribute {
+    /// Where the attribute's data comes from.
+    pub source: AttributeSource,
+    /// How each of the attribute's components are stored.
+    pub format: VertexFormat,
+}
+
+impl LayoutAttribute {
+    /// Create a new layout attribute.
+    pub fn new(source: AttributeSource, format: VertexFormat) -> Self {
+        LayoutAttribute { source, format }
+    }
+}
+
+/// The vertex data that an attribute in a [`VertexLayout`] is made of.
+///
+/// [`VertexLayout`]: struct.VertexLayout.html
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AttributeSource {
+    /// [`Vertex.position`], 3 components
+    ///
+    /// [`Vertex.position`]: struct.Vertex.html#method.position
+    Position,
+    /// [`Vertex.normal`], 3 components
+    ///
+    /// [`Vertex.normal`]: struct.Vertex.html#method.normal
+    Normal,
+    /// [`Vertex.face_tangent`], 3 components
+    ///
+    /// [`Vertex.face_tangent`]: struct.Vertex.html#method.face_tangent
+    FaceTangent,
+    /// [`Vertex.tangent`], 4 components
+    ///
+    /// [`Vertex.tangent`]: struct.Vertex.html#method.tangent
+    Tangent,
+    /// [`Vertex.uv`], 2 components
+    ///
+    /// [`Vertex.uv`]: struct.Vertex.html#method.uv
+    Uv,
+    /// A UV layer other than the active one, 2 components
+    UvLayer(String),
+    /// [`Vertex.color`], 4 components
+    ///
+    /// [`Vertex.color`]: struct.Vertex.html#method.color
+    Color,
+    /// The index of each bone in [`Vertex.bones`], one component per bone influence
+    ///
+    /// [`Vertex.bones`]: struct.Vertex.html#method.bones
+    Joints,
+    /// The weight of each bone in [`Vertex.bones`], one component per bone influence
+    ///
+    /// [`Vertex.bones`]: struct.Vertex.html#method.bones
+    Weights,
+    /// A custom attribute, with as many components as its attribute size
+    Custom(String),
+}
+
+/// How each component of an attribute is stored.
+///
+/// Normalized formats map integers onto floats in the shader, such as unorm8's 255 becoming 1.0.
+/// Values outside of the range that a normalized format can represent are clamped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VertexFormat {
+    /// 32 bit float
+    Float32,
+    /// 16 bit float
+    Float16,
+    /// 16 bit integer that maps onto \[-1.0, 1.0\]
+    Snorm16,
+    /// 16 bit unsigned integer that maps onto \[0.0, 1.0\]
+    Unorm16,
+    /// 8 bit integer that maps onto \[-1.0, 1.0\]
+    Snorm8,
+    /// 8 bit unsigned integer that maps onto \[0.0, 1.0\]
+    Unorm8,
+    /// 8 bit unsigned integer. Only supported for joints.
+    Uint8,
+    /// 16 bit unsigned integer. Only supported for joints.
+    Uint16,
+    /// A unit vector encoded onto an octahedron and stored as two snorm16's.
+    /// Only supported for normals and face tangents.
+    ///
+    /// @see https://knarkowicz.wordpress.com/2014/04/16/octahedron-normal-vector-encoding/
+    Octahedral16,
+    /// A unit vector encoded onto an octahedron and stored as two snorm8's.
+    /// Only supported for normals and face tangents.
+    Octahedral8,
+}
+
+impl VertexFormat {
+    /// The number of bytes in each stored component.
+    pub fn component_size(&self) -> usize {
+        match self {
+            VertexFormat::Float32 => 4,
+            VertexFormat::Float16
+            | VertexFormat::Snorm16
+            | VertexFormat::Unorm16
+            | VertexFormat::Uint16
+            | VertexFormat::Octahedral16 => 2,
+            VertexFormat::Snorm8
+            | VertexFormat::Unorm8
+            | VertexFormat::Uint8
+            | VertexFormat::Octahedral8 => 1,
+        }
+    }
+
+    /// Whether or not the shader should normalize the integers into floats.
+    pub fn is_normalized(&self) -> bool {
+        match self {
+            VertexFormat::Float32
+            | VertexFormat::Float16
+            | VertexFormat::Uint8
+            | VertexFormat::Uint16 => false,
+            VertexFormat::Snorm16
+            | VertexFormat::Unorm16
+            | VertexFormat::Snorm8
+            | VertexFormat::Unorm8
+            | VertexFormat::Octahedral16
+            | VertexFormat::Octahedral8 => true,
+        }
+    }
+
+    fn is_octahedral(&self) -> bool {
+        matches!(self, VertexFormat::Octahedral16 | VertexFormat::Octahedral8)
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self, VertexFormat::Uint8 | VertexFormat::Uint16)
+    }
+}
+
+/// An error while interleaving vertices with a [`VertexLayout`].
+///
+/// [`VertexLayout`]: struct.VertexLayout.html
+#[derive(Debug, thiserror::Error)]
+pub enum VertexLayoutError {
+    /// Not every vertex has the data for an attribute, such as a layout with normals for a mesh
+    /// without normals.
+    #[error("Not every vertex has data for {attribute:?}")]
+    MissingAttribute {
+        /// The attribute that is missing
+        attribute: AttributeSource,
+    },
+    /// The format can't be used for the attribute, such as octahedral uvs or float joints.
+    #[error("{attribute:?} can not be stored as {format:?}")]
+    UnsupportedFormat {
+        /// The attribute
+        attribute: AttributeSource,
+        /// The format that was requested for it
+        format: VertexFormat,
+    },
+    /// The alignment is zero or not a power of two.
+    #[error("Alignment {alignment} is not a power of two")]
+    InvalidAlignment {
+        /// The requested alignment
+        alignment: usize,
+    },
+}
+
+/// Vertices that were packed into a single buffer using a [`VertexLayout`].
+///
+/// [`VertexLayout`]: struct.VertexLayout.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterleavedVertices {
+    bytes: Vec<u8>,
+    stride: usize,
+    vertex_count: usize,
+    attributes: Vec<InterleavedAttribute>,
+}
+
+impl InterleavedVertices {
+    /// The vertex buffer, with every component in little endian byte order.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Take ownership of the vertex buffer.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// The number of bytes between the start of one vertex and the start of the next.
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// The number of vertices in the buffer.
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    /// Where each attribute is within a vertex, in the order of the layout. Useful for setting up
+    /// vertex attribute pointers.
+    pub fn attributes(&self) -> &[InterleavedAttribute] {
+        &self.attributes
+    }
+}
+
+/// Where an attribute is within each vertex of [`InterleavedVertices`].
+///
+/// [`InterleavedVertices`]: struct.InterleavedVertices.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterleavedAttribute {
+    source: AttributeSource,
+    format: VertexFormat,
+    components: u8,
+    offset: usize,
+}
+
+impl InterleavedAttribute {
+    /// Where the attribute's data came from.
+    pub fn source(&self) -> &AttributeSource {
+        &self.source
+    }
+
+    /// How each component is stored.
+    pub fn format(&self) -> VertexFormat {
+        self.format
+    }
+
+    /// The number of stored components, such as 2 for an octahedral normal.
+    pub fn components(&self) -> u8 {
+        self.components
+    }
+
+    /// The number of bytes between the start of the vertex and the start of this attribute.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The number of bytes that the attribute takes up, not including padding.
+    pub fn size(&self) -> usize {
+        self.components as usize * self.format.component_size()
+    }
+}
+
+impl SingleIndexedVertexAttributes {
+    /// Pack every vertex into a single buffer using the given layout.
+    ///
+    /// Each attribute is quantized into its format and then padded to the layout's alignment.
+    ///
+    /// Unorm weights are quantized such that each vertex's weights still sum to exactly 1.0.
+    pub fn interleave_with_layout(
+        &self,
+        layout: &VertexLayout,
+    ) -> Result<InterleavedVertices, VertexLayoutError> {
+        let alignment = layout.alignment;
+        if !alignment.is_power_of_two() {
+            return Err(VertexLayoutError::InvalidAlignment { alignment });
+        }
+
+        let mut attributes = vec![];
+        let mut attribute_data = vec![];
+        let mut offset = 0;
+
+        for attribute in layout.attributes.iter() {
+            let source = &attribute.source;
+            let format = attribute.format;
+
+            let data = self.attribute_source_data(source)?;
+            validate_format(source, format, data.attribute_size)?;
+
+            let components = match format.is_octahedral() {
+                true => 2,
+                false => data.attribute_size,
+            };
+
+            attributes.push(InterleavedAttribute {
+                source: source.clone(),
+                format,
+                components,
+                offset,
+            });
+            attribute_data.push(data);
+
+            offset = align(offset + attributes.last().unwrap().size(), alignment);
+        }
+
+        let stride = offset;
+        let vertex_count = self.vertices.len();
+
+        let mut bytes = Vec::with_capacity(stride * vertex_count);
+
+        for vertex_idx in 0..vertex_count {
+            let vertex_start = bytes.len();
+
+            for (attribute, data) in attributes.iter().zip(attribute_data.iter()) {
+                // Pad the previous attribute up to this one's offset
+                bytes.resize(vertex_start + attribute.offset, 0);
+
+                let size = data.attribute_size as usize;
+                let values = &data.data[vertex_idx * size..(vertex_idx + 1) * size];
+
+                write_attribute(&mut bytes, attribute, values);
+            }
+
+            bytes.resize(vertex_start + stride, 0);
+        }
+
+        Ok(InterleavedVertices {
+            bytes,
+            stride,
+            vertex_count,
+            attributes,
+        })
+    }
+
+    /// Every vertex's data for an attribute source.
+    fn attribute_source_data(
+        &self,
+        source: &AttributeSource,
+    ) -> Result<VertexAttribute<f32>, VertexLayoutError> {
+        let missing = || VertexLayoutError::MissingAttribute {
+            attribute: source.clone(),
+        };
+
+        let vertices = &self.vertices;
+
+        let (data, attribute_size) = match source {
+            AttributeSource::Position => (
+                vertices.iter().flat_map(|v| v.position.to_vec()).collect(),
+                3,
+            ),
+            AttributeSource::Normal => (
+                collect_optional(vertices.iter().map(|v| v.normal.map(|n| n.to_vec())))
+                    .ok_or_else(missing)?,
+                3,
+            ),
+            AttributeSource::FaceTangent => (
+                collect_optional(vertices.iter().map(|v| v.face_tangent.map(|t| t.to_vec())))
+                    .ok_or_else(missing)?,
+                3,
+            ),
+            AttributeSource::Tangent => (
+                collect_optional(vertices.iter().map(|v| v.tangent.map(|t| t.to_vec())))
+                    .ok_or_else(missing)?,
+                4,
+            ),
+            AttributeSource::Uv => (
+                collect_optional(vertices.iter().map(|v| v.uv.map(|uv| uv.to_vec())))
+                    .ok_or_else(missing)?,
+                2,
+            ),
+            AttributeSource::UvLayer(name) => {
+                let layer = match self.active_uv_layer.as_ref() {
+                    Some(active) if active == name => {
+                        return self.attribute_source_data(&AttributeSource::Uv)
+                    }
+                    _ => self.uv_layers.get(name).ok_or_else(missing)?,
+                };
+                (layer.data.clone(), 2)
+            }
+            AttributeSource::Color => (
+                collect_optional(vertices.iter().map(|v| v.color.map(|c| c.to_vec())))
+                    .ok_or_else(missing)?,
+                4,
+            ),
+            AttributeSource::Joints => (
+                collect_optional(vertices.iter().map(|v| {
+                    v.bones()
+                        .map(|bones| bones.iter().map(|b| b.bone_idx as f32).collect())
+                }))
+                .ok_or_else(missing)?,
+                self.bone_influences_per_vertex().ok_or_else(missing)? as u8,
+            ),
+            AttributeSource::Weights => (
+                collect_optional(vertices.iter().map(|v| {
+                    v.bones()
+                        .map(|bones| bones.iter().map(|b| b.weight).collect())
+                }))
+                .ok_or_else(missing)?,
+                self.bone_influences_per_vertex().ok_or_else(missing)? as u8,
+            ),
+            AttributeSource::Custom(name) => {
+                let custom = self.custom_attributes.get(name).ok_or_else(missing)?;
+                (custom.data.clone(), custom.attribute_size)
+            }
+        };
+
+        Ok(VertexAttribute {
+            data,
+            attribute_size,
+        })
+    }
+}
+
+/// Joints are indices so they must stay integers, and nothing else should be truncated into
+/// integers. Only unit vectors can be octahedral encoded.
+fn validate_format(
+    source: &AttributeSource,
+    format: VertexFormat,
+    attribute_size: u8,
+) -> Result<(), VertexLayoutError> {
+    let is_unit_vector = matches!(
+        source,
+        AttributeSource::Normal | AttributeSource::FaceTangent
+    );
+
+    let supported = match source {
+        AttributeSource::Joints => format.is_integer(),
+        _ if format.is_integer() => false,
+        _ if format.is_octahedral() => is_unit_vector && attribute_size == 3,
+        _ => true,
+    };
+
+    match supported {
+        true => Ok(()),
+        false => Err(VertexLayoutError::UnsupportedFormat {
+            attribute: source.clone(),
+            format,
+        }),
+    }
+}
+
+fn write_attribute(bytes: &mut Vec<u8>, attribute: &InterleavedAttribute, values: &[f32]) {
+    let format = attribute.format;
+
+    match (&attribute.source, format) {
+        (_, VertexFormat::Octahedral16) | (_, VertexFormat::Octahedral8) => {
+            let [x, y] = octahedral_encode([values[0], values[1], values[2]]);
+            let snorm = match format {
+                VertexFormat::Octahedral16 => VertexFormat::Snorm16,
+                _ => VertexFormat::Snorm8,
+            };
+            write_component(bytes, snorm, x);
+            write_component(bytes, snorm, y);
+        }
+        (AttributeSource::Weights, VertexFormat::Unorm8) => {
+            for weight in quantize_weights(values, u8::MAX as u32) {
+                bytes.push(weight as u8);
+            }
+        }
+        (AttributeSource::Weights, VertexFormat::Unorm16) => {
+            for weight in quantize_weights(values, u16::MAX as u32) {
+                bytes.extend_from_slice(&(weight as u16).to_le_bytes());
+            }
+        }
+        _ => {
+            for value in values {
+                write_component(bytes, format, *value);
+            }
+        }
+    };
+}
+
+fn write_component(bytes: &mut Vec<u8>, format: VertexFormat, value: f32) {
+    match format {
+        VertexFormat::Float32 => bytes.extend_from_slice(&value.to_le_bytes()),
+        VertexFormat::Float16 => {
+            bytes.extend_from_slice(&half::f16::from_f32(value).to_bits().to_le_bytes())
+        }
+        VertexFormat::Snorm16 => {
+            let value = (value.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        VertexFormat::Unorm16 => {
+            let value = (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        VertexFormat::Snorm8 => {
+            let value = (value.clamp(-1.0, 1.0) * i8::MAX as f32).round() as i8;
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        VertexFormat::Unorm8 => {
+            bytes.push((value.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8);
+        }
+        VertexFormat::Uint8 => bytes.push(value as u8),
+        VertexFormat::Uint16 => bytes.extend_from_slice(&(value as u16).to_le_bytes()),
+        VertexFormat::Octahedral16 | VertexFormat::Octahedral8 => {
+            unreachable!("Octahedral vectors are written as two snorm components")
+        }
+    };
+}
+
+/// Encode a unit vector onto the two dimensional unfolded octahedron in \[-1.0, 1.0\].
+///
+/// Decoding in a shader:
+///
+/// ```glsl
+/// vec3 n = vec3(e.xy, 1.0 - abs(e.x) - abs(e.y));
+/// float t = max(-n.z, 0.0);
+/// n.xy += vec2(n.x >= 0.0 ? -t : t, n.y >= 0.0 ? -t : t);
+/// n = normalize(n);
+/// ```
+fn octahedral_encode(vector: [f32; 3]) -> [f32; 2] {
+    let [x, y, z] = vector;
+    let l1_norm = x.abs() + y.abs() + z.abs();
+    if l1_norm == 0.0 {
+        return [0.0, 0.0];
+    }
+
+    let (x, y, z) = (x / l1_norm, y / l1_norm, z / l1_norm);
+
+    if z >= 0.0 {
+        [x, y]
+    } else {
+        [
+            (1.0 - y.abs()) * sign_not_zero(x),
+            (1.0 - x.abs()) * sign_not_zero(y),
+        ]
+    }
+}
+
+fn sign_not_zero(value: f32) -> f32 {
+    if value >= 0.0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+/// Quantize weights onto [0, max] such that they sum to exactly `max`.
+///
+/// Rounding each weight on its own can leave the sum off by a few steps, so we give the
+/// difference to the largest weight.
+fn quantize_weights(weights: &[f32], max: u32) -> Vec<u32> {
+    let total: f32 = weights.iter().map(|w| w.max(0.0)).sum();
+    if total <= 0.0 {
+        return vec![0; weights.len()];
+    }
+
+    let mut quantized: Vec<u32> = weights
+        .iter()
+        .map(|w| (w.max(0.0) / total * max as f32).round() as u32)
+        .collect();
+
+    let largest = (0..quantized.len())
+        .max_by_key(|idx| quantized[*idx])
+        .unwrap();
+    let sum: u32 = quantized.iter().sum();
+    quantized[largest] = (quantized[largest] + max).saturating_sub(sum).min(max);
+
+    quantized
+}
+
+fn collect_optional(per_vertex: impl Iterator<Item = Option<Vec<f32>>>) -> Option<Vec<f32>> {
+    let per_vertex: Vec<Vec<f32>> = per_vertex.collect::<Option<_>>()?;
+    Some(per_vertex.into_iter().flatten().collect())
+}
+
+fn align(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoneInfluence, Vertex};
+
+    /// Verify that attribute offsets and the stride are aligned, and that each attribute is
+    /// written at its offset.
+    #[test]
+    fn offsets_and_stride() {
+        let single_indexed = two_vertices();
+        let layout = VertexLayout {
+            attributes: vec![
+                LayoutAttribute::new(AttributeSource::Position, VertexFormat::Float32),
+                LayoutAttribute::new(AttributeSource::Uv, VertexFormat::Float16),
+                LayoutAttribute::new(AttributeSource::Color, VertexFormat::Unorm8),
+                LayoutAttribute::new(AttributeSource::Normal, VertexFormat::Snorm16),
+            ],
+            ..VertexLayout::default()
+        };
+
+        let interleaved = single_indexed.interleave_with_layout(&layout).unwrap();
+
+        let offsets: Vec<usize> = interleaved
+            .attributes()
+            .iter()
+            .map(|a| a.offset())
+            .collect();
+        // The snorm16 normal takes up 6 bytes, which gets padded to 8.
+        assert_eq!(offsets, vec![0, 12, 16, 20]);
+        assert_eq!(interleaved.stride(), 28);
+        assert_eq!(interleaved.vertex_count(), 2);
+        assert_eq!(interleaved.bytes().len(), 56);
+
+        let second_vertex = &interleaved.bytes()[28..];
+        assert_eq!(&second_vertex[0..4], &1.0f32.to_le_bytes());
+        assert_eq!(
+            &second_vertex[12..14],
+            &half::f16::from_f32(0.5).to_bits().to_le_bytes()
+        );
+        assert_eq!(&second_vertex[16..20], &[255, 0, 128, 255]);
+        assert_eq!(&second_vertex[20..22], &i16::MAX.to_le_bytes());
+        assert_eq!(&second_vertex[26..28], &[0, 0]);
+    }
+
+    /// Verify that joints are written as integers and weights are quantized to sum to one
+    #[test]
+    fn joints_and_weights() {
+        let single_indexed = two_vertices();
+        let layout = VertexLayout {
+            attributes: vec![
+                LayoutAttribute::new(AttributeSource::Joints, VertexFormat::Uint16),
+                LayoutAttribute::new(AttributeSource::Weights, VertexFormat::Unorm8),
+            ],
+            ..VertexLayout::default()
+        };
+
+        let interleaved = single_indexed.interleave_with_layout(&layout).unwrap();
+        let bytes = interleaved.bytes();
+
+        assert_eq!(interleaved.stride(), 12);
+        assert_eq!(&bytes[0..8], &[3, 0, 7, 0, 1, 0, 0, 0]);
+
+        // A quarter each rounds to 64, which would sum to 256
+        assert_eq!(&bytes[8..12], &[64, 64, 64, 63]);
+    }
+
+    /// Verify that octahedral normals take up two components and decode back to the normal
+    #[test]
+    fn octahedral_normals() {
+        for normal in &[
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+            [0.6, 0.0, -0.8],
+            [0.0, 1.0, 0.0],
+        ] {
+            let [x, y] = octahedral_encode(*normal);
+
+            let mut decoded = [x, y, 1.0 - x.abs() - y.abs()];
+            let t = (-decoded[2]).max(0.0);
+            decoded[0] += if decoded[0] >= 0.0 { -t } else { t };
+            decoded[1] += if decoded[1] >= 0.0 { -t } else { t };
+            let length = decoded.iter().map(|c| c * c).sum::<f32>().sqrt();
+
+            for idx in 0..3 {
+                assert!((decoded[idx] / length - normal[idx]).abs() < 1e-5);
+            }
+        }
+
+        let layout = VertexLayout {
+            attributes: vec![LayoutAttribute::new(
+                AttributeSource::Normal,
+                VertexFormat::Octahedral8,
+            )],
+            alignment: 1,
+        };
+        let interleaved = two_vertices().interleave_with_layout(&layout).unwrap();
+
+        assert_eq!(interleaved.attributes()[0].components(), 2);
+        assert_eq!(interleaved.stride(), 2);
+    }
+
+    /// Verify that custom attributes and UV layers can be part of the layout
+    #[test]
+    fn named_attributes() {
+        let mut single_indexed = two_vertices();
+        single_indexed.custom_attributes.insert(
+            "wind".to_string(),
+            VertexAttribute::new(vec![0.25, 0.75], 1).unwrap(),
+        );
+
+        let layout = VertexLayout {
+            attributes: vec![LayoutAttribute::new(
+                AttributeSource::Custom("wind".to_string()),
+                VertexFormat::Unorm8,
+            )],
+            alignment: 1,
+        };
+
+        let interleaved = single_indexed.interleave_with_layout(&layout).unwrap();
+        assert_eq!(interleaved.bytes(), &[64, 191]);
+
+        let layout = VertexLayout {
+            attributes: vec![LayoutAttribute::new(
+                AttributeSource::UvLayer("Lightmap".to_string()),
+                VertexFormat::Float32,
+            )],
+            ..VertexLayout::default()
+        };
+        match single_indexed.interleave_with_layout(&layout) {
+            Err(VertexLayoutError::MissingAttribute { .. }) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    /// Verify that we return an error for formats that don't make sense for an attribute
+    #[test]
+    fn error_if_unsupported_format() {
+        for (source, format) in &[
+            (AttributeSource::Joints, VertexFormat::Float32),
+            (AttributeSource::Position, VertexFormat::Uint16),
+            (AttributeSource::Uv, VertexFormat::Octahedral16),
+        ] {
+            let layout = VertexLayout {
+                attributes: vec![LayoutAttribute::new(source.clone(), *format)],
+                ..VertexLayout::default()
+            };
+
+            match two_vertices().interleave_with_layout(&layout) {
+                Err(VertexLayoutError::UnsupportedFormat { .. }) => {}
+                _ => unreachable!(),
+            };
+        }
+    }
+
+    fn two_vertices() -> SingleIndexedVertexAttributes {
+        let bones = vec![
+            BoneInfluence {
+                bone_idx: 3,
+                weight: 1.0,
+            },
+            BoneInfluence {
+                bone_idx: 7,
+                weight: 1.0,
+            },
+            BoneInfluence {
+                bone_idx: 1,
+                weight: 1.0,
+            },
+            BoneInfluence {
+                bone_idx: 0,
+                weight: 1.0,
+            },
+        ];
+
+        let vertex = |x: f32| Vertex {
+            position: [x, 0.0, 0.0],
+            normal: Some([x, 0.0, 1.0 - x]),
+            uv: Some([x / 2.0, 0.0]),
+            color: Some([1.0, 0.0, 0.5, 1.0]),
+            bones: Some(bones.clone()),
+            ..Vertex::default()
+        };
+
+        SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 0],
+            vertices: vec![vertex(0.0), vertex(1.0)],
+            ..SingleIndexedVertexAttributes::default()
+        }
     }
 }
@@ -16,36 +16,59 @@
 //! @see https://docs.blender.org/manual/en/dev/modeling/meshes/introduction.html - Mesh Introduction
 //! @see https://github.com/chinedufn/blender-actions-to-json - Exporting blender armatures / actions
 
-#[macro_use]
-extern crate failure;
 #[macro_use]
 extern crate serde_derive;
 
-pub use self::combine_indices::CreateSingleIndexConfig;
+pub use self::combine_indices::{
+    CombineIndicesError, CreateSingleIndexConfig, WeightedNormalsError,
+};
 pub use self::export::*;
+pub use self::gltf::{Gltf, GltfConfig, GltfError};
+pub use self::interleave::{
+    AttributeSource, InterleavedAttribute, InterleavedVertices, LayoutAttribute, VertexFormat,
+    VertexLayout, VertexLayoutError,
+};
+pub use self::obj::ObjError;
+pub use self::optimize::{OptimizationStats, VertexCacheStats, MAX_VERTEX_CACHE_SIZE};
+pub use crate::bone::RemapBonesError;
 pub use crate::bounding_box::BoundingBox;
 use crate::custom_property::CustomProperty;
+pub use crate::face_tangents::TangentError;
 pub use crate::material::PrincipledBSDF;
+pub use crate::merge::{MergeError, MeshToMerge};
 use crate::serde::serialize_hashmap_deterministic;
+pub use crate::simplify::{Lod, SimplifyConfig};
+pub use crate::triangulate::TriangulationWarning;
+pub use crate::validate::MeshProblem;
 pub use crate::vertex_attributes::{
-    BoneInfluence, MultiIndexedVertexAttributes, SingleIndexedVertexAttributes, Vertex,
-    VertexAttribute,
+    BoneInfluence, IndexFormat, IndexedAttribute, MaterialRange, MultiIndexedVertexAttributes,
+    SingleIndexedVertexAttributes, Vertex, VertexAttribute, VertexAttributeError,
 };
+pub use crate::vertex_tangents::VertexTangentError;
+pub use blender_armature::{Axis, CoordinateSystem, Hand};
 pub use material::{Channel, MaterialInput};
 use std::collections::HashMap;
 
 mod bone;
 mod bounding_box;
 mod combine_indices;
+mod coordinate_system;
 mod custom_property;
 mod export;
 mod face_tangents;
+mod gltf;
 mod interleave;
 mod material;
+mod merge;
+mod obj;
+mod optimize;
 mod serde;
+mod simplify;
+mod transform;
 mod triangulate;
+mod validate;
 mod vertex_attributes;
-mod y_up;
+mod vertex_tangents;
 
 mod create_mesh;
 
@@ -53,12 +76,12 @@ mod create_mesh;
 mod test_utils;
 
 /// Something went wrong in the Blender child process that was trying to parse your mesh data.
-#[derive(Debug, Fail)]
+#[derive(Debug, thiserror::Error)]
 pub enum BlenderError {
     /// Errors in Blender are written to stderr. We capture the stderr from the `blender` child
     /// process that we spawned when attempting to export meshes from a `.blend` file.
-    #[fail(
-        display = "There was an issue while exporting meshes: Blender stderr output: {}",
+    #[error(
+        "There was an issue while exporting meshes: Blender stderr output: {}",
         _0
     )]
     Stderr(String),
@@ -77,8 +100,14 @@ pub struct BlenderMesh {
     multi_indexed_vertex_attributes: MultiIndexedVertexAttributes,
     #[serde(default, serialize_with = "serialize_hashmap_deterministic")]
     materials: HashMap<String, PrincipledBSDF>,
+    // The name of the material in each of the mesh's material slots, or `None` for empty slots.
+    #[serde(default)]
+    material_slots: Vec<Option<String>>,
     #[serde(default, serialize_with = "serialize_hashmap_deterministic")]
     custom_properties: HashMap<String, CustomProperty>,
+    // Meshes are exported from Blender as Z up right handed.
+    #[serde(default)]
+    coordinate_system: CoordinateSystem,
 }
 
 impl BlenderMesh {
@@ -102,6 +131,19 @@ impl BlenderMesh {
         &mut self.materials
     }
 
+    /// The name of the material in each of the mesh's material slots, or `None` for slots
+    /// without a material.
+    ///
+    /// Each face's material index points into these slots.
+    pub fn material_slots(&self) -> &Vec<Option<String>> {
+        &self.material_slots
+    }
+
+    /// Set the name of the material in each of the mesh's material slots.
+    pub fn set_material_slots(&mut self, material_slots: Vec<Option<String>>) {
+        self.material_slots = material_slots;
+    }
+
     /// Custom properties for this mesh
     ///
     /// i.e. in Blender this might be found with `bpy.context.view_layer.objects.active.keys()`
@@ -119,6 +161,16 @@ impl BlenderMesh {
         self.bounding_box = bounding_box;
     }
 
+    /// The mesh's vertex data, with separate indices for each attribute.
+    pub fn multi_indexed_vertex_attributes(&self) -> &MultiIndexedVertexAttributes {
+        &self.multi_indexed_vertex_attributes
+    }
+
+    /// Mutable access to the mesh's multi indexed vertex data.
+    pub fn multi_indexed_vertex_attributes_mut(&mut self) -> &mut MultiIndexedVertexAttributes {
+        &mut self.multi_indexed_vertex_attributes
+    }
+
     /// The name of the mesh
     pub fn name(&self) -> &String {
         &self.name
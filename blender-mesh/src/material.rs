@@ -1,3 +1,6 @@
+use crate::serialize_hashmap_deterministic;
+use std::collections::HashMap;
+
 /// Material data for a mesh
 ///
 /// # Blender
@@ -16,6 +19,11 @@ pub struct PrincipledBSDF {
     pub(crate) metallic: MaterialInput<f32, (String, Channel)>,
     /// The filename for the material's normal map
     pub(crate) normal_map: Option<String>,
+    /// The UV layer that each image texture samples from, keyed by the texture's image name.
+    ///
+    /// Textures that aren't in this map sample from the mesh's active UV layer.
+    #[serde(default, serialize_with = "serialize_hashmap_deterministic")]
+    pub(crate) texture_uv_layers: HashMap<String, String>,
 }
 
 /// An input to a material property.
@@ -86,6 +94,7 @@ impl PrincipledBSDF {
             roughness,
             metallic,
             normal_map,
+            texture_uv_layers: HashMap::new(),
         }
     }
 
@@ -118,4 +127,16 @@ impl PrincipledBSDF {
     pub fn normal_map(&self) -> Option<&String> {
         self.normal_map.as_ref()
     }
+
+    /// The name of the UV layer that an image texture samples from.
+    ///
+    /// `None` means that the texture samples from the mesh's active UV layer.
+    pub fn texture_uv_layer(&self, image: &str) -> Option<&String> {
+        self.texture_uv_layers.get(image)
+    }
+
+    /// Set the name of the UV layer that an image texture samples from.
+    pub fn set_texture_uv_layer(&mut self, image: String, uv_layer: String) {
+        self.texture_uv_layers.insert(image, uv_layer);
+    }
 }
@@ -0,0 +1,756 @@
+//! Combining several meshes into one, such as when statically batching level geometry that was
+//! authored as separate objects.
+
+use crate::bone::BoneInfluencesPerVertex;
+use crate::bounding_box::BoundingBox;
+use crate::vertex_attributes::{
+    IndexedAttribute, MultiIndexedVertexAttributes, VertexAttribute, VertexBoneInfluences,
+};
+use crate::BlenderMesh;
+use nalgebra::{Matrix4, Point3};
+use std::collections::BTreeSet;
+
+/// A mesh to merge with [`BlenderMesh::merge`], along with an optional transform to apply to it
+/// first.
+///
+/// [`BlenderMesh::merge`]: struct.BlenderMesh.html#method.merge
+#[derive(Debug, Clone, Copy)]
+pub struct MeshToMerge<'a> {
+    /// The mesh
+    pub mesh: &'a BlenderMesh,
+    /// Applied to the mesh in its own coordinate system before it is merged, such as the world
+    /// transform of the object that the mesh belongs to.
+    pub transform: Option<Matrix4<f32>>,
+}
+
+impl<'a> From<&'a BlenderMesh> for MeshToMerge<'a> {
+    fn from(mesh: &'a BlenderMesh) -> Self {
+        MeshToMerge {
+            mesh,
+            transform: None,
+        }
+    }
+}
+
+/// An error while merging meshes
+#[derive(Debug, thiserror::Error)]
+pub enum MergeError {
+    /// Two meshes have a material with the same name but different data, so we can't tell which
+    /// one the merged mesh should use.
+    #[error("Mesh {mesh} has a material {material} that conflicts with an earlier mesh's")]
+    ConflictingMaterial {
+        /// The name of the mesh with the conflicting material
+        mesh: String,
+        /// The name of the material
+        material: String,
+    },
+    /// Bone indices point into the parent armature's bones, so meshes that are parented to
+    /// different armatures can't share bone influences.
+    #[error("Mesh {mesh} is parented to armature {armature} but an earlier mesh is parented to {expected}")]
+    DifferentArmatures {
+        /// The name of the mesh with the different armature
+        mesh: String,
+        /// The mesh's armature
+        armature: String,
+        /// The armature of the earlier meshes
+        expected: String,
+    },
+    /// Two meshes have an attribute with the same name but a different number of floats per
+    /// vertex, such as a custom attribute that is a scalar in one mesh and a vector in another.
+    #[error("Mesh {mesh} has {attribute} with {size} floats per vertex but an earlier mesh has {expected}")]
+    AttributeSizeMismatch {
+        /// The name of the mesh with the mismatched attribute
+        mesh: String,
+        /// The attribute, such as `normals` or the name of a custom attribute
+        attribute: String,
+        /// The attribute size in the mesh
+        size: u8,
+        /// The attribute size in the earlier meshes
+        expected: u8,
+    },
+}
+
+impl BlenderMesh {
+    /// Combine several meshes into one mesh.
+    ///
+    /// The merged mesh uses the name and coordinate system of the first mesh. Each mesh gets its
+    /// transform applied (see [`BlenderMesh.transform`]) and is then changed to the merged
+    /// coordinate system.
+    ///
+    /// - Faces and their vertex attributes are concatenated, offsetting each mesh's indices.
+    ///
+    /// - An attribute that only some of the meshes have is filled in for the others with zeros,
+    ///   or with white for vertex colors.
+    ///
+    /// - Materials are combined by name, along with the material slots that the faces use. It is
+    ///   an error for two meshes to have different materials with the same name.
+    ///
+    /// - Bone indices point into the bones of the parent armature, so the bone influences of
+    ///   meshes that share an armature (by name) are kept as they are. Vertices of meshes that
+    ///   have no bone influences aren't influenced by any bones. It is an error for meshes to be
+    ///   parented to different armatures.
+    ///
+    /// - The bounding box contains every mesh's bounding box.
+    ///
+    /// - Custom properties are combined, with earlier meshes taking precedence.
+    ///
+    /// [`BlenderMesh.transform`]: #method.transform
+    pub fn merge<'a>(
+        meshes: impl IntoIterator<Item = MeshToMerge<'a>>,
+    ) -> Result<BlenderMesh, MergeError> {
+        let mut meshes = meshes.into_iter().peekable();
+
+        let coordinate_system = match meshes.peek() {
+            Some(first) => first.mesh.coordinate_system,
+            None => return Ok(BlenderMesh::default()),
+        };
+
+        let meshes: Vec<BlenderMesh> = meshes
+            .map(|to_merge| {
+                let mut mesh = to_merge.mesh.clone();
+                if let Some(transform) = to_merge.transform.as_ref() {
+                    mesh.transform(transform);
+                }
+                mesh.change_coordinate_system(coordinate_system);
+                mesh
+            })
+            .collect();
+
+        let mut merged = BlenderMesh {
+            name: meshes[0].name.clone(),
+            coordinate_system,
+            ..BlenderMesh::default()
+        };
+
+        merge_attributes(&mut merged.multi_indexed_vertex_attributes, &meshes)?;
+        merge_materials(&mut merged, &meshes)?;
+        merge_bone_influences(&mut merged, &meshes)?;
+
+        merged.bounding_box = merge_bounding_boxes(&meshes);
+
+        for mesh in meshes.iter() {
+            for (name, property) in mesh.custom_properties.iter() {
+                merged
+                    .custom_properties
+                    .entry(name.clone())
+                    .or_insert_with(|| property.clone());
+            }
+        }
+
+        Ok(merged)
+    }
+}
+
+/// The attributes that each face vertex can have.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum AttributeKey {
+    Positions,
+    Normals,
+    Uvs,
+    Colors,
+    UvLayer(String),
+    Custom(String),
+}
+
+impl AttributeKey {
+    fn get<'a>(&self, multi: &'a MultiIndexedVertexAttributes) -> Option<&'a IndexedAttribute> {
+        match self {
+            AttributeKey::Positions => Some(&multi.positions),
+            AttributeKey::Normals => multi.normals.as_ref(),
+            AttributeKey::Uvs => multi.uvs.as_ref(),
+            AttributeKey::Colors => multi.colors.as_ref(),
+            AttributeKey::UvLayer(name) => multi.uv_layers.get(name),
+            AttributeKey::Custom(name) => multi.custom_attributes.get(name),
+        }
+    }
+
+    fn set(self, multi: &mut MultiIndexedVertexAttributes, attribute: IndexedAttribute) {
+        match self {
+            AttributeKey::Positions => multi.positions = attribute,
+            AttributeKey::Normals => multi.normals = Some(attribute),
+            AttributeKey::Uvs => multi.uvs = Some(attribute),
+            AttributeKey::Colors => multi.colors = Some(attribute),
+            AttributeKey::UvLayer(name) => {
+                multi.uv_layers.insert(name, attribute);
+            }
+            AttributeKey::Custom(name) => {
+                multi.custom_attributes.insert(name, attribute);
+            }
+        }
+    }
+
+    fn fill_value(&self) -> f32 {
+        match self {
+            AttributeKey::Colors => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            AttributeKey::Positions => "positions".to_string(),
+            AttributeKey::Normals => "normals".to_string(),
+            AttributeKey::Uvs => "uvs".to_string(),
+            AttributeKey::Colors => "colors".to_string(),
+            AttributeKey::UvLayer(name) => format!("uv layer {}", name),
+            AttributeKey::Custom(name) => format!("custom attribute {}", name),
+        }
+    }
+}
+
+fn merge_attributes(
+    merged: &mut MultiIndexedVertexAttributes,
+    meshes: &[BlenderMesh],
+) -> Result<(), MergeError> {
+    let mut keys = BTreeSet::new();
+    keys.insert(AttributeKey::Positions);
+
+    for mesh in meshes.iter() {
+        let multi = &mesh.multi_indexed_vertex_attributes;
+
+        merged
+            .vertices_in_each_face
+            .extend_from_slice(&multi.vertices_in_each_face);
+
+        if merged.active_uv_layer.is_none() {
+            merged.active_uv_layer = multi.active_uv_layer.clone();
+        }
+
+        if multi.normals.is_some() {
+            keys.insert(AttributeKey::Normals);
+        }
+        if multi.uvs.is_some() {
+            keys.insert(AttributeKey::Uvs);
+        }
+        if multi.colors.is_some() {
+            keys.insert(AttributeKey::Colors);
+        }
+        keys.extend(multi.uv_layers.keys().cloned().map(AttributeKey::UvLayer));
+        keys.extend(
+            multi
+                .custom_attributes
+                .keys()
+                .cloned()
+                .map(AttributeKey::Custom),
+        );
+    }
+
+    for key in keys {
+        let attribute = merge_attribute(&key, meshes)?;
+        key.set(merged, attribute);
+    }
+
+    Ok(())
+}
+
+/// Concatenate one attribute across all of the meshes, offsetting each mesh's indices past the
+/// data of the meshes before it.
+fn merge_attribute(
+    key: &AttributeKey,
+    meshes: &[BlenderMesh],
+) -> Result<IndexedAttribute, MergeError> {
+    let mut attribute_size: Option<u8> = None;
+
+    for mesh in meshes.iter() {
+        let size = match key.get(&mesh.multi_indexed_vertex_attributes) {
+            Some(attribute) if !attribute.attribute.data.is_empty() => {
+                attribute.attribute.attribute_size
+            }
+            _ => continue,
+        };
+
+        match attribute_size {
+            None => attribute_size = Some(size),
+            Some(expected) if expected != size => {
+                return Err(MergeError::AttributeSizeMismatch {
+                    mesh: mesh.name.clone(),
+                    attribute: key.description(),
+                    size,
+                    expected,
+                });
+            }
+            _ => {}
+        };
+    }
+
+    let attribute_size = attribute_size.unwrap_or(match key {
+        AttributeKey::Positions | AttributeKey::Normals => 3,
+        AttributeKey::Colors => 4,
+        _ => 2,
+    });
+
+    let mut indices = vec![];
+    let mut data = vec![];
+
+    for mesh in meshes.iter() {
+        let multi = &mesh.multi_indexed_vertex_attributes;
+        let offset = (data.len() / attribute_size as usize) as u32;
+
+        match key.get(multi) {
+            Some(attribute) if !attribute.attribute.data.is_empty() => {
+                indices.extend(attribute.indices.iter().map(|index| index + offset));
+                data.extend_from_slice(&attribute.attribute.data);
+            }
+            _ => {
+                let face_vertex_count: usize = multi
+                    .vertices_in_each_face
+                    .iter()
+                    .map(|count| *count as usize)
+                    .sum();
+
+                if face_vertex_count > 0 {
+                    indices.resize(indices.len() + face_vertex_count, offset);
+                    data.resize(data.len() + attribute_size as usize, key.fill_value());
+                }
+            }
+        };
+    }
+
+    Ok(IndexedAttribute {
+        indices,
+        attribute: VertexAttribute {
+            data,
+            attribute_size,
+        },
+    })
+}
+
+/// Combine the materials by name and point every face at its material's slot in the merged
+/// mesh.
+fn merge_materials(merged: &mut BlenderMesh, meshes: &[BlenderMesh]) -> Result<(), MergeError> {
+    for mesh in meshes.iter() {
+        for (name, material) in mesh.materials.iter() {
+            match merged.materials.get(name) {
+                Some(existing) if existing != material => {
+                    return Err(MergeError::ConflictingMaterial {
+                        mesh: mesh.name.clone(),
+                        material: name.clone(),
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    merged.materials.insert(name.clone(), material.clone());
+                }
+            };
+        }
+    }
+
+    let has_material_slots = meshes.iter().any(|mesh| {
+        !mesh.material_slots.is_empty()
+            || !mesh
+                .multi_indexed_vertex_attributes
+                .material_indices
+                .is_empty()
+    });
+    if !has_material_slots {
+        return Ok(());
+    }
+
+    let mut material_indices = vec![];
+
+    for mesh in meshes.iter() {
+        let slots = if mesh.material_slots.is_empty() {
+            vec![None]
+        } else {
+            mesh.material_slots.clone()
+        };
+
+        let slot_indices: Vec<u16> = slots
+            .into_iter()
+            .map(|slot| {
+                match merged
+                    .material_slots
+                    .iter()
+                    .position(|merged| merged == &slot)
+                {
+                    Some(idx) => idx as u16,
+                    None => {
+                        merged.material_slots.push(slot);
+                        merged.material_slots.len() as u16 - 1
+                    }
+                }
+            })
+            .collect();
+
+        let multi = &mesh.multi_indexed_vertex_attributes;
+
+        for face in 0..multi.vertices_in_each_face.len() {
+            // Faces without a material index use the first slot.
+            let slot = multi.material_indices.get(face).copied().unwrap_or(0);
+            let slot = slot_indices
+                .get(slot as usize)
+                .copied()
+                .unwrap_or(slot_indices[0]);
+
+            material_indices.push(slot);
+        }
+    }
+
+    merged.multi_indexed_vertex_attributes.material_indices = material_indices;
+
+    Ok(())
+}
+
+/// Concatenate the bone influences for each position, giving the positions of meshes without
+/// bone influences no influences at all.
+fn merge_bone_influences(
+    merged: &mut BlenderMesh,
+    meshes: &[BlenderMesh],
+) -> Result<(), MergeError> {
+    for mesh in meshes.iter() {
+        let armature = match mesh.armature_name.as_ref() {
+            Some(armature) => armature,
+            None => continue,
+        };
+
+        match merged.armature_name.as_ref() {
+            Some(expected) if expected != armature => {
+                return Err(MergeError::DifferentArmatures {
+                    mesh: mesh.name.clone(),
+                    armature: armature.clone(),
+                    expected: expected.clone(),
+                });
+            }
+            Some(_) => {}
+            None => merged.armature_name = Some(armature.clone()),
+        };
+    }
+
+    if meshes.iter().all(|mesh| {
+        mesh.multi_indexed_vertex_attributes
+            .bone_influences
+            .is_none()
+    }) {
+        return Ok(());
+    }
+
+    let first_bone_names = meshes
+        .iter()
+        .find_map(|mesh| {
+            mesh.multi_indexed_vertex_attributes
+                .bone_influences
+                .as_ref()
+        })
+        .map(|influences| &influences.bone_names);
+
+    // The exporter writes all of the armature's bones for every mesh, so meshes that share an
+    // armature usually have the same bone names and their indices are kept as they are.
+    let same_bone_names = meshes.iter().all(|mesh| {
+        mesh.multi_indexed_vertex_attributes
+            .bone_influences
+            .as_ref()
+            .is_none_or(|influences| Some(&influences.bone_names) == first_bone_names)
+    });
+
+    // Meshes that were exported at different times might have different bone orders, so when
+    // every mesh knows its bone names we point the indices at a merged list of names. The list
+    // starts off with the first mesh's bones so that they stay in the armature's order.
+    let remap_by_name = !same_bone_names
+        && meshes.iter().all(|mesh| {
+            mesh.multi_indexed_vertex_attributes
+                .bone_influences
+                .as_ref()
+                .is_none_or(|influences| {
+                    influences
+                        .bone_indices
+                        .iter()
+                        .all(|bone_idx| (*bone_idx as usize) < influences.bone_names.len())
+                })
+        });
+
+    let mut bones_per_vertex = vec![];
+    let mut bone_indices = vec![];
+    let mut bone_weights = vec![];
+    let mut bone_names: Vec<String> = vec![];
+    if same_bone_names {
+        bone_names = first_bone_names.cloned().unwrap_or_default();
+    } else if remap_by_name {
+        for name in first_bone_names.into_iter().flatten() {
+            if !bone_names.contains(name) {
+                bone_names.push(name.clone());
+            }
+        }
+    }
+
+    for mesh in meshes.iter() {
+        let multi = &mesh.multi_indexed_vertex_attributes;
+        let position_count = multi.positions.attribute.data.len() / 3;
+
+        match multi.bone_influences.as_ref() {
+            Some(influences) => {
+                match &influences.bones_per_vertex {
+                    BoneInfluencesPerVertex::NonUniform(counts) => {
+                        bones_per_vertex.extend_from_slice(counts)
+                    }
+                    BoneInfluencesPerVertex::Uniform(count) => {
+                        bones_per_vertex.resize(bones_per_vertex.len() + position_count, *count)
+                    }
+                };
+                if remap_by_name {
+                    bone_indices.extend(influences.bone_indices.iter().map(|bone_idx| {
+                        let name = &influences.bone_names[*bone_idx as usize];
+                        match bone_names.iter().position(|merged| merged == name) {
+                            Some(merged_idx) => merged_idx as u8,
+                            None => {
+                                bone_names.push(name.clone());
+                                bone_names.len() as u8 - 1
+                            }
+                        }
+                    }));
+                } else {
+                    bone_indices.extend_from_slice(&influences.bone_indices);
+                }
+                bone_weights.extend_from_slice(&influences.bone_weights);
+            }
+            None => bones_per_vertex.resize(bones_per_vertex.len() + position_count, 0),
+        };
+    }
+
+    merged.multi_indexed_vertex_attributes.bone_influences = Some(VertexBoneInfluences {
+        bones_per_vertex: BoneInfluencesPerVertex::NonUniform(bones_per_vertex),
+        bone_indices,
+        bone_weights,
+        bone_names,
+    });
+
+    Ok(())
+}
+
+/// The smallest box that contains the bounding box of every mesh that has positions.
+fn merge_bounding_boxes(meshes: &[BlenderMesh]) -> BoundingBox {
+    let mut bounding_boxes = meshes
+        .iter()
+        .filter(|mesh| {
+            !mesh
+                .multi_indexed_vertex_attributes
+                .positions
+                .attribute
+                .data
+                .is_empty()
+        })
+        .map(|mesh| mesh.bounding_box);
+
+    let first = match bounding_boxes.next() {
+        Some(first) => first,
+        None => return BoundingBox::default(),
+    };
+
+    bounding_boxes.fold(first, |merged, bounding_box| BoundingBox {
+        min_corner: Point3::from(
+            merged
+                .min_corner
+                .coords
+                .inf(&bounding_box.min_corner.coords),
+        ),
+        max_corner: Point3::from(
+            merged
+                .max_corner
+                .coords
+                .sup(&bounding_box.max_corner.coords),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed;
+    use crate::PrincipledBSDF;
+    use nalgebra::Vector3;
+
+    /// Verify that indices are offset past the data of earlier meshes, and that an attribute
+    /// that only one mesh has is filled in for the other
+    #[test]
+    fn concatenate_attributes() {
+        let first = triangle_mesh("first");
+        let mut second = triangle_mesh("second");
+        second
+            .multi_indexed_vertex_attributes
+            .insert_custom_attribute("wind".to_string(), indexed((vec![0.5], 1).into()));
+        second
+            .multi_indexed_vertex_attributes
+            .custom_attributes
+            .get_mut("wind")
+            .unwrap()
+            .indices = vec![0, 0, 0];
+
+        let merged = BlenderMesh::merge(vec![
+            MeshToMerge::from(&first),
+            MeshToMerge {
+                mesh: &second,
+                transform: Some(Matrix4::new_translation(&Vector3::new(10.0, 0.0, 0.0))),
+            },
+        ])
+        .unwrap();
+
+        let multi = &merged.multi_indexed_vertex_attributes;
+        assert_eq!(multi.vertices_in_each_face, vec![3, 3]);
+        assert_eq!(multi.positions.indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(multi.positions.attribute.data[9], 10.0);
+
+        let wind = &multi.custom_attributes["wind"];
+        assert_eq!(wind.indices, vec![0, 0, 0, 1, 1, 1]);
+        assert_eq!(wind.attribute.data, vec![0.0, 0.5]);
+
+        assert_eq!(merged.name, "first");
+        assert_eq!(merged.bounding_box.min_corner, Point3::new(0.0, 0.0, 0.0));
+        assert_eq!(merged.bounding_box.max_corner, Point3::new(11.0, 1.0, 0.0));
+    }
+
+    /// Verify that materials are combined by name, that faces point to the merged slots and
+    /// that two different materials with the same name are an error
+    #[test]
+    fn merge_materials() {
+        let mut first = triangle_mesh("first");
+        first
+            .materials
+            .insert("Stone".to_string(), PrincipledBSDF::default());
+        first.material_slots = vec![Some("Stone".to_string())];
+
+        let mut second = triangle_mesh("second");
+        second
+            .materials
+            .insert("Stone".to_string(), PrincipledBSDF::default());
+        second
+            .materials
+            .insert("Moss".to_string(), PrincipledBSDF::default());
+        second.material_slots = vec![Some("Moss".to_string()), Some("Stone".to_string())];
+        second.multi_indexed_vertex_attributes.material_indices = vec![1];
+
+        let third = triangle_mesh("third");
+
+        let merged =
+            BlenderMesh::merge(vec![(&first).into(), (&second).into(), (&third).into()]).unwrap();
+
+        assert_eq!(merged.materials.len(), 2);
+        assert_eq!(
+            merged.material_slots,
+            vec![Some("Stone".to_string()), Some("Moss".to_string()), None]
+        );
+        assert_eq!(
+            merged.multi_indexed_vertex_attributes.material_indices,
+            vec![0, 0, 2]
+        );
+
+        second.materials.get_mut("Stone").unwrap().roughness = crate::MaterialInput::Uniform(0.123);
+        match BlenderMesh::merge(vec![(&first).into(), (&second).into()]) {
+            Err(MergeError::ConflictingMaterial { mesh, material }) => {
+                assert_eq!(mesh, "second");
+                assert_eq!(material, "Stone");
+            }
+            other => panic!("{:?}", other),
+        };
+    }
+
+    /// Verify that bone influences are kept for meshes that share an armature and that meshes
+    /// with different armatures can't be merged
+    #[test]
+    fn merge_bone_influences() {
+        let mut first = triangle_mesh("first");
+        first.armature_name = Some("Rig".to_string());
+        first.multi_indexed_vertex_attributes.bone_influences = Some(VertexBoneInfluences {
+            bones_per_vertex: BoneInfluencesPerVertex::Uniform(1),
+            bone_indices: vec![0, 1, 2],
+            bone_weights: vec![1.0, 1.0, 1.0],
+            bone_names: vec![],
+        });
+
+        let second = triangle_mesh("second");
+
+        let merged = BlenderMesh::merge(vec![(&first).into(), (&second).into()]).unwrap();
+
+        assert_eq!(merged.armature_name, Some("Rig".to_string()));
+        assert_eq!(
+            merged.multi_indexed_vertex_attributes.bone_influences,
+            Some(VertexBoneInfluences {
+                bones_per_vertex: BoneInfluencesPerVertex::NonUniform(vec![1, 1, 1, 0, 0, 0]),
+                bone_indices: vec![0, 1, 2],
+                bone_weights: vec![1.0, 1.0, 1.0],
+                bone_names: vec![],
+            })
+        );
+
+        let mut other_rig = second.clone();
+        other_rig.armature_name = Some("OtherRig".to_string());
+        match BlenderMesh::merge(vec![(&first).into(), (&other_rig).into()]) {
+            Err(MergeError::DifferentArmatures { mesh, .. }) => assert_eq!(mesh, "second"),
+            other => panic!("{:?}", other),
+        };
+    }
+
+    /// Verify that meshes that were exported with different bone orders are merged by bone name
+    #[test]
+    fn merge_bone_influences_by_name() {
+        let with_bones = |name: &str, bone_names: [&str; 2]| {
+            let mut mesh = triangle_mesh(name);
+            mesh.armature_name = Some("Rig".to_string());
+            mesh.multi_indexed_vertex_attributes.bone_influences = Some(VertexBoneInfluences {
+                bones_per_vertex: BoneInfluencesPerVertex::Uniform(1),
+                bone_indices: vec![0, 1, 1],
+                bone_weights: vec![1.0, 1.0, 1.0],
+                bone_names: bone_names.iter().map(|name| name.to_string()).collect(),
+            });
+            mesh
+        };
+
+        let first = with_bones("first", ["Hips", "Spine"]);
+        let second = with_bones("second", ["Head", "Hips"]);
+
+        let merged = BlenderMesh::merge(vec![(&first).into(), (&second).into()]).unwrap();
+        let influences = merged
+            .multi_indexed_vertex_attributes
+            .bone_influences
+            .unwrap();
+
+        assert_eq!(influences.bone_indices, vec![0, 1, 1, 2, 0, 0]);
+        assert_eq!(influences.bone_names, vec!["Hips", "Spine", "Head"]);
+    }
+
+    /// Verify that merging meshes that share the same bones keeps the armature's bone order
+    /// instead of the order that the bones are first used in
+    #[test]
+    fn merge_bone_influences_keeps_armature_order() {
+        let with_bones = |name: &str, bone_indices: Vec<u8>| {
+            let mut mesh = triangle_mesh(name);
+            mesh.armature_name = Some("Rig".to_string());
+            mesh.multi_indexed_vertex_attributes.bone_influences = Some(VertexBoneInfluences {
+                bones_per_vertex: BoneInfluencesPerVertex::Uniform(1),
+                bone_indices,
+                bone_weights: vec![1.0, 1.0, 1.0],
+                bone_names: vec!["Root".to_string(), "Spine".to_string(), "Hand".to_string()],
+            });
+            mesh
+        };
+
+        let first = with_bones("first", vec![2, 2, 2]);
+        let second = with_bones("second", vec![0, 1, 1]);
+
+        let merged = BlenderMesh::merge(vec![(&first).into(), (&second).into()]).unwrap();
+        let influences = merged
+            .multi_indexed_vertex_attributes
+            .bone_influences
+            .unwrap();
+
+        assert_eq!(influences.bone_indices, vec![2, 2, 2, 0, 1, 1]);
+        assert_eq!(influences.bone_names, vec!["Root", "Spine", "Hand"]);
+    }
+
+    /// A single triangle at the origin.
+    fn triangle_mesh(name: &str) -> BlenderMesh {
+        let positions = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+
+        BlenderMesh {
+            name: name.to_string(),
+            bounding_box: BoundingBox::from_positions(&positions),
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![3],
+                positions: IndexedAttribute {
+                    indices: vec![0, 1, 2],
+                    attribute: (positions, 3).into(),
+                },
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        }
+    }
+}
@@ -0,0 +1,594 @@
+//! Import meshes from Wavefront OBJ files.
+//!
+//! OBJ files store separate indices for a vertex's position, uv and normal, just like our
+//! `MultiIndexedVertexAttributes`, so an OBJ can be used anywhere that a mesh that was exported
+//! from Blender can.
+//!
+//! @see http://paulbourke.net/dataformats/obj/
+//! @see http://paulbourke.net/dataformats/mtl/
+
+use self::mtl::parse_mtl;
+use crate::vertex_attributes::IndexedAttribute;
+use crate::{BlenderMesh, BoundingBox, MultiIndexedVertexAttributes, VertexAttribute};
+use std::collections::HashMap;
+
+mod mtl;
+
+/// An error while parsing an OBJ or MTL file.
+///
+/// Line numbers start from 1.
+#[derive(Debug, thiserror::Error)]
+pub enum ObjError {
+    /// A line in the OBJ file could not be parsed.
+    #[error("Could not parse line {line} of the OBJ file: {content}")]
+    MalformedObjLine {
+        /// The line number
+        line: usize,
+        /// The contents of the line
+        content: String,
+    },
+    /// A line in the MTL file could not be parsed.
+    #[error("Could not parse line {line} of the MTL file: {content}")]
+    MalformedMtlLine {
+        /// The line number
+        line: usize,
+        /// The contents of the line
+        content: String,
+    },
+    /// A face referred to a position, uv or normal that has not been defined.
+    #[error("Face on line {line} refers to undefined vertex data {index}")]
+    IndexOutOfBounds {
+        /// The line number
+        line: usize,
+        /// The index as written in the file
+        index: i64,
+    },
+    /// Every vertex in every face needs to either have a uv index or not, and either have a
+    /// normal index or not, since each attribute has one index per face vertex.
+    #[error("Face on line {line} does not have the same uv and normal indices as earlier faces")]
+    InconsistentFaceVertex {
+        /// The line number
+        line: usize,
+    },
+    /// Faces can have at most 255 vertices.
+    #[error("Face on line {line} has {vertices} vertices, the most that we support is 255")]
+    FaceTooLarge {
+        /// The line number
+        line: usize,
+        /// The number of vertices in the face
+        vertices: usize,
+    },
+    /// There is more vertex data than can be referred to by a `u32` index.
+    #[error("Face on line {line} refers to index {index} which does not fit into a u32")]
+    IndexTooLarge {
+        /// The line number
+        line: usize,
+        /// The zero based index
+        index: usize,
+    },
+    /// A `usemtl` statement named a material that the MTL file does not define.
+    #[error("The OBJ file uses material {material} which is not defined in the MTL file")]
+    UndefinedMaterial {
+        /// The name of the material
+        material: String,
+    },
+}
+
+impl BlenderMesh {
+    /// Create a mesh from the contents of an OBJ file and, optionally, the MTL file that its
+    /// `mtllib` statement points to.
+    ///
+    /// The mesh is named after the first `o` statement in the file.
+    ///
+    /// Every material that is referenced by a `usemtl` statement is read from the MTL file.
+    ///
+    /// | MTL statement     | PrincipledBSDF input |
+    /// | ---               | ---                  |
+    /// | `Kd` / `map_Kd`   | base color           |
+    /// | `Pr` / `map_Pr`   | roughness            |
+    /// | `Pm` / `map_Pm`   | metallic             |
+    /// | `norm`            | normal map           |
+    ///
+    /// Inputs that aren't in the MTL file get the defaults of Blender's Principled BSDF node.
+    ///
+    /// Each material becomes a material slot, in the order that they are first used, and each
+    /// face uses the slot of the most recent `usemtl` statement.
+    ///
+    /// Vertex colors written as `v x y z r g b` become the mesh's colors, as long as every
+    /// position has one.
+    ///
+    /// Points, lines, smoothing groups and free form geometry are ignored.
+    pub fn from_obj(obj: &str, mtl: Option<&str>) -> Result<BlenderMesh, ObjError> {
+        let mut parser = ObjParser::default();
+
+        for (line_idx, line) in obj.lines().enumerate() {
+            parser.parse_line(line_idx + 1, line)?;
+        }
+
+        let defined_materials = match mtl {
+            Some(mtl) => parse_mtl(mtl)?,
+            None => HashMap::new(),
+        };
+
+        let ObjParser {
+            name,
+            positions,
+            colors,
+            normals,
+            uvs,
+            vertices_in_each_face,
+            position_indices,
+            uv_indices,
+            normal_indices,
+            used_materials,
+            face_materials,
+            ..
+        } = parser;
+
+        // Every distinct `usemtl` becomes a material slot, in the order that they were first used.
+        // Faces before the first `usemtl` get an empty slot.
+        let mut material_slots: Vec<Option<String>> = vec![];
+        let mut material_indices = vec![];
+        if !used_materials.is_empty() {
+            for material in face_materials {
+                let slot = match material_slots.iter().position(|slot| *slot == material) {
+                    Some(slot) => slot,
+                    None => {
+                        material_slots.push(material);
+                        material_slots.len() - 1
+                    }
+                };
+                material_indices.push(slot as u16);
+            }
+        }
+
+        let mut materials = HashMap::new();
+        for material in used_materials {
+            let bsdf = defined_materials.get(&material).cloned().ok_or_else(|| {
+                ObjError::UndefinedMaterial {
+                    material: material.clone(),
+                }
+            })?;
+            materials.insert(material, bsdf);
+        }
+
+        let bounding_box = BoundingBox::from_positions(&positions);
+
+        let normals = normal_indices.map(|indices| {
+            IndexedAttribute::new(
+                indices,
+                VertexAttribute {
+                    data: normals,
+                    attribute_size: 3,
+                },
+            )
+        });
+        // Colors are stored alongside positions so they share the position indices.
+        let colors = if colors.len() / 4 == positions.len() / 3 && !colors.is_empty() {
+            Some(IndexedAttribute::new(
+                position_indices.clone(),
+                VertexAttribute {
+                    data: colors,
+                    attribute_size: 4,
+                },
+            ))
+        } else {
+            None
+        };
+        let uvs = uv_indices.map(|indices| {
+            IndexedAttribute::new(
+                indices,
+                VertexAttribute {
+                    data: uvs,
+                    attribute_size: 2,
+                },
+            )
+        });
+
+        Ok(BlenderMesh {
+            name: name.unwrap_or_default(),
+            bounding_box,
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face,
+                positions: IndexedAttribute::new(
+                    position_indices,
+                    VertexAttribute {
+                        data: positions,
+                        attribute_size: 3,
+                    },
+                ),
+                normals,
+                uvs,
+                bone_influences: None,
+                colors,
+                material_indices,
+                ..MultiIndexedVertexAttributes::default()
+            },
+            materials,
+            material_slots,
+            ..BlenderMesh::default()
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct ObjParser {
+    name: Option<String>,
+    positions: Vec<f32>,
+    // RGBA colors of the positions that had an `r g b` after them.
+    colors: Vec<f32>,
+    normals: Vec<f32>,
+    uvs: Vec<f32>,
+    vertices_in_each_face: Vec<u8>,
+    position_indices: Vec<u32>,
+    // These are `None` until the first face tells us whether or not it has uvs / normals.
+    uv_indices: Option<Vec<u32>>,
+    normal_indices: Option<Vec<u32>>,
+    used_materials: Vec<String>,
+    // The material from the most recent `usemtl` statement.
+    current_material: Option<String>,
+    // The material that was in use when each face was parsed.
+    face_materials: Vec<Option<String>>,
+}
+
+impl ObjParser {
+    fn parse_line(&mut self, line_number: usize, line: &str) -> Result<(), ObjError> {
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+
+        let malformed = || ObjError::MalformedObjLine {
+            line: line_number,
+            content: line.to_string(),
+        };
+
+        match tokens.next() {
+            Some("v") => {
+                let position = parse_floats(tokens, 3).ok_or_else(malformed)?;
+                self.positions.extend_from_slice(&position[0..3]);
+
+                // A fourth number on its own is a rational curve weight, not a color.
+                if position.len() >= 6 {
+                    self.colors.extend_from_slice(&position[3..6]);
+                    self.colors.push(1.0);
+                }
+            }
+            Some("vn") => {
+                let normal = parse_floats(tokens, 3).ok_or_else(malformed)?;
+                self.normals.extend_from_slice(&normal[0..3]);
+            }
+            Some("vt") => {
+                // The v coordinate is optional and defaults to 0.
+                let mut uv = parse_floats(tokens, 1).ok_or_else(malformed)?;
+                uv.resize(2, 0.0);
+                self.uvs.extend_from_slice(&uv[0..2]);
+            }
+            Some("f") => {
+                self.parse_face(line_number, tokens.collect())?;
+            }
+            Some("o") if self.name.is_none() => {
+                self.name = Some(tokens.collect::<Vec<_>>().join(" "));
+            }
+            Some("usemtl") => {
+                let material = tokens.collect::<Vec<_>>().join(" ");
+                if material.is_empty() {
+                    return Err(malformed());
+                }
+
+                if !self.used_materials.contains(&material) {
+                    self.used_materials.push(material.clone());
+                }
+                self.current_material = Some(material);
+            }
+            _ => {}
+        };
+
+        Ok(())
+    }
+
+    fn parse_face(&mut self, line_number: usize, face_vertices: Vec<&str>) -> Result<(), ObjError> {
+        if face_vertices.len() < 3 {
+            return Err(ObjError::MalformedObjLine {
+                line: line_number,
+                content: format!("f {}", face_vertices.join(" ")),
+            });
+        }
+        if face_vertices.len() > u8::MAX as usize {
+            return Err(ObjError::FaceTooLarge {
+                line: line_number,
+                vertices: face_vertices.len(),
+            });
+        }
+
+        let mut face = vec![];
+        for face_vertex in face_vertices.iter() {
+            face.push(self.parse_face_vertex(line_number, face_vertex)?);
+        }
+
+        let has_uvs = face[0].1.is_some();
+        let has_normals = face[0].2.is_some();
+
+        // The first face decides which attributes every face vertex has.
+        if self.vertices_in_each_face.is_empty() {
+            self.uv_indices = if has_uvs { Some(vec![]) } else { None };
+            self.normal_indices = if has_normals { Some(vec![]) } else { None };
+        }
+
+        for (position, uv, normal) in face {
+            if uv.is_some() != self.uv_indices.is_some()
+                || normal.is_some() != self.normal_indices.is_some()
+            {
+                return Err(ObjError::InconsistentFaceVertex { line: line_number });
+            }
+
+            self.position_indices.push(position);
+            if let (Some(uv_indices), Some(uv)) = (self.uv_indices.as_mut(), uv) {
+                uv_indices.push(uv);
+            }
+            if let (Some(normal_indices), Some(normal)) = (self.normal_indices.as_mut(), normal) {
+                normal_indices.push(normal);
+            }
+        }
+
+        self.vertices_in_each_face.push(face_vertices.len() as u8);
+        self.face_materials.push(self.current_material.clone());
+
+        Ok(())
+    }
+
+    /// Parse `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero based indices.
+    fn parse_face_vertex(
+        &self,
+        line_number: usize,
+        face_vertex: &str,
+    ) -> Result<(u32, Option<u32>, Option<u32>), ObjError> {
+        let mut indices = face_vertex.split('/');
+
+        let mut resolve =
+            |data_len: usize, attribute_size: usize| -> Result<Option<u32>, ObjError> {
+                let index = match indices.next() {
+                    Some(index) if !index.is_empty() => index,
+                    _ => return Ok(None),
+                };
+
+                let index: i64 = index.parse().map_err(|_| ObjError::MalformedObjLine {
+                    line: line_number,
+                    content: face_vertex.to_string(),
+                })?;
+
+                Ok(Some(resolve_index(
+                    line_number,
+                    index,
+                    data_len / attribute_size,
+                )?))
+            };
+
+        let position = resolve(self.positions.len(), 3)?.ok_or(ObjError::MalformedObjLine {
+            line: line_number,
+            content: face_vertex.to_string(),
+        })?;
+        let uv = resolve(self.uvs.len(), 2)?;
+        let normal = resolve(self.normals.len(), 3)?;
+
+        Ok((position, uv, normal))
+    }
+}
+
+/// OBJ indices start from 1, and negative indices count backwards from the most recently defined
+/// data.
+fn resolve_index(line: usize, index: i64, defined: usize) -> Result<u32, ObjError> {
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        defined as i64 + index
+    };
+
+    if index == 0 || resolved < 0 || resolved >= defined as i64 {
+        return Err(ObjError::IndexOutOfBounds { line, index });
+    }
+
+    if resolved > u32::MAX as i64 {
+        return Err(ObjError::IndexTooLarge {
+            line,
+            index: resolved as usize,
+        });
+    }
+
+    Ok(resolved as u32)
+}
+
+/// Parse the rest of the line's tokens as floats, returning `None` if there are fewer than
+/// `min_count` or one of them isn't a number.
+fn parse_floats<'a>(tokens: impl Iterator<Item = &'a str>, min_count: usize) -> Option<Vec<f32>> {
+    let floats = tokens
+        .map(|token| token.parse().ok())
+        .collect::<Option<Vec<f32>>>()?;
+
+    if floats.len() < min_count {
+        return None;
+    }
+
+    Some(floats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CreateSingleIndexConfig, MaterialInput};
+
+    const QUAD_AND_TRIANGLE: &str = r#"
+# A quad and a triangle that share an edge
+mtllib quad.mtl
+o QuadAndTriangle
+v 0.0 0.0 0.0
+v 1.0 0.0 0.0
+v 1.0 1.0 0.0
+v 0.0 1.0 0.0
+v 2.0 0.5 -1.0
+vt 0.0 0.0
+vt 1.0 0.0
+vt 1.0 1.0
+vt 0.0 1.0
+vn 0.0 0.0 1.0
+usemtl Red
+s off
+f 1/1/1 2/2/1 3/3/1 4/4/1
+f -4/2/-1 -1/3/-1 -3/3/-1
+"#;
+
+    const MTL: &str = r#"
+newmtl Red
+Kd 1.0 0.0 0.0
+Pr 0.25
+Pm 1.0
+
+newmtl Unused
+Kd 0.0 1.0 0.0
+"#;
+
+    /// Verify that we keep the OBJ's separate indices
+    #[test]
+    fn multi_indexed_attributes() {
+        let mesh = BlenderMesh::from_obj(QUAD_AND_TRIANGLE, Some(MTL)).unwrap();
+        let multi = &mesh.multi_indexed_vertex_attributes;
+
+        assert_eq!(mesh.name(), "QuadAndTriangle");
+        assert_eq!(multi.vertices_in_each_face, vec![4, 3]);
+        assert_eq!(multi.positions.indices, vec![0, 1, 2, 3, 1, 4, 2]);
+        assert_eq!(multi.positions.attribute.data.len(), 15);
+        assert_eq!(
+            multi.uvs.as_ref().unwrap().indices,
+            vec![0, 1, 2, 3, 1, 2, 2]
+        );
+        assert_eq!(
+            multi.normals.as_ref().unwrap().indices,
+            vec![0, 0, 0, 0, 0, 0, 0]
+        );
+
+        assert_eq!(mesh.bounding_box().min_corner, [0.0, 0.0, -1.0].into());
+        assert_eq!(mesh.bounding_box().max_corner, [2.0, 1.0, 0.0].into());
+    }
+
+    /// Verify that we create a material for each `usemtl`
+    #[test]
+    fn used_materials() {
+        let mesh = BlenderMesh::from_obj(QUAD_AND_TRIANGLE, Some(MTL)).unwrap();
+
+        assert_eq!(mesh.materials().len(), 1);
+
+        let red = &mesh.materials()["Red"];
+        assert_eq!(red.base_color(), &MaterialInput::Uniform([1.0, 0.0, 0.0]));
+        assert_eq!(red.roughness(), &MaterialInput::Uniform(0.25));
+        assert_eq!(red.metallic(), &MaterialInput::Uniform(1.0));
+    }
+
+    /// Verify that each face uses the slot of the material from the most recent `usemtl`
+    #[test]
+    fn face_material_indices() {
+        let obj = r#"
+v 0 0 0
+v 1 0 0
+v 0 1 0
+f 1 2 3
+usemtl Red
+f 1 2 3
+usemtl Green
+f 1 2 3
+usemtl Red
+f 1 2 3
+"#;
+        let mtl = "newmtl Red\nKd 1 0 0\nnewmtl Green\nKd 0 1 0";
+
+        let mesh = BlenderMesh::from_obj(obj, Some(mtl)).unwrap();
+
+        assert_eq!(
+            mesh.material_slots(),
+            &vec![None, Some("Red".to_string()), Some("Green".to_string())]
+        );
+        assert_eq!(
+            mesh.multi_indexed_vertex_attributes.material_indices(),
+            &vec![0, 1, 2, 1]
+        );
+    }
+
+    /// Verify that an imported OBJ can be combined into a single index, re-using the vertices on
+    /// the shared edge
+    #[test]
+    fn combine_obj_indices() {
+        let mut mesh = BlenderMesh::from_obj(QUAD_AND_TRIANGLE, Some(MTL)).unwrap();
+
+        let single = mesh
+            .combine_vertex_indices(&CreateSingleIndexConfig::default())
+            .unwrap();
+
+        assert_eq!(single.indices().len(), 9);
+        assert_eq!(single.vertices().len(), 5);
+    }
+
+    /// Verify that faces without uvs or normals lead to a mesh without uvs or normals
+    #[test]
+    fn positions_only() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3";
+
+        let mesh = BlenderMesh::from_obj(obj, None).unwrap();
+
+        assert!(mesh.multi_indexed_vertex_attributes.uvs.is_none());
+        assert!(mesh.multi_indexed_vertex_attributes.normals.is_none());
+    }
+
+    /// Verify that colors after each position become vertex colors that use the position indices
+    #[test]
+    fn vertex_colors() {
+        let obj = "v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0 0 0 1\nf 3 2 1";
+
+        let mesh = BlenderMesh::from_obj(obj, None).unwrap();
+        let colors = mesh.multi_indexed_vertex_attributes.colors().unwrap();
+
+        assert_eq!(colors.indices(), &vec![2, 1, 0]);
+        assert_eq!(colors.attribute().attribute_size, 4);
+        assert_eq!(&colors.attribute().data[8..12], &[0.0, 0.0, 1.0, 1.0]);
+    }
+
+    /// Verify that we don't create colors when only some positions have them
+    #[test]
+    fn partial_vertex_colors_ignored() {
+        let obj = "v 0 0 0 1 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3";
+
+        let mesh = BlenderMesh::from_obj(obj, None).unwrap();
+
+        assert!(mesh.multi_indexed_vertex_attributes.colors().is_none());
+    }
+
+    /// Verify that we return an error when faces mix having and not having uvs
+    #[test]
+    fn error_if_inconsistent_face_vertices() {
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nf 1/1 2/1 3/1\nf 1 2 3";
+
+        match BlenderMesh::from_obj(obj, None) {
+            Err(ObjError::InconsistentFaceVertex { line }) => assert_eq!(line, 6),
+            _ => unreachable!(),
+        };
+    }
+
+    /// Verify that we return an error when a face refers to a vertex that doesn't exist
+    #[test]
+    fn error_if_index_out_of_bounds() {
+        let obj = "v 0 0 0\nv 1 0 0\nf 1 2 3";
+
+        match BlenderMesh::from_obj(obj, None) {
+            Err(ObjError::IndexOutOfBounds { line, index }) => {
+                assert_eq!(line, 3);
+                assert_eq!(index, 3);
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    /// Verify that we return an error when a used material isn't in the MTL file
+    #[test]
+    fn error_if_material_undefined() {
+        match BlenderMesh::from_obj(QUAD_AND_TRIANGLE, None) {
+            Err(ObjError::UndefinedMaterial { material }) => assert_eq!(material, "Red"),
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,185 @@
+use super::ObjError;
+use crate::{Channel, MaterialInput, PrincipledBSDF};
+use std::collections::HashMap;
+
+/// Parse every material in an MTL file.
+///
+/// Roughness and metallic textures are read from the channel that an `-imfchan` option points
+/// to, or otherwise from the green and blue channel respectively.
+pub(super) fn parse_mtl(mtl: &str) -> Result<HashMap<String, PrincipledBSDF>, ObjError> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, PrincipledBSDF)> = None;
+
+    for (line_idx, line) in mtl.lines().enumerate() {
+        let line = line.split('#').next().unwrap();
+        let mut tokens = line.split_whitespace();
+
+        let malformed = || ObjError::MalformedMtlLine {
+            line: line_idx + 1,
+            content: line.to_string(),
+        };
+
+        let statement = match tokens.next() {
+            Some(statement) => statement,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if statement == "newmtl" {
+            if args.is_empty() {
+                return Err(malformed());
+            }
+
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material);
+            }
+            current = Some((args.join(" "), default_material()));
+
+            continue;
+        }
+
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None if is_material_statement(statement) => return Err(malformed()),
+            None => continue,
+        };
+
+        match statement {
+            "Kd" => {
+                let rgb = parse_floats(&args, 3).ok_or_else(malformed)?;
+                material.base_color = MaterialInput::Uniform([rgb[0], rgb[1], rgb[2]]);
+            }
+            "map_Kd" => {
+                let (image, _) = parse_texture(&args).ok_or_else(malformed)?;
+                material.base_color = MaterialInput::ImageTexture(image);
+            }
+            "Pr" => {
+                let roughness = parse_floats(&args, 1).ok_or_else(malformed)?;
+                material.roughness = MaterialInput::Uniform(roughness[0]);
+            }
+            "map_Pr" => {
+                let (image, channel) = parse_texture(&args).ok_or_else(malformed)?;
+                material.roughness =
+                    MaterialInput::ImageTexture((image, channel.unwrap_or(Channel::Green)));
+            }
+            "Pm" => {
+                let metallic = parse_floats(&args, 1).ok_or_else(malformed)?;
+                material.metallic = MaterialInput::Uniform(metallic[0]);
+            }
+            "map_Pm" => {
+                let (image, channel) = parse_texture(&args).ok_or_else(malformed)?;
+                material.metallic =
+                    MaterialInput::ImageTexture((image, channel.unwrap_or(Channel::Blue)));
+            }
+            "norm" => {
+                let (image, _) = parse_texture(&args).ok_or_else(malformed)?;
+                material.normal_map = Some(image);
+            }
+            _ => {}
+        };
+    }
+
+    if let Some((name, material)) = current.take() {
+        materials.insert(name, material);
+    }
+
+    Ok(materials)
+}
+
+/// The defaults of Blender's Principled BSDF node
+fn default_material() -> PrincipledBSDF {
+    PrincipledBSDF::new(
+        MaterialInput::Uniform([0.8, 0.8, 0.8]),
+        MaterialInput::Uniform(0.5),
+        MaterialInput::Uniform(0.0),
+        None,
+    )
+}
+
+fn is_material_statement(statement: &str) -> bool {
+    matches!(
+        statement,
+        "Kd" | "map_Kd" | "Pr" | "map_Pr" | "Pm" | "map_Pm" | "norm"
+    )
+}
+
+fn parse_floats(args: &[&str], count: usize) -> Option<Vec<f32>> {
+    if args.len() < count {
+        return None;
+    }
+
+    args[0..count].iter().map(|arg| arg.parse().ok()).collect()
+}
+
+/// Texture statements are a series of options followed by the filename, such as
+/// `map_Pr -imfchan g -bm 1.0 textures/roughness.png`.
+///
+/// We return the file's name without its directories along with the channel from `-imfchan`.
+fn parse_texture(args: &[&str]) -> Option<(String, Option<Channel>)> {
+    let path = args.last()?;
+    let image = path.rsplit(&['/', '\\'][..]).next()?;
+
+    let channel = args
+        .iter()
+        .position(|arg| *arg == "-imfchan")
+        .and_then(|idx| args.get(idx + 1))
+        .and_then(|channel| match *channel {
+            "r" => Some(Channel::Red),
+            "g" => Some(Channel::Green),
+            "b" => Some(Channel::Blue),
+            _ => None,
+        });
+
+    Some((image.to_string(), channel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that texture statements become image texture inputs
+    #[test]
+    fn texture_inputs() {
+        let mtl = r#"
+newmtl Textured
+map_Kd -s 1 1 1 textures/color.png
+map_Pr -imfchan r textures/packed.png
+map_Pm textures/packed.png
+norm C:\textures\normal.png
+"#;
+
+        let materials = parse_mtl(mtl).unwrap();
+        let textured = &materials["Textured"];
+
+        assert_eq!(
+            textured.base_color(),
+            &MaterialInput::ImageTexture("color.png".to_string())
+        );
+        assert_eq!(
+            textured.roughness(),
+            &MaterialInput::ImageTexture(("packed.png".to_string(), Channel::Red))
+        );
+        assert_eq!(
+            textured.metallic(),
+            &MaterialInput::ImageTexture(("packed.png".to_string(), Channel::Blue))
+        );
+        assert_eq!(textured.normal_map(), Some(&"normal.png".to_string()));
+    }
+
+    /// Verify that inputs that aren't in the file use the Principled BSDF defaults
+    #[test]
+    fn default_inputs() {
+        let materials = parse_mtl("newmtl Empty\nNs 250.0").unwrap();
+
+        assert_eq!(materials["Empty"], default_material());
+    }
+
+    /// Verify that we return an error for material statements that come before any `newmtl`
+    #[test]
+    fn error_if_statement_before_newmtl() {
+        match parse_mtl("Kd 1 1 1") {
+            Err(ObjError::MalformedMtlLine { line, .. }) => assert_eq!(line, 1),
+            _ => unreachable!(),
+        };
+    }
+}
@@ -0,0 +1,614 @@
+//! Reorder a mesh's triangles and vertices so that the GPU can render it faster.
+//!
+//! Each pass returns the ACMR and ATVR of the mesh before and after the pass, so that you can
+//! measure how much it helped.
+//!
+//! @see https://tomforsyth1000.github.io/papers/fast_vert_cache_opt.html
+//! @see https://gfx.cs.princeton.edu/pubs/Sander_2007_%3ETR/tipsy.pdf
+
+use crate::SingleIndexedVertexAttributes;
+use nalgebra::Vector3;
+use std::collections::VecDeque;
+
+/// The largest cache that [`SingleIndexedVertexAttributes::optimize_vertex_cache`] will model.
+///
+/// [`SingleIndexedVertexAttributes::optimize_vertex_cache`]: struct.SingleIndexedVertexAttributes.html#method.optimize_vertex_cache
+pub const MAX_VERTEX_CACHE_SIZE: usize = 64;
+
+// Tuned values from Tom Forsyth's paper.
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+/// How well a mesh's indices make use of a FIFO post-transform vertex cache.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VertexCacheStats {
+    /// Average cache miss ratio - the number of vertices that get transformed per triangle.
+    ///
+    /// Ranges from 3.0 in the worst case down to about 0.5 for a large, well ordered grid.
+    pub acmr: f32,
+    /// Average transform to vertex ratio - the number of times that each vertex gets transformed.
+    ///
+    /// 1.0 is ideal.
+    pub atvr: f32,
+}
+
+/// The [`VertexCacheStats`] of a mesh before and after an optimization pass.
+///
+/// [`VertexCacheStats`]: struct.VertexCacheStats.html
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptimizationStats {
+    /// The stats before the pass
+    pub before: VertexCacheStats,
+    /// The stats after the pass
+    pub after: VertexCacheStats,
+}
+
+impl SingleIndexedVertexAttributes {
+    /// Simulate a FIFO post-transform vertex cache with `cache_size` entries to see how well
+    /// the indices make use of it.
+    ///
+    /// Typical hardware caches hold somewhere between 16 and 32 vertices.
+    pub fn vertex_cache_stats(&self, cache_size: usize) -> VertexCacheStats {
+        vertex_cache_stats(&self.indices, self.vertices.len(), cache_size)
+    }
+
+    /// Reorder the triangles so that vertices get re-used while they are still in the
+    /// post-transform vertex cache, using Tom Forsyth's linear-speed vertex cache optimization.
+    ///
+    /// `cache_size` is the number of vertices in the cache, up to [`MAX_VERTEX_CACHE_SIZE`].
+    ///
+    /// Triangles are only reordered within their [`MaterialRange`].
+    ///
+    /// [`MaterialRange`]: struct.MaterialRange.html
+    /// [`MAX_VERTEX_CACHE_SIZE`]: constant.MAX_VERTEX_CACHE_SIZE.html
+    pub fn optimize_vertex_cache(&mut self, cache_size: usize) -> OptimizationStats {
+        let before = self.vertex_cache_stats(cache_size);
+
+        for range in self.index_ranges() {
+            let optimized = forsyth(
+                &self.indices[range.clone()],
+                self.vertices.len(),
+                cache_size,
+            );
+            self.indices[range].copy_from_slice(&optimized);
+        }
+
+        OptimizationStats {
+            before,
+            after: self.vertex_cache_stats(cache_size),
+        }
+    }
+
+    /// Reorder the vertices into the order that the indices first use them in, so that
+    /// vertices that get fetched together sit next to each other in memory.
+    ///
+    /// Run this after [`optimize_vertex_cache`] and [`optimize_overdraw`], since those change the
+    /// order of the triangles.
+    ///
+    /// Vertices that are not used by any triangle are moved to the end.
+    ///
+    /// The triangles are not reordered, so the stats are only reported for consistency with the
+    /// other passes.
+    ///
+    /// [`optimize_vertex_cache`]: #method.optimize_vertex_cache
+    /// [`optimize_overdraw`]: #method.optimize_overdraw
+    pub fn optimize_vertex_fetch(&mut self, cache_size: usize) -> OptimizationStats {
+        let before = self.vertex_cache_stats(cache_size);
+
+        let mut remap = vec![None; self.vertices.len()];
+        let mut order = Vec::with_capacity(self.vertices.len());
+
+        for index in self.indices.iter() {
+            if remap[*index as usize].is_none() {
+                remap[*index as usize] = Some(order.len() as u32);
+                order.push(*index as usize);
+            }
+        }
+        for (old_idx, new_idx) in remap.iter_mut().enumerate() {
+            if new_idx.is_none() {
+                *new_idx = Some(order.len() as u32);
+                order.push(old_idx);
+            }
+        }
+
+        for index in self.indices.iter_mut() {
+            *index = remap[*index as usize].unwrap();
+        }
+
+        self.reorder_vertices(&order);
+
+        OptimizationStats {
+            before,
+            after: self.vertex_cache_stats(cache_size),
+        }
+    }
+
+    /// Reorder clusters of triangles so that the ones that face outwards get drawn first,
+    /// which lets the depth test reject more of the pixels that would otherwise get shaded and
+    /// then overwritten.
+    ///
+    /// Run this after [`optimize_vertex_cache`]. Clusters are split wherever the vertex cache
+    /// starts over, so the vertex cache ordering within each cluster is kept.
+    ///
+    /// Moving clusters around can still cost some vertex cache hits. The new order is only kept
+    /// if its ACMR is at most `threshold` times the old ACMR. 1.05 is a good starting point.
+    ///
+    /// Triangles are only reordered within their [`MaterialRange`].
+    ///
+    /// [`MaterialRange`]: struct.MaterialRange.html
+    /// [`optimize_vertex_cache`]: #method.optimize_vertex_cache
+    pub fn optimize_overdraw(&mut self, cache_size: usize, threshold: f32) -> OptimizationStats {
+        let before = self.vertex_cache_stats(cache_size);
+
+        let mesh_centroid = self.triangles_centroid(0..self.indices.len() / 3);
+        let mut reordered = Vec::with_capacity(self.indices.len());
+
+        // Clusters are only moved around within their material's range.
+        for range in self.index_ranges() {
+            let first_triangle = range.start / 3;
+            let clusters = cache_clusters(&self.indices[range], self.vertices.len(), cache_size);
+
+            let mut sorted = clusters
+                .iter()
+                .map(|cluster| {
+                    let cluster = first_triangle + cluster.start..first_triangle + cluster.end;
+                    let centroid = self.triangles_centroid(cluster.clone());
+                    let normal = self.triangles_normal(cluster.clone());
+
+                    let sort_key = (centroid - mesh_centroid).dot(&normal);
+
+                    (sort_key, cluster)
+                })
+                .collect::<Vec<_>>();
+            // Stable, so that clusters that face the same way keep their order.
+            sorted.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+            for (_, cluster) in sorted {
+                reordered.extend_from_slice(&self.indices[cluster.start * 3..cluster.end * 3]);
+            }
+        }
+
+        let after = vertex_cache_stats(&reordered, self.vertices.len(), cache_size);
+        if after.acmr <= before.acmr * threshold {
+            self.indices = reordered;
+        }
+
+        OptimizationStats {
+            before,
+            after: self.vertex_cache_stats(cache_size),
+        }
+    }
+
+    /// The area weighted centroid of a range of triangles.
+    fn triangles_centroid(&self, triangles: std::ops::Range<usize>) -> Vector3<f32> {
+        let mut centroid = Vector3::zeros();
+        let mut total_area = 0.0;
+
+        for triangle in triangles {
+            let [a, b, c] = self.triangle_positions(triangle);
+            let area = (b - a).cross(&(c - a)).norm();
+
+            centroid += (a + b + c) / 3.0 * area;
+            total_area += area;
+        }
+
+        if total_area > 0.0 {
+            centroid /= total_area;
+        }
+
+        centroid
+    }
+
+    /// The normalized, area weighted normal of a range of triangles.
+    fn triangles_normal(&self, triangles: std::ops::Range<usize>) -> Vector3<f32> {
+        let mut normal = Vector3::zeros();
+
+        for triangle in triangles {
+            let [a, b, c] = self.triangle_positions(triangle);
+            normal += (b - a).cross(&(c - a));
+        }
+
+        normal.try_normalize(0.0).unwrap_or(normal)
+    }
+
+    fn triangle_positions(&self, triangle: usize) -> [Vector3<f32>; 3] {
+        let corner = |idx: usize| {
+            Vector3::from(self.vertices[self.indices[triangle * 3 + idx] as usize].position)
+        };
+
+        [corner(0), corner(1), corner(2)]
+    }
+}
+
+fn vertex_cache_stats(indices: &[u32], vertex_count: usize, cache_size: usize) -> VertexCacheStats {
+    let mut cache = VecDeque::with_capacity(cache_size);
+    let mut misses = 0;
+
+    for index in indices {
+        if !cache.contains(index) {
+            misses += 1;
+
+            if cache.len() == cache_size {
+                cache.pop_front();
+            }
+            cache.push_back(*index);
+        }
+    }
+
+    let triangle_count = indices.len() / 3;
+
+    VertexCacheStats {
+        acmr: if triangle_count == 0 {
+            0.0
+        } else {
+            misses as f32 / triangle_count as f32
+        },
+        atvr: if vertex_count == 0 {
+            0.0
+        } else {
+            misses as f32 / vertex_count as f32
+        },
+    }
+}
+
+/// Split the triangles into clusters wherever every corner of a triangle misses the vertex
+/// cache, since those are the points where the cache has started over.
+fn cache_clusters(
+    indices: &[u32],
+    vertex_count: usize,
+    cache_size: usize,
+) -> Vec<std::ops::Range<usize>> {
+    let mut clusters = vec![];
+    let mut cluster_start = 0;
+
+    // The time at which each vertex was last put into the cache.
+    let mut cache_timestamps = vec![None; vertex_count];
+    let mut time: usize = 0;
+
+    for (triangle, corners) in indices.chunks(3).enumerate() {
+        let mut misses = 0;
+
+        for index in corners {
+            let in_cache = match cache_timestamps[*index as usize] {
+                Some(timestamp) => time - timestamp < cache_size,
+                None => false,
+            };
+
+            if !in_cache {
+                cache_timestamps[*index as usize] = Some(time);
+                time += 1;
+                misses += 1;
+            }
+        }
+
+        if misses == 3 && triangle != cluster_start {
+            clusters.push(cluster_start..triangle);
+            cluster_start = triangle;
+        }
+    }
+
+    if cluster_start < indices.len() / 3 {
+        clusters.push(cluster_start..indices.len() / 3);
+    }
+
+    clusters
+}
+
+/// Tom Forsyth's linear-speed vertex cache optimization.
+///
+/// Repeatedly emits the highest scoring triangle, where a triangle's score is the sum of the
+/// scores of its vertices. Vertices that are in the (LRU) cache score higher, as do vertices that
+/// only have a few triangles left to emit, so that we don't leave lone triangles behind.
+fn forsyth(indices: &[u32], vertex_count: usize, cache_size: usize) -> Vec<u32> {
+    let cache_size = cache_size.clamp(4, MAX_VERTEX_CACHE_SIZE);
+    let triangle_count = indices.len() / 3;
+
+    let mut vertex_triangles = vec![vec![]; vertex_count];
+    for (triangle, corners) in indices.chunks(3).enumerate() {
+        for index in corners {
+            vertex_triangles[*index as usize].push(triangle);
+        }
+    }
+
+    let mut remaining_triangles = vertex_triangles
+        .iter()
+        .map(|triangles| triangles.len())
+        .collect::<Vec<_>>();
+    let mut cache_position = vec![None; vertex_count];
+
+    let mut vertex_scores = (0..vertex_count)
+        .map(|vertex| vertex_score(None, remaining_triangles[vertex], cache_size))
+        .collect::<Vec<_>>();
+
+    let mut triangle_scores = indices
+        .chunks(3)
+        .map(|corners| {
+            corners
+                .iter()
+                .map(|index| vertex_scores[*index as usize])
+                .sum::<f32>()
+        })
+        .collect::<Vec<f32>>();
+    let mut emitted = vec![false; triangle_count];
+
+    let mut optimized = Vec::with_capacity(indices.len());
+    // The LRU cache, most recently used first. Holds 3 extra entries so that vertices that are
+    // about to be pushed out can still have their scores updated.
+    let mut cache: Vec<u32> = Vec::with_capacity(cache_size + 3);
+    // Used to find the next triangle when none of the triangles in the cache are left.
+    let mut dead_end_cursor = 0;
+
+    let mut next_triangle =
+        next_unemitted_triangle(&triangle_scores, &emitted, &mut dead_end_cursor);
+
+    while let Some(triangle) = next_triangle {
+        emitted[triangle] = true;
+
+        let corners = &indices[triangle * 3..triangle * 3 + 3];
+        optimized.extend_from_slice(corners);
+
+        for index in corners {
+            remaining_triangles[*index as usize] -= 1;
+            let triangles = &mut vertex_triangles[*index as usize];
+            triangles.retain(|t| *t != triangle);
+
+            if let Some(position) = cache.iter().position(|cached| cached == index) {
+                cache.remove(position);
+            }
+        }
+        for index in corners.iter().rev() {
+            cache.insert(0, *index);
+        }
+
+        let evicted = if cache.len() > cache_size {
+            cache.split_off(cache_size)
+        } else {
+            vec![]
+        };
+
+        for (position, index) in cache.iter().enumerate() {
+            cache_position[*index as usize] = Some(position);
+        }
+        for index in evicted.iter() {
+            cache_position[*index as usize] = None;
+        }
+
+        // Re-score every vertex whose cache position changed, along with its triangles.
+        for index in cache.iter().chain(evicted.iter()) {
+            let vertex = *index as usize;
+            let score = vertex_score(
+                cache_position[vertex],
+                remaining_triangles[vertex],
+                cache_size,
+            );
+            let delta = score - vertex_scores[vertex];
+            vertex_scores[vertex] = score;
+
+            for triangle in vertex_triangles[vertex].iter() {
+                triangle_scores[*triangle] += delta;
+            }
+        }
+
+        next_triangle = cache
+            .iter()
+            .flat_map(|index| vertex_triangles[*index as usize].iter())
+            .copied()
+            .fold(None, |best: Option<usize>, triangle| match best {
+                Some(best) if triangle_scores[best] >= triangle_scores[triangle] => Some(best),
+                _ => Some(triangle),
+            })
+            .or_else(|| next_unemitted_triangle(&triangle_scores, &emitted, &mut dead_end_cursor));
+    }
+
+    optimized
+}
+
+/// The first triangle that has not been emitted yet. Only used when every triangle that uses a
+/// cached vertex has already been emitted, so any triangle is as good as any other.
+fn next_unemitted_triangle(
+    triangle_scores: &[f32],
+    emitted: &[bool],
+    dead_end_cursor: &mut usize,
+) -> Option<usize> {
+    while *dead_end_cursor < triangle_scores.len() {
+        if !emitted[*dead_end_cursor] {
+            return Some(*dead_end_cursor);
+        }
+        *dead_end_cursor += 1;
+    }
+
+    None
+}
+
+fn vertex_score(
+    cache_position: Option<usize>,
+    remaining_triangles: usize,
+    cache_size: usize,
+) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        // The most recent triangle's vertices get a fixed score so that we don't favor
+        // re-using the same triangle's edge over and over.
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scale = 1.0 / (cache_size - 3) as f32;
+            (1.0 - (position - 3) as f32 * scale).powf(CACHE_DECAY_POWER)
+        }
+        None => 0.0,
+    };
+
+    let valence_boost =
+        VALENCE_BOOST_SCALE * (remaining_triangles as f32).powf(-VALENCE_BOOST_POWER);
+
+    cache_score + valence_boost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Vertex, VertexAttribute};
+
+    /// Verify that we calculate the ACMR and ATVR of a FIFO cache
+    #[test]
+    fn cache_stats() {
+        let mesh = SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 2, 2, 1, 3, 4, 5, 0],
+            vertices: vec![Vertex::default(); 6],
+            ..SingleIndexedVertexAttributes::default()
+        };
+
+        // 0, 1, 2, 3 miss, 4 and 5 miss and push 0 and 1 out, then 0 misses again.
+        let stats = mesh.vertex_cache_stats(4);
+        assert_eq!(stats.acmr, 7. / 3.);
+        assert_eq!(stats.atvr, 7. / 6.);
+    }
+
+    /// Verify that optimizing a grid whose triangles are in a cache unfriendly order lowers the
+    /// ACMR, without adding or losing any triangles.
+    #[test]
+    fn optimize_grid_vertex_cache() {
+        let mut mesh = grid(20);
+        // Go down the columns, so that each row's vertices have been pushed out of the cache by
+        // the time that we come back to them.
+        mesh.indices = column_major(&mesh.indices, 20);
+
+        let before_triangles = sorted_triangles(&mesh.indices);
+        let stats = mesh.optimize_vertex_cache(16);
+
+        assert!(stats.after.acmr < stats.before.acmr * 0.75, "{:?}", stats);
+        assert!(stats.after.acmr < 1.0, "{:?}", stats);
+        assert_eq!(sorted_triangles(&mesh.indices), before_triangles);
+    }
+
+    /// Verify that vertices are put in the order that they are first used in, and that the uv
+    /// layers and custom attributes follow their vertices.
+    #[test]
+    fn optimize_vertex_fetch() {
+        let mut mesh = SingleIndexedVertexAttributes {
+            indices: vec![3, 1, 2],
+            vertices: (0..5)
+                .map(|idx| Vertex {
+                    position: [idx as f32, 0., 0.],
+                    ..Vertex::default()
+                })
+                .collect(),
+            ..SingleIndexedVertexAttributes::default()
+        };
+        mesh.custom_attributes.insert(
+            "wind".to_string(),
+            VertexAttribute::new(vec![0., 1., 2., 3., 4.], 1).unwrap(),
+        );
+
+        let stats = mesh.optimize_vertex_fetch(16);
+
+        assert_eq!(stats.before, stats.after);
+        assert_eq!(mesh.indices(), &vec![0, 1, 2]);
+        let positions = mesh
+            .vertices()
+            .iter()
+            .map(|vertex| vertex.position()[0])
+            .collect::<Vec<_>>();
+        assert_eq!(positions, vec![3., 1., 2., 0., 4.]);
+        assert_eq!(mesh.custom_attributes()["wind"].data(), &positions);
+    }
+
+    /// Verify that clusters facing away from the middle of a mesh are drawn first, as long as it
+    /// doesn't cost too many cache misses.
+    #[test]
+    fn optimize_overdraw() {
+        let positions = [
+            // A triangle in the middle of the mesh that faces inwards
+            [0., 0., 0.],
+            [0., 1., 0.],
+            [1., 0., 0.],
+            // A triangle at the top of the mesh that faces outwards
+            [0., 0., 5.],
+            [1., 0., 5.],
+            [0., 1., 5.],
+            // A triangle at the bottom of the mesh that faces outwards
+            [0., 0., -5.],
+            [0., 1., -5.],
+            [1., 0., -5.],
+        ];
+        let mut mesh = SingleIndexedVertexAttributes {
+            indices: (0..9).collect(),
+            vertices: positions
+                .iter()
+                .map(|position| Vertex {
+                    position: *position,
+                    ..Vertex::default()
+                })
+                .collect(),
+            ..SingleIndexedVertexAttributes::default()
+        };
+
+        let stats = mesh.optimize_overdraw(16, 1.05);
+
+        assert_eq!(stats.before, stats.after);
+        assert_eq!(mesh.indices(), &vec![3, 4, 5, 6, 7, 8, 0, 1, 2]);
+    }
+
+    /// Verify that we keep the old order if the new one would cost too many cache misses
+    #[test]
+    fn overdraw_threshold() {
+        let mut mesh = grid(20);
+        mesh.indices = column_major(&mesh.indices, 20);
+        mesh.optimize_vertex_cache(16);
+        let indices = mesh.indices.clone();
+
+        let stats = mesh.optimize_overdraw(16, 0.5);
+
+        assert_eq!(stats.before, stats.after);
+        assert_eq!(mesh.indices, indices);
+    }
+
+    /// A flat grid of quads, each split into two triangles, in row major order.
+    fn grid(size: u32) -> SingleIndexedVertexAttributes {
+        let mut indices = vec![];
+        for row in 0..size {
+            for column in 0..size {
+                let corner = row * (size + 1) + column;
+                let next_row = corner + size + 1;
+
+                indices.extend_from_slice(&[corner, corner + 1, next_row + 1]);
+                indices.extend_from_slice(&[corner, next_row + 1, next_row]);
+            }
+        }
+
+        let vertices = (0..(size + 1) * (size + 1))
+            .map(|idx| Vertex {
+                position: [(idx % (size + 1)) as f32, (idx / (size + 1)) as f32, 0.],
+                ..Vertex::default()
+            })
+            .collect();
+
+        SingleIndexedVertexAttributes {
+            indices,
+            vertices,
+            ..SingleIndexedVertexAttributes::default()
+        }
+    }
+
+    fn column_major(indices: &[u32], size: usize) -> Vec<u32> {
+        let quads = indices.chunks(6).collect::<Vec<_>>();
+
+        (0..size)
+            .flat_map(|column| (0..size).map(move |row| row * size + column))
+            .flat_map(|quad| quads[quad].to_vec())
+            .collect()
+    }
+
+    fn sorted_triangles(indices: &[u32]) -> Vec<Vec<u32>> {
+        let mut triangles = indices
+            .chunks(3)
+            .map(|triangle| triangle.to_vec())
+            .collect::<Vec<_>>();
+        triangles.sort();
+        triangles
+    }
+}
@@ -0,0 +1,859 @@
+//! Reduce the number of triangles in a mesh using quadric error metrics, such as when generating
+//! levels of detail.
+//!
+//! Vertices are collapsed onto one of their neighbors (half-edge collapses), so every vertex in a
+//! simplified mesh is one of the original vertices, with all of its attributes untouched.
+//!
+//! @see https://www.cs.cmu.edu/~./garland/Papers/quadrics.pdf
+
+use crate::{SingleIndexedVertexAttributes, Vertex};
+use nalgebra::Vector3;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Configuration for simplifying a mesh.
+///
+/// Simplification stops as soon as either target is reached.
+#[derive(Debug, Copy, Clone)]
+pub struct SimplifyConfig {
+    /// Stop once the mesh has this fraction of its original triangles.
+    pub target_triangle_ratio: f32,
+    /// Stop before any collapse that would move the surface by more than this distance, in the
+    /// same units as the mesh's positions.
+    pub max_error: f32,
+}
+
+impl Default for SimplifyConfig {
+    fn default() -> Self {
+        SimplifyConfig {
+            target_triangle_ratio: 0.5,
+            max_error: f32::INFINITY,
+        }
+    }
+}
+
+/// A simplified version of a mesh, along with how far it strays from the original.
+#[derive(Debug)]
+pub struct Lod {
+    mesh: SingleIndexedVertexAttributes,
+    error: f32,
+}
+
+impl Lod {
+    /// The simplified mesh
+    pub fn mesh(&self) -> &SingleIndexedVertexAttributes {
+        &self.mesh
+    }
+
+    /// Take ownership of the simplified mesh
+    pub fn into_mesh(self) -> SingleIndexedVertexAttributes {
+        self.mesh
+    }
+
+    /// An estimate of the largest distance between the simplified surface and the original
+    /// surface, in the same units as the mesh's positions.
+    pub fn error(&self) -> f32 {
+        self.error
+    }
+
+    /// The number of triangles in the simplified mesh
+    pub fn triangle_count(&self) -> usize {
+        self.mesh.indices.len() / 3
+    }
+}
+
+impl SingleIndexedVertexAttributes {
+    /// Simplify the mesh down to the targets in the config.
+    ///
+    /// Boundaries in the mesh are kept in place:
+    ///
+    /// - Vertices that share a position with other vertices, such as along a UV seam or a hard
+    ///   edge in the normals, are only collapsed along the seam onto another position where every
+    ///   side of the seam has a vertex, so attributes are never mixed across the seam. Meshes
+    ///   where every triangle has its own vertices, such as flat shaded meshes with no coplanar
+    ///   neighbors, can't be simplified.
+    ///
+    /// - Vertices along open edges of the mesh are never moved.
+    ///
+    /// - Vertices that neighbor a vertex that is influenced by different bones are never moved,
+    ///   so the boundaries between bone influences keep their shape.
+    ///
+    /// Collapses that would flip a triangle over are skipped.
+    ///
+    /// Each material's triangles stay within their own [`MaterialRange`].
+    ///
+    /// [`MaterialRange`]: struct.MaterialRange.html
+    pub fn simplify(&self, config: &SimplifyConfig) -> Lod {
+        Simplifier::new(self).simplify(config)
+    }
+
+    /// Simplify the mesh once for each config, such as to generate a chain of levels of detail.
+    ///
+    /// Every level is simplified from the original mesh, so each level's error is measured
+    /// against the original surface.
+    pub fn lod_chain(&self, levels: &[SimplifyConfig]) -> Vec<Lod> {
+        levels.iter().map(|config| self.simplify(config)).collect()
+    }
+}
+
+struct Simplifier<'a> {
+    mesh: &'a SingleIndexedVertexAttributes,
+    triangles: Vec<[u32; 3]>,
+    triangle_alive: Vec<bool>,
+    vertex_triangles: Vec<Vec<usize>>,
+    vertex_alive: Vec<bool>,
+    // Vertices that can't be collapsed onto other vertices.
+    locked: Vec<bool>,
+    // The index of each vertex's position within `quadrics`.
+    vertex_position: Vec<usize>,
+    // The vertices at each position. There is more than one along seams.
+    position_vertices: Vec<Vec<u32>>,
+    quadrics: Vec<Quadric>,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(mesh: &'a SingleIndexedVertexAttributes) -> Self {
+        let vertex_count = mesh.vertices.len();
+
+        let triangles = mesh
+            .indices
+            .chunks(3)
+            .map(|corners| [corners[0], corners[1], corners[2]])
+            .collect::<Vec<_>>();
+
+        let mut vertex_triangles = vec![vec![]; vertex_count];
+        for (triangle, corners) in triangles.iter().enumerate() {
+            for corner in corners {
+                vertex_triangles[*corner as usize].push(triangle);
+            }
+        }
+
+        let mut positions = HashMap::new();
+        let vertex_position = mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let key = vertex.position.map(f32::to_bits);
+                let next = positions.len();
+                *positions.entry(key).or_insert(next)
+            })
+            .collect::<Vec<usize>>();
+
+        let mut position_vertices = vec![vec![]; positions.len()];
+        for (vertex, position) in vertex_position.iter().enumerate() {
+            position_vertices[*position].push(vertex as u32);
+        }
+
+        // Edges between positions that only have one triangle are open edges of the mesh.
+        // Using positions means that UV seams and hard edges don't count as open edges.
+        let mut edge_triangles = HashMap::new();
+        for corners in triangles.iter() {
+            for edge in 0..3 {
+                let a = vertex_position[corners[edge] as usize];
+                let b = vertex_position[corners[(edge + 1) % 3] as usize];
+                *edge_triangles.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+        }
+        let mut on_border = vec![false; positions.len()];
+        for ((a, b), count) in edge_triangles {
+            if count == 1 {
+                on_border[a] = true;
+                on_border[b] = true;
+            }
+        }
+
+        // Vertices next to a vertex with different bone influences sit on the boundary between
+        // bones, so moving them would change the shape of the boundary.
+        let mut on_bone_boundary = vec![false; vertex_count];
+        for corners in triangles.iter() {
+            for edge in 0..3 {
+                let a = corners[edge] as usize;
+                let b = corners[(edge + 1) % 3] as usize;
+
+                if influencing_bones(&mesh.vertices[a]) != influencing_bones(&mesh.vertices[b]) {
+                    on_bone_boundary[a] = true;
+                    on_bone_boundary[b] = true;
+                }
+            }
+        }
+
+        let locked = vertex_position
+            .iter()
+            .zip(on_bone_boundary)
+            .map(|(position, on_bone_boundary)| on_border[*position] || on_bone_boundary)
+            .collect();
+
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        for corners in triangles.iter() {
+            let [a, b, c] =
+                corners.map(|corner| Vector3::from(mesh.vertices[corner as usize].position));
+            let normal = (b - a).cross(&(c - a));
+            let double_area = normal.norm();
+            if double_area == 0.0 {
+                continue;
+            }
+
+            let normal = normal / double_area;
+            let quadric = Quadric::from_plane(normal, -normal.dot(&a), double_area / 2.0);
+
+            for corner in corners {
+                quadrics[vertex_position[*corner as usize]].add(&quadric);
+            }
+        }
+
+        Simplifier {
+            mesh,
+            triangle_alive: vec![true; triangles.len()],
+            triangles,
+            vertex_triangles,
+            vertex_alive: vec![true; vertex_count],
+            locked,
+            vertex_position,
+            position_vertices,
+            quadrics,
+        }
+    }
+
+    fn simplify(mut self, config: &SimplifyConfig) -> Lod {
+        let target_triangle_count =
+            (self.triangles.len() as f32 * config.target_triangle_ratio.max(0.0)).ceil() as usize;
+        let mut triangle_count = self.triangles.len();
+        let mut error: f32 = 0.0;
+
+        let mut collapses = BinaryHeap::new();
+        for vertex in 0..self.mesh.vertices.len() {
+            self.push_collapses(vertex as u32, &mut collapses);
+        }
+
+        while triangle_count > target_triangle_count {
+            let collapse = match collapses.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            let (from, onto) = (collapse.from, collapse.onto);
+
+            if !self.vertex_alive[from as usize] || !self.vertex_alive[onto as usize] {
+                continue;
+            }
+
+            // Quadrics only ever grow, so a stale cost is never higher than the real cost.
+            let cost = self.collapse_cost(from, onto);
+            if cost > collapse.cost {
+                collapses.push(Collapse { cost, ..collapse });
+                continue;
+            }
+
+            if cost > config.max_error {
+                break;
+            }
+
+            let seam_collapses = match self.seam_collapses(from, onto) {
+                Some(seam_collapses) => seam_collapses,
+                None => continue,
+            };
+            if !seam_collapses
+                .iter()
+                .all(|(from, onto)| self.can_collapse(*from, *onto))
+            {
+                continue;
+            }
+
+            for (from, onto) in seam_collapses.iter() {
+                triangle_count -= self.collapse(*from, *onto);
+            }
+            let from_quadric = self.quadrics[self.vertex_position[from as usize]];
+            self.quadrics[self.vertex_position[onto as usize]].add(&from_quadric);
+            error = error.max(cost);
+
+            for (_, onto) in seam_collapses {
+                self.push_collapses(onto, &mut collapses);
+            }
+        }
+
+        Lod {
+            mesh: self.simplified_mesh(),
+            error,
+        }
+    }
+
+    /// Add every collapse of an edge that touches the vertex.
+    fn push_collapses(&self, vertex: u32, collapses: &mut BinaryHeap<Collapse>) {
+        for neighbor in self.neighbors(vertex) {
+            for (from, onto) in [(vertex, neighbor), (neighbor, vertex)].iter() {
+                if self.locked[*from as usize] {
+                    continue;
+                }
+
+                collapses.push(Collapse {
+                    cost: self.collapse_cost(*from, *onto),
+                    from: *from,
+                    onto: *onto,
+                });
+            }
+        }
+    }
+
+    /// The distance that the surface moves when collapsing one vertex onto another.
+    fn collapse_cost(&self, from: u32, onto: u32) -> f32 {
+        let mut quadric = self.quadrics[self.vertex_position[from as usize]];
+        quadric.add(&self.quadrics[self.vertex_position[onto as usize]]);
+
+        quadric
+            .squared_distance(self.mesh.vertices[onto as usize].position)
+            .sqrt()
+    }
+
+    /// Every vertex at the `from` vertex's position paired with the vertex at the `onto` vertex's
+    /// position that it collapses onto.
+    ///
+    /// Vertices along a seam can only be collapsed if every side of the seam has exactly one
+    /// neighboring vertex at the `onto` position, which keeps the sides of the seam apart.
+    fn seam_collapses(&self, from: u32, onto: u32) -> Option<Vec<(u32, u32)>> {
+        let from_position = self.vertex_position[from as usize];
+        let onto_position = self.vertex_position[onto as usize];
+        if from_position == onto_position {
+            return None;
+        }
+
+        let alive = |vertex: &u32| self.vertex_alive[*vertex as usize];
+
+        let mut seam_collapses = vec![];
+        for vertex in self.position_vertices[from_position].iter().copied() {
+            if !alive(&vertex) {
+                continue;
+            }
+            if self.locked[vertex as usize] {
+                return None;
+            }
+
+            let neighbors = self.neighbors(vertex);
+            let mut targets = self.position_vertices[onto_position]
+                .iter()
+                .copied()
+                .filter(alive)
+                .filter(|target| neighbors.contains(target));
+
+            match (targets.next(), targets.next()) {
+                (Some(target), None) => seam_collapses.push((vertex, target)),
+                _ => return None,
+            }
+        }
+
+        if seam_collapses.contains(&(from, onto)) {
+            Some(seam_collapses)
+        } else {
+            None
+        }
+    }
+
+    /// Whether collapsing the edge keeps the mesh manifold and keeps every triangle facing the
+    /// same way.
+    fn can_collapse(&self, from: u32, onto: u32) -> bool {
+        let mut opposite: HashSet<u32> = HashSet::new();
+        let mut connected = false;
+
+        for triangle in self.live_triangles(from) {
+            let corners = self.triangles[triangle];
+
+            if corners.contains(&onto) {
+                connected = true;
+                opposite.extend(corners.iter().filter(|c| **c != from && **c != onto));
+                continue;
+            }
+
+            let before = self.triangle_normal(corners);
+            let after = self.triangle_normal(corners.map(|c| if c == from { onto } else { c }));
+            if before.dot(&after) <= 0.0 || after.norm() == 0.0 {
+                return false;
+            }
+        }
+
+        // Vertices that neighbor both ends of the edge, without sharing a triangle with the
+        // edge, would end up with an edge that has more than two triangles.
+        let onto_neighbors = self.neighbors(onto);
+        connected
+            && self
+                .neighbors(from)
+                .iter()
+                .filter(|neighbor| onto_neighbors.contains(neighbor))
+                .all(|neighbor| opposite.contains(neighbor))
+    }
+
+    /// Collapse a vertex onto a neighbor, returning the number of triangles that were removed.
+    fn collapse(&mut self, from: u32, onto: u32) -> usize {
+        let mut removed = 0;
+
+        for triangle in std::mem::take(&mut self.vertex_triangles[from as usize]) {
+            if !self.triangle_alive[triangle] {
+                continue;
+            }
+
+            let corners = &mut self.triangles[triangle];
+            if corners.contains(&onto) {
+                self.triangle_alive[triangle] = false;
+                removed += 1;
+            } else {
+                for corner in corners.iter_mut().filter(|c| **c == from) {
+                    *corner = onto;
+                }
+                self.vertex_triangles[onto as usize].push(triangle);
+            }
+        }
+
+        self.vertex_alive[from as usize] = false;
+
+        removed
+    }
+
+    fn simplified_mesh(&self) -> SingleIndexedVertexAttributes {
+        let mut remap = vec![None; self.mesh.vertices.len()];
+        let mut order = vec![];
+        let mut indices = vec![];
+
+        let mut material_ranges = self.mesh.material_ranges.clone();
+        let mut push_triangles = |triangles: std::ops::Range<usize>, indices: &mut Vec<u32>| {
+            for triangle in triangles.filter(|triangle| self.triangle_alive[*triangle]) {
+                for corner in self.triangles[triangle].iter() {
+                    let index = *remap[*corner as usize].get_or_insert_with(|| {
+                        order.push(*corner as usize);
+                        order.len() as u32 - 1
+                    });
+                    indices.push(index);
+                }
+            }
+        };
+
+        if material_ranges.is_empty() {
+            push_triangles(0..self.triangles.len(), &mut indices);
+        }
+        for material_range in material_ranges.iter_mut() {
+            let start = indices.len();
+            push_triangles(
+                material_range.indices.start / 3..material_range.indices.end / 3,
+                &mut indices,
+            );
+            material_range.indices = start..indices.len();
+        }
+
+        let mut simplified = SingleIndexedVertexAttributes {
+            indices,
+            index_format: self.mesh.index_format,
+            vertices: self.mesh.vertices.clone(),
+            active_uv_layer: self.mesh.active_uv_layer.clone(),
+            uv_layers: self.mesh.uv_layers.clone(),
+            custom_attributes: self.mesh.custom_attributes.clone(),
+            triangulation_warnings: self.mesh.triangulation_warnings.clone(),
+            material_ranges,
+            coordinate_system: self.mesh.coordinate_system,
+        };
+        simplified.reorder_vertices(&order);
+
+        simplified
+    }
+
+    fn live_triangles(&self, vertex: u32) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_triangles[vertex as usize]
+            .iter()
+            .copied()
+            .filter(move |triangle| self.triangle_alive[*triangle])
+    }
+
+    fn neighbors(&self, vertex: u32) -> HashSet<u32> {
+        self.live_triangles(vertex)
+            .flat_map(|triangle| self.triangles[triangle].to_vec())
+            .filter(|neighbor| *neighbor != vertex)
+            .collect()
+    }
+
+    fn triangle_normal(&self, corners: [u32; 3]) -> Vector3<f32> {
+        let [a, b, c] =
+            corners.map(|corner| Vector3::from(self.mesh.vertices[corner as usize].position));
+        (b - a).cross(&(c - a))
+    }
+}
+
+/// The sorted indices of the bones that influence a vertex.
+fn influencing_bones(vertex: &Vertex) -> Vec<u8> {
+    let mut bones = vertex
+        .bones
+        .iter()
+        .flatten()
+        .filter(|influence| influence.weight > 0.0)
+        .map(|influence| influence.bone_idx)
+        .collect::<Vec<_>>();
+    bones.sort_unstable();
+    bones
+}
+
+/// Collapsing one vertex onto another. Ordered so that a `BinaryHeap` pops the cheapest first.
+#[derive(Debug, Copy, Clone)]
+struct Collapse {
+    cost: f32,
+    from: u32,
+    onto: u32,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| (other.from, other.onto).cmp(&(self.from, self.onto)))
+    }
+}
+
+/// The sum of the squared distances to a set of planes, weighted by the area of the triangle that
+/// each plane came from.
+///
+/// Stores the upper triangle of the symmetric 4x4 matrix.
+#[derive(Debug, Copy, Clone, Default)]
+struct Quadric {
+    aa: f64,
+    ab: f64,
+    ac: f64,
+    ad: f64,
+    bb: f64,
+    bc: f64,
+    bd: f64,
+    cc: f64,
+    cd: f64,
+    dd: f64,
+    weight: f64,
+}
+
+impl Quadric {
+    fn from_plane(normal: Vector3<f32>, d: f32, weight: f32) -> Quadric {
+        let (a, b, c) = (normal.x as f64, normal.y as f64, normal.z as f64);
+        let (d, w) = (d as f64, weight as f64);
+
+        Quadric {
+            aa: a * a * w,
+            ab: a * b * w,
+            ac: a * c * w,
+            ad: a * d * w,
+            bb: b * b * w,
+            bc: b * c * w,
+            bd: b * d * w,
+            cc: c * c * w,
+            cd: c * d * w,
+            dd: d * d * w,
+            weight: w,
+        }
+    }
+
+    fn add(&mut self, other: &Quadric) {
+        self.aa += other.aa;
+        self.ab += other.ab;
+        self.ac += other.ac;
+        self.ad += other.ad;
+        self.bb += other.bb;
+        self.bc += other.bc;
+        self.bd += other.bd;
+        self.cc += other.cc;
+        self.cd += other.cd;
+        self.dd += other.dd;
+        self.weight += other.weight;
+    }
+
+    /// The area weighted average of the squared distances from the point to each plane.
+    fn squared_distance(&self, point: [f32; 3]) -> f32 {
+        if self.weight == 0.0 {
+            return 0.0;
+        }
+
+        let [x, y, z] = point.map(|p| p as f64);
+
+        let error = self.aa * x * x
+            + 2.0 * self.ab * x * y
+            + 2.0 * self.ac * x * z
+            + 2.0 * self.ad * x
+            + self.bb * y * y
+            + 2.0 * self.bc * y * z
+            + 2.0 * self.bd * y
+            + self.cc * z * z
+            + 2.0 * self.cd * z
+            + self.dd;
+
+        (error / self.weight).max(0.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoneInfluence;
+
+    /// Verify that a flat grid can be simplified down to the target ratio without any error,
+    /// while its open edges stay in place.
+    #[test]
+    fn simplify_flat_grid() {
+        let mesh = grid(10, |_, _| 0.);
+
+        let lod = mesh.simplify(&SimplifyConfig {
+            target_triangle_ratio: 0.5,
+            ..SimplifyConfig::default()
+        });
+
+        assert!(lod.triangle_count() <= 100);
+        assert_eq!(lod.error(), 0.);
+        for x in 0..=10 {
+            assert!(has_position(lod.mesh(), [x as f32, 0., 0.]));
+            assert!(has_position(lod.mesh(), [x as f32, 10., 0.]));
+        }
+    }
+
+    /// Verify that we stop before collapses that would move the surface too far
+    #[test]
+    fn stop_at_max_error() {
+        let mesh = grid(10, |x, y| ((x * x * 7 + y * 3 + x * y) % 5) as f32 * 0.2);
+
+        let lod = mesh.simplify(&SimplifyConfig {
+            target_triangle_ratio: 0.,
+            max_error: 0.01,
+        });
+        let unlimited = mesh.simplify(&SimplifyConfig {
+            target_triangle_ratio: 0.,
+            ..SimplifyConfig::default()
+        });
+
+        assert!(lod.error() <= 0.01);
+        assert!(unlimited.error() > 0.01);
+        assert!(lod.triangle_count() > unlimited.triangle_count());
+    }
+
+    /// Verify that vertices that share a position, such as along a UV seam, are only collapsed
+    /// along the seam and never mix the attributes from either side of it
+    #[test]
+    fn seams_collapse_along_the_seam() {
+        let mut mesh = grid(10, |_, _| 0.);
+        // Split every vertex in the middle column into two vertices with different uvs.
+        split_column(&mut mesh, 5, 11);
+
+        let lod = mesh.simplify(&SimplifyConfig {
+            target_triangle_ratio: 0.,
+            ..SimplifyConfig::default()
+        });
+
+        let vertices = lod.mesh().vertices();
+        let seam_positions = vertices
+            .iter()
+            .filter(|vertex| vertex.position()[0] == 5.)
+            .count();
+        assert!(seam_positions < 22);
+        assert!(has_position(lod.mesh(), [5., 0., 0.]));
+        assert!(has_position(lod.mesh(), [5., 10., 0.]));
+
+        for triangle in lod.mesh().indices().chunks(3) {
+            let corners = triangle.iter().map(|idx| &vertices[*idx as usize]);
+            let right_of_seam = corners.clone().any(|vertex| vertex.position()[0] > 5.);
+
+            for vertex in corners.filter(|vertex| vertex.position()[0] == 5.) {
+                assert_eq!(vertex.uv.is_some(), right_of_seam);
+            }
+        }
+    }
+
+    /// Verify that a mesh with hard edges, where every position along an edge is split, still
+    /// simplifies down to its flat faces
+    #[test]
+    fn simplify_split_normal_cube() {
+        let mesh = split_normal_cube(4);
+        assert_eq!(mesh.indices.len() / 3, 192);
+
+        let lod = mesh.simplify(&SimplifyConfig {
+            target_triangle_ratio: 0.,
+            ..SimplifyConfig::default()
+        });
+
+        assert_eq!(lod.triangle_count(), 12);
+        assert!(lod.error() < 1e-4);
+
+        let vertices = lod.mesh().vertices();
+        for triangle in lod.mesh().indices().chunks(3) {
+            let normal = vertices[triangle[0] as usize].normal();
+            for idx in triangle {
+                assert_eq!(vertices[*idx as usize].normal(), normal);
+            }
+        }
+    }
+
+    /// Verify that vertices on the boundary between bone influences are not moved
+    #[test]
+    fn bone_influence_boundaries_are_kept() {
+        let mut mesh = grid(10, |_, _| 0.);
+        for vertex in mesh.vertices.iter_mut() {
+            let bone_idx = if vertex.position[0] < 5. { 0 } else { 1 };
+            vertex.bones = Some(vec![
+                BoneInfluence {
+                    bone_idx,
+                    weight: 1.0,
+                };
+                4
+            ]);
+        }
+
+        let lod = mesh.simplify(&SimplifyConfig {
+            target_triangle_ratio: 0.,
+            ..SimplifyConfig::default()
+        });
+
+        assert!(lod.triangle_count() < 200);
+        for y in 0..=10 {
+            assert!(has_position(lod.mesh(), [5., y as f32, 0.]));
+            assert!(has_position(lod.mesh(), [4., y as f32, 0.]));
+        }
+    }
+
+    /// Verify that every level of a LOD chain has fewer triangles than the last
+    #[test]
+    fn lod_chain() {
+        let mesh = grid(20, |x, y| {
+            ((x as f32 * 0.3).sin() + (y as f32 * 0.2).cos()) * 2.
+        });
+
+        let lods = mesh.lod_chain(&[
+            SimplifyConfig {
+                target_triangle_ratio: 0.5,
+                ..SimplifyConfig::default()
+            },
+            SimplifyConfig {
+                target_triangle_ratio: 0.25,
+                ..SimplifyConfig::default()
+            },
+        ]);
+
+        assert!(lods[0].triangle_count() <= 400);
+        assert!(lods[1].triangle_count() <= 200);
+        assert!(lods[0].error() > 0.);
+        assert!(lods[1].error() >= lods[0].error());
+    }
+
+    /// A grid of quads, each split into two triangles, with a height at each vertex.
+    fn grid(size: u32, height: impl Fn(u32, u32) -> f32) -> SingleIndexedVertexAttributes {
+        let mut indices = vec![];
+        for row in 0..size {
+            for column in 0..size {
+                let corner = row * (size + 1) + column;
+                let next_row = corner + size + 1;
+
+                indices.extend_from_slice(&[corner, corner + 1, next_row + 1]);
+                indices.extend_from_slice(&[corner, next_row + 1, next_row]);
+            }
+        }
+
+        let vertices = (0..(size + 1) * (size + 1))
+            .map(|idx| {
+                let (x, y) = (idx % (size + 1), idx / (size + 1));
+                Vertex {
+                    position: [x as f32, y as f32, height(x, y)],
+                    ..Vertex::default()
+                }
+            })
+            .collect();
+
+        SingleIndexedVertexAttributes {
+            indices,
+            vertices,
+            ..SingleIndexedVertexAttributes::default()
+        }
+    }
+
+    /// A cube from -1 to 1 where each face is a grid of quads with its own vertices, so every
+    /// position along the cube's edges is split.
+    fn split_normal_cube(size: u32) -> SingleIndexedVertexAttributes {
+        let mut indices = vec![];
+        let mut vertices = vec![];
+
+        for axis in 0..3 {
+            for sign in [-1., 1.].iter() {
+                let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+                let mut normal = [0.; 3];
+                normal[axis] = *sign;
+
+                let first = vertices.len() as u32;
+                for idx in 0..(size + 1) * (size + 1) {
+                    let (u, v) = (idx % (size + 1), idx / (size + 1));
+
+                    let mut position = [0.; 3];
+                    position[axis] = *sign;
+                    position[u_axis] = u as f32 / size as f32 * 2. - 1.;
+                    position[v_axis] = v as f32 / size as f32 * 2. - 1.;
+
+                    vertices.push(Vertex {
+                        position,
+                        normal: Some(normal),
+                        ..Vertex::default()
+                    });
+                }
+
+                for row in 0..size {
+                    for column in 0..size {
+                        let corner = first + row * (size + 1) + column;
+                        let next_row = corner + size + 1;
+
+                        let mut quad = [
+                            [corner, corner + 1, next_row + 1],
+                            [corner, next_row + 1, next_row],
+                        ];
+                        if *sign < 0. {
+                            for triangle in quad.iter_mut() {
+                                triangle.swap(1, 2);
+                            }
+                        }
+                        indices.extend(quad.iter().flatten());
+                    }
+                }
+            }
+        }
+
+        SingleIndexedVertexAttributes {
+            indices,
+            vertices,
+            ..SingleIndexedVertexAttributes::default()
+        }
+    }
+
+    /// Give the triangles to the right of a column their own copy of the column's vertices.
+    fn split_column(mesh: &mut SingleIndexedVertexAttributes, column: u32, row_len: u32) {
+        let mut copies = HashMap::new();
+
+        let vertices = &mut mesh.vertices;
+
+        for triangle in mesh.indices.chunks_mut(3) {
+            let right_of_column = triangle.iter().any(|idx| idx % row_len > column);
+
+            for index in triangle.iter_mut() {
+                if right_of_column && *index % row_len == column {
+                    let vertex = vertices[*index as usize].clone();
+                    *index = *copies.entry(*index).or_insert_with(|| {
+                        vertices.push(Vertex {
+                            uv: Some([1., 0.]),
+                            ..vertex
+                        });
+                        vertices.len() as u32 - 1
+                    });
+                }
+            }
+        }
+    }
+
+    fn has_position(mesh: &SingleIndexedVertexAttributes, position: [f32; 3]) -> bool {
+        mesh.vertices()
+            .iter()
+            .any(|vertex| vertex.position() == position)
+    }
+}
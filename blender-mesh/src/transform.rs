@@ -0,0 +1,217 @@
+use crate::bounding_box::BoundingBox;
+use crate::{BlenderMesh, SingleIndexedVertexAttributes};
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3};
+
+impl BlenderMesh {
+    /// Apply a transformation matrix to the mesh, such as an object's world transform that you
+    /// want to bake into its vertices or a scale from centimeters to meters.
+    ///
+    /// Positions are transformed by the full matrix, normals by its inverse-transpose so that they
+    /// stay perpendicular to their faces under non uniform scales. Normals are renormalized
+    /// afterwards.
+    ///
+    /// When the matrix mirrors the mesh (a negative determinant) the winding order of every face
+    /// is reversed so that faces keep facing the same way.
+    ///
+    /// The bounding box is recomputed from the transformed positions.
+    pub fn transform(&mut self, matrix: &Matrix4<f32>) {
+        let linear = linear_part(matrix);
+        let normal_matrix = normal_matrix(&linear);
+        let multi = &mut self.multi_indexed_vertex_attributes;
+
+        for position in multi.positions.attribute.data.chunks_exact_mut(3) {
+            let transformed =
+                matrix.transform_point(&Point3::new(position[0], position[1], position[2]));
+            position.copy_from_slice(transformed.coords.as_slice());
+        }
+
+        if let Some(normals) = multi.normals.as_mut() {
+            for normal in normals.attribute.data.chunks_exact_mut(3) {
+                let transformed =
+                    transform_direction(&normal_matrix, [normal[0], normal[1], normal[2]]);
+                normal.copy_from_slice(&transformed);
+            }
+        }
+
+        if linear.determinant() < 0.0 {
+            multi.reverse_face_winding();
+        }
+
+        self.bounding_box = BoundingBox::from_positions(&multi.positions.attribute.data);
+    }
+}
+
+impl SingleIndexedVertexAttributes {
+    /// Apply a transformation matrix to the vertices.
+    ///
+    /// Works just like [`BlenderMesh.transform`], and also transforms the face and vertex
+    /// tangents.
+    ///
+    /// Tangents lie along the surface, so they're transformed by the matrix itself rather than
+    /// its inverse-transpose, which keeps them perpendicular to the transformed normals. They're
+    /// renormalized afterwards. When the matrix mirrors the vertices the w component of the vertex
+    /// tangents is negated since the bitangents are mirrored.
+    ///
+    /// [`BlenderMesh.transform`]: struct.BlenderMesh.html#method.transform
+    pub fn transform(&mut self, matrix: &Matrix4<f32>) {
+        let linear = linear_part(matrix);
+        let normal_matrix = normal_matrix(&linear);
+        let mirrors = linear.determinant() < 0.0;
+
+        for vertex in self.vertices.iter_mut() {
+            vertex.position = matrix
+                .transform_point(&Point3::from(vertex.position))
+                .coords
+                .into();
+            vertex.normal = vertex
+                .normal
+                .map(|normal| transform_direction(&normal_matrix, normal));
+            vertex.face_tangent = vertex
+                .face_tangent
+                .map(|tangent| transform_direction(&linear, tangent));
+            vertex.tangent = vertex.tangent.map(|[x, y, z, w]| {
+                let [x, y, z] = transform_direction(&linear, [x, y, z]);
+                [x, y, z, if mirrors { -w } else { w }]
+            });
+        }
+
+        if mirrors {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+    }
+}
+
+/// The upper left 3x3 of the matrix, which is all that applies to directions.
+fn linear_part(matrix: &Matrix4<f32>) -> Matrix3<f32> {
+    Matrix3::from_fn(|row, column| matrix[(row, column)])
+}
+
+/// The inverse-transpose of the matrix. A matrix that can't be inverted (such as a scale of zero
+/// along an axis) has no sensible normal transform, so we fall back to the matrix itself.
+fn normal_matrix(linear: &Matrix3<f32>) -> Matrix3<f32> {
+    linear
+        .try_inverse()
+        .map(|inverse| inverse.transpose())
+        .unwrap_or(*linear)
+}
+
+/// Transform and renormalize a direction. Directions that collapse to zero length are left as
+/// zero rather than becoming NaN.
+fn transform_direction(matrix: &Matrix3<f32>, direction: [f32; 3]) -> [f32; 3] {
+    let transformed = matrix * Vector3::from(direction);
+
+    transformed
+        .try_normalize(f32::EPSILON)
+        .unwrap_or(transformed)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexed;
+    use crate::vertex_attributes::MultiIndexedVertexAttributes;
+    use crate::Vertex;
+
+    /// Verify that positions get the full transform and that the bounding box is recomputed
+    #[test]
+    fn translate_and_scale() {
+        let mut mesh = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                positions: indexed((vec![0.0, 0.0, 0.0, 1.0, 2.0, 3.0], 3).into()),
+                normals: Some(indexed((vec![0.0, 0.0, 1.0], 3).into())),
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        };
+
+        let matrix =
+            Matrix4::new_translation(&Vector3::new(1.0, 1.0, 1.0)) * Matrix4::new_scaling(0.01);
+        mesh.transform(&matrix);
+
+        let multi = &mesh.multi_indexed_vertex_attributes;
+        let positions = &multi.positions.attribute.data;
+        assert!((positions[3] - 1.01).abs() < 1e-6);
+        assert!((positions[5] - 1.03).abs() < 1e-6);
+
+        assert_eq!(
+            multi.normals.as_ref().unwrap().attribute.data,
+            vec![0.0, 0.0, 1.0]
+        );
+
+        assert_eq!(mesh.bounding_box().min_corner, Point3::new(1.0, 1.0, 1.0));
+        assert!((mesh.bounding_box().max_corner.z - 1.03).abs() < 1e-6);
+    }
+
+    /// Verify that normals stay perpendicular to a sloped face when it is scaled non uniformly
+    #[test]
+    fn non_uniform_scale_normals() {
+        let mut single_indexed = SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 2],
+            vertices: vec![
+                Vertex {
+                    position: [1.0, 0.0, 0.0],
+                    normal: Some([0.5f32.sqrt(), 0.5f32.sqrt(), 0.0]),
+                    tangent: Some([-(0.5f32.sqrt()), 0.5f32.sqrt(), 0.0, 1.0]),
+                    ..Vertex::default()
+                };
+                3
+            ],
+            ..SingleIndexedVertexAttributes::default()
+        };
+
+        single_indexed.transform(&Matrix4::new_nonuniform_scaling(&Vector3::new(
+            2.0, 1.0, 1.0,
+        )));
+
+        let vertex = &single_indexed.vertices()[0];
+        let normal = Vector3::from(vertex.normal().unwrap());
+        let [x, y, z, _] = vertex.tangent().unwrap();
+        let tangent = Vector3::new(x, y, z);
+
+        assert!((normal.norm() - 1.0).abs() < 1e-6);
+        assert!((tangent.norm() - 1.0).abs() < 1e-6);
+        assert!(normal.dot(&tangent).abs() < 1e-6);
+        assert!(normal.x < normal.y);
+    }
+
+    /// Verify that mirroring reverses the winding order of every face and flips the handedness of
+    /// the tangents
+    #[test]
+    fn negative_determinant_flips_winding() {
+        let original = BlenderMesh::pbr_cube_without_textures();
+        let mut mesh = original.clone();
+
+        let mirror = Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0));
+        mesh.transform(&mirror);
+
+        let positions = &mesh.multi_indexed_vertex_attributes.positions.indices;
+        let original_positions = &original.multi_indexed_vertex_attributes.positions.indices;
+        assert_eq!(positions[0], original_positions[0]);
+        assert_eq!(positions[1], original_positions[3]);
+
+        mesh.transform(&mirror);
+        assert_eq!(mesh, original);
+
+        let mut single_indexed = SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 2],
+            vertices: vec![
+                Vertex {
+                    tangent: Some([1.0, 0.0, 0.0, 1.0]),
+                    ..Vertex::default()
+                };
+                3
+            ],
+            ..SingleIndexedVertexAttributes::default()
+        };
+        single_indexed.transform(&mirror);
+
+        assert_eq!(single_indexed.indices(), &vec![0, 2, 1]);
+        assert_eq!(
+            single_indexed.vertices()[0].tangent(),
+            Some([-1.0, 0.0, 0.0, -1.0])
+        );
+    }
+}
@@ -1,84 +1,380 @@
-use crate::BlenderMesh;
+use crate::{BlenderMesh, Vertex};
+use nalgebra::{Vector2, Vector3};
+
+/// A face that could not be triangulated cleanly.
+///
+/// The face is still triangulated, so the mesh can be used as is, but it might not look the way
+/// that it did in Blender.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, thiserror::Error)]
+pub enum TriangulationWarning {
+    /// The face has no area, such as when all of its vertices lie on a line. It was split into a
+    /// fan of triangles around its first vertex.
+    #[error("Face {face_idx} has no area and was triangulated as a fan")]
+    DegenerateFace {
+        /// The index of the face within `vertices_in_each_face`
+        face_idx: usize,
+    },
+    /// No ear could be clipped from the face, usually because it intersects itself when projected
+    /// onto its best fit plane. The rest of the face was split into a fan of triangles.
+    #[error("Face {face_idx} intersects itself and was partially triangulated as a fan")]
+    SelfIntersectingFace {
+        /// The index of the face within `vertices_in_each_face`
+        face_idx: usize,
+    },
+}
+
 impl BlenderMesh {
     /// When exporting a mesh from Blender, faces will usually have 4 vertices (quad) but some
-    /// faces might have 3 (triangle).
+    /// faces might have 3 (triangle) or more (n-gon).
     ///
     /// We read `self.num_vertices_in_each_face` to check how
     /// many vertices each face has.
     ///
-    /// If a face has 4 vertices we convert it into two triangles, each with 3 vertices.
+    /// Faces with more than 3 vertices are projected onto their best fit plane and split into
+    /// triangles using ear clipping, so both convex and concave faces are supported. Triangles
+    /// keep the winding order of the face that they came from.
     ///
-    /// # Panics
-    ///
-    /// Panics if a face has more than 4 vertices. In the future we might support 5+ vertices,
-    /// but I haven't run into that yet. Not even sure if Blender can have faces with 5 vertices..
-    pub(crate) fn triangulate(&self, indices: &Vec<u16>) -> Vec<u16> {
+    /// Faces that can't be triangulated cleanly, such as faces with no area, are split into a fan
+    /// of triangles and reported in the returned warnings.
+    pub(crate) fn triangulate(
+        &self,
+        indices: &[u32],
+        vertices: &[Vertex],
+    ) -> (Vec<u32>, Vec<TriangulationWarning>) {
         let mut triangulated_position_indices = vec![];
-        let mut triangulated_face_vertex_counts = vec![];
+        let mut warnings = vec![];
 
         let mut face_pointer = 0;
 
-        for num_verts_in_face in self
+        for (face_idx, num_verts_in_face) in self
             .multi_indexed_vertex_attributes
             .vertices_in_each_face
             .iter()
+            .enumerate()
         {
-            triangulated_position_indices.push(indices[face_pointer]);
-            triangulated_position_indices.push(indices[face_pointer + 1]);
-            triangulated_position_indices.push(indices[face_pointer + 2]);
-
-            triangulated_face_vertex_counts.push(3);
+            let num_verts_in_face = *num_verts_in_face as usize;
+            let face = &indices[face_pointer..face_pointer + num_verts_in_face];
 
-            match num_verts_in_face {
-                &3 => {}
-                &4 => {
-                    triangulated_position_indices.push(indices[face_pointer]);
-                    triangulated_position_indices.push(indices[face_pointer + 2]);
-                    triangulated_position_indices.push(indices[face_pointer + 3]);
+            let positions: Vec<Vector3<f32>> = face
+                .iter()
+                .map(|idx| Vector3::from(vertices[*idx as usize].position))
+                .collect();
 
-                    triangulated_face_vertex_counts.push(3);
+            match triangulate_face(&positions) {
+                Ok(triangles) => {
+                    triangulated_position_indices.extend(triangles.iter().map(|idx| face[*idx]));
                 }
-                _ => {
-                    panic!("blender-mesh currently only supports triangulating faces with 3 or 4 vertices");
+                Err((triangles, warning)) => {
+                    triangulated_position_indices.extend(triangles.iter().map(|idx| face[*idx]));
+                    warnings.push(match warning {
+                        FaceProblem::Degenerate => {
+                            TriangulationWarning::DegenerateFace { face_idx }
+                        }
+                        FaceProblem::SelfIntersecting => {
+                            TriangulationWarning::SelfIntersectingFace { face_idx }
+                        }
+                    });
                 }
             };
 
-            face_pointer += *num_verts_in_face as usize;
+            face_pointer += num_verts_in_face;
         }
 
         // TODO: ? Set to Uniform(3)
         // multi.vertices_in_each_face = triangulated_face_vertex_counts;
 
-        return triangulated_position_indices;
+        (triangulated_position_indices, warnings)
+    }
+}
+
+enum FaceProblem {
+    Degenerate,
+    SelfIntersecting,
+}
+
+/// Split a face into triangles, returning the index of each triangle's corners within the face.
+///
+/// When the face has a problem we still return a triangulation, along with the problem.
+fn triangulate_face(positions: &[Vector3<f32>]) -> Result<Vec<usize>, (Vec<usize>, FaceProblem)> {
+    let normal = newell_normal(positions);
+
+    let longest_edge = (0..positions.len())
+        .map(|idx| (positions[(idx + 1) % positions.len()] - positions[idx]).norm())
+        .fold(0.0, f32::max);
+
+    // The length of the Newell normal is twice the face's area.
+    let twice_area = normal.norm();
+    if twice_area <= longest_edge * longest_edge * 1e-6 || twice_area.is_nan() {
+        return Err((
+            fan(&(0..positions.len()).collect::<Vec<_>>()),
+            FaceProblem::Degenerate,
+        ));
+    }
+
+    if positions.len() == 3 {
+        return Ok(vec![0, 1, 2]);
+    }
+
+    let projected = project_onto_plane(positions, &normal.normalize());
+    ear_clip(&projected)
+}
+
+/// The normal of the face's best fit plane, with a length of twice the face's area.
+///
+/// Unlike the cross product of two edges this works for concave and non-planar faces.
+///
+/// @see https://www.khronos.org/opengl/wiki/Calculating_a_Surface_Normal
+fn newell_normal(positions: &[Vector3<f32>]) -> Vector3<f32> {
+    let mut normal = Vector3::zeros();
+
+    for (idx, current) in positions.iter().enumerate() {
+        let next = positions[(idx + 1) % positions.len()];
+
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+
+    normal
+}
+
+/// Project positions onto the plane with the given normal.
+///
+/// The axes are chosen such that the face winds counter clockwise in two dimensions.
+fn project_onto_plane(positions: &[Vector3<f32>], normal: &Vector3<f32>) -> Vec<Vector2<f32>> {
+    // Any axis that isn't parallel to the normal will do.
+    let helper = if normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+
+    let u_axis = normal.cross(&helper).normalize();
+    let v_axis = normal.cross(&u_axis);
+
+    positions
+        .iter()
+        .map(|position| Vector2::new(position.dot(&u_axis), position.dot(&v_axis)))
+        .collect()
+}
+
+/// Repeatedly clip a convex corner that has no other vertices inside of it until only one
+/// triangle remains.
+///
+/// @see https://www.geometrictools.com/Documentation/TriangulationByEarClipping.pdf
+fn ear_clip(projected: &[Vector2<f32>]) -> Result<Vec<usize>, (Vec<usize>, FaceProblem)> {
+    let mut remaining: Vec<usize> = (0..projected.len()).collect();
+    let mut triangles = Vec::with_capacity((projected.len() - 2) * 3);
+
+    while remaining.len() > 3 {
+        let ear = find_ear(projected, &remaining, false)
+            // Corners whose edges lie on a line don't form an ear, but clipping them is harmless
+            // when nothing else can be clipped.
+            .or_else(|| find_ear(projected, &remaining, true));
+
+        let ear = match ear {
+            Some(ear) => ear,
+            None => {
+                triangles.extend(fan(&remaining));
+                return Err((triangles, FaceProblem::SelfIntersecting));
+            }
+        };
+
+        let len = remaining.len();
+        triangles.push(remaining[(ear + len - 1) % len]);
+        triangles.push(remaining[ear]);
+        triangles.push(remaining[(ear + 1) % len]);
+
+        remaining.remove(ear);
+    }
+
+    triangles.extend(remaining);
+
+    Ok(triangles)
+}
+
+/// Find the position of an ear within the remaining corners, starting from the second corner so
+/// that convex faces become a fan around their first vertex.
+fn find_ear(projected: &[Vector2<f32>], remaining: &[usize], allow_flat: bool) -> Option<usize> {
+    let len = remaining.len();
+
+    (1..=len).map(|offset| offset % len).find(|&ear| {
+        let prev = projected[remaining[(ear + len - 1) % len]];
+        let current = projected[remaining[ear]];
+        let next = projected[remaining[(ear + 1) % len]];
+
+        let corner = cross_2d(current - prev, next - current);
+        if corner < 0.0 || (corner == 0.0 && !allow_flat) {
+            return false;
+        }
+
+        !remaining.iter().any(|&other| {
+            let other = projected[other];
+
+            // Vertices that share a position with a corner, such as at a hole's bridge, don't
+            // block the ear.
+            if other == prev || other == current || other == next {
+                return false;
+            }
+
+            is_inside_triangle(other, prev, current, next)
+        })
+    })
+}
+
+/// Whether or not a point is inside of, or on the edge of, a counter clockwise triangle.
+fn is_inside_triangle(
+    point: Vector2<f32>,
+    a: Vector2<f32>,
+    b: Vector2<f32>,
+    c: Vector2<f32>,
+) -> bool {
+    cross_2d(b - a, point - a) >= 0.0
+        && cross_2d(c - b, point - b) >= 0.0
+        && cross_2d(a - c, point - c) >= 0.0
+}
+
+fn cross_2d(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Split a polygon into triangles that all share its first corner.
+fn fan(corners: &[usize]) -> Vec<usize> {
+    let mut triangles = vec![];
+
+    for idx in 1..corners.len() - 1 {
+        triangles.push(corners[0]);
+        triangles.push(corners[idx]);
+        triangles.push(corners[idx + 1]);
     }
+
+    triangles
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::vertex_attributes::{
-        IndexedAttribute, MultiIndexedVertexAttributes, VertexAttribute,
-    };
+    use crate::vertex_attributes::MultiIndexedVertexAttributes;
 
     #[test]
     fn triangulate_faces() {
-        let start_mesh = BlenderMesh {
-            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
-                positions: IndexedAttribute {
-                    indices: vec![0, 1, 2, 3, 4, 5, 6, 7],
-                    attribute: VertexAttribute::default(),
-                },
-                vertices_in_each_face: vec![4, 4],
-                ..MultiIndexedVertexAttributes::default()
-            }
-            .into(),
-            ..BlenderMesh::default()
-        };
+        let start_mesh = mesh(vec![4, 4]);
 
-        let triangulated_indices = start_mesh.triangulate(&vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        let vertices = vertices(&[
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [1., 1., 0.],
+            [0., 1., 0.],
+            [0., 0., 1.],
+            [1., 0., 1.],
+            [1., 1., 1.],
+            [0., 1., 1.],
+        ]);
+
+        let (triangulated_indices, warnings) =
+            start_mesh.triangulate(&[0, 1, 2, 3, 4, 5, 6, 7], &vertices);
         assert_eq!(
             triangulated_indices,
             vec![0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]
         );
+        assert_eq!(warnings, vec![]);
+    }
+
+    /// Verify that we don't clip the reflex corner of a concave n-gon, and that every triangle
+    /// keeps the face's winding.
+    ///
+    /// ```text
+    /// 4 ------- 3
+    /// |         |
+    /// |    1    |
+    /// |  /   \  |
+    /// 0        2
+    /// ```
+    #[test]
+    fn concave_ngon() {
+        let positions = [
+            [0., 0., 0.],
+            [1., 1., 0.],
+            [2., 0., 0.],
+            [2., 3., 0.],
+            [0., 3., 0.],
+        ];
+        let start_mesh = mesh(vec![5]);
+
+        let (triangulated, warnings) =
+            start_mesh.triangulate(&[0, 1, 2, 3, 4], &vertices(&positions));
+
+        assert_eq!(warnings, vec![]);
+        assert_eq!(triangulated, vec![1, 2, 3, 0, 1, 3, 0, 3, 4]);
+
+        for triangle in triangulated.chunks(3) {
+            let a = Vector3::from(positions[triangle[0] as usize]);
+            let b = Vector3::from(positions[triangle[1] as usize]);
+            let c = Vector3::from(positions[triangle[2] as usize]);
+
+            assert!((b - a).cross(&(c - a)).z > 0.0);
+        }
+    }
+
+    /// Verify that faces whose vertices are not on one plane are triangulated on their best fit
+    /// plane.
+    #[test]
+    fn non_planar_ngon() {
+        let start_mesh = mesh(vec![5]);
+        let vertices = vertices(&[
+            [0., 0., 0.],
+            [1., 0., 0.1],
+            [1.5, 1., -0.1],
+            [0.5, 1.5, 0.1],
+            [-0.5, 1., -0.1],
+        ]);
+
+        let (triangulated, warnings) = start_mesh.triangulate(&[0, 1, 2, 3, 4], &vertices);
+
+        assert_eq!(warnings, vec![]);
+        assert_eq!(triangulated, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    /// Verify that faces with no area are reported instead of causing a panic
+    #[test]
+    fn degenerate_face_warning() {
+        let start_mesh = mesh(vec![3, 4]);
+        let vertices = vertices(&[
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [0., 1., 0.],
+            [0., 0., 0.],
+            [1., 0., 0.],
+            [2., 0., 0.],
+            [3., 0., 0.],
+        ]);
+
+        let (triangulated, warnings) = start_mesh.triangulate(&[0, 1, 2, 3, 4, 5, 6], &vertices);
+
+        assert_eq!(triangulated, vec![0, 1, 2, 3, 4, 5, 3, 5, 6]);
+        assert_eq!(
+            warnings,
+            vec![TriangulationWarning::DegenerateFace { face_idx: 1 }]
+        );
+    }
+
+    fn mesh(vertices_in_each_face: Vec<u8>) -> BlenderMesh {
+        BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face,
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        }
+    }
+
+    fn vertices(positions: &[[f32; 3]]) -> Vec<Vertex> {
+        positions
+            .iter()
+            .map(|position| Vertex {
+                position: *position,
+                ..Vertex::default()
+            })
+            .collect()
     }
 }
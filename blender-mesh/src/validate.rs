@@ -0,0 +1,518 @@
+//! Checking a mesh for data that would cause problems later on, such as while combining its
+//! vertex indices.
+
+use crate::bone::BoneInfluencesPerVertex;
+use crate::vertex_attributes::IndexedAttribute;
+use crate::BlenderMesh;
+use nalgebra::Vector3;
+
+/// The smallest face area and normal length that we don't consider to be zero.
+const EPSILON: f32 = 1e-12;
+
+/// How far a vertex's bone weights can be from summing to one.
+const BONE_WEIGHT_SUM_TOLERANCE: f32 = 0.01;
+
+/// A problem with a mesh's data, found by [`BlenderMesh.validate`].
+///
+/// Attributes are named `positions`, `normals`, `uvs`, `colors`, `uv layer <name>` or
+/// `custom attribute <name>`.
+///
+/// [`BlenderMesh.validate`]: struct.BlenderMesh.html#method.validate
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum MeshProblem {
+    /// An index points past the end of its attribute's data.
+    #[error("{attribute} index {index} at face vertex {face_vertex} is out of range, there are {vertex_count} vertices")]
+    IndexOutOfRange {
+        /// The attribute
+        attribute: String,
+        /// The position of the index in the attribute's indices
+        face_vertex: usize,
+        /// The index
+        index: u32,
+        /// The number of vertices in the attribute's data
+        vertex_count: usize,
+    },
+    /// Every attribute needs one index per face vertex, just like the positions.
+    #[error("{attribute} has {count} indices but the positions have {expected}")]
+    IndexCountMismatch {
+        /// The attribute
+        attribute: String,
+        /// The number of indices
+        count: usize,
+        /// The number of position indices
+        expected: usize,
+    },
+    /// The attribute's data doesn't divide evenly into vertices.
+    #[error("{attribute} has {len} values which can't be split into vertices of {attribute_size} values each")]
+    LengthNotMultipleOfSize {
+        /// The attribute
+        attribute: String,
+        /// The number of values in the data
+        len: usize,
+        /// The number of values per vertex
+        attribute_size: u8,
+    },
+    /// A value is NaN or infinite.
+    #[error("{attribute} value {data_index} is {value}")]
+    NonFiniteValue {
+        /// The attribute
+        attribute: String,
+        /// The position of the value in the attribute's data
+        data_index: usize,
+        /// The value
+        value: f32,
+    },
+    /// The number of vertices in every face adds up to a different number than the number of
+    /// position indices.
+    #[error("The faces have {face_vertex_count} vertices in total but there are {index_count} position indices")]
+    FaceVertexCountMismatch {
+        /// The sum of `vertices_in_each_face`
+        face_vertex_count: usize,
+        /// The number of position indices
+        index_count: usize,
+    },
+    /// A face with fewer than three vertices, which can't be triangulated.
+    #[error("Face {face} only has {vertex_count} vertices")]
+    TooFewFaceVertices {
+        /// The index of the face
+        face: usize,
+        /// The number of vertices in the face
+        vertex_count: u8,
+    },
+    /// A face with no area, such as one whose vertices are all in a line.
+    #[error("Face {face} is degenerate")]
+    DegenerateFace {
+        /// The index of the face
+        face: usize,
+    },
+    /// A normal with no length, which can't be normalized.
+    #[error("Normal {normal} has zero length")]
+    ZeroLengthNormal {
+        /// The index of the normal in the normals data
+        normal: usize,
+    },
+    /// A face uses a material slot that the mesh doesn't have.
+    #[error("Face {face} uses material slot {material_index} but there are {slot_count} slots")]
+    MaterialIndexOutOfRange {
+        /// The index of the face
+        face: usize,
+        /// The material slot
+        material_index: u16,
+        /// The number of material slots
+        slot_count: usize,
+    },
+    /// The bone influences don't line up with the positions, or with their own indices and
+    /// weights.
+    #[error("There are bone influences for {count} {what} but {expected} were expected")]
+    BoneInfluenceCountMismatch {
+        /// What was counted, such as `vertices` or `bone weights`
+        what: String,
+        /// The number of them
+        count: usize,
+        /// The number that there should be
+        expected: usize,
+    },
+    /// A vertex's bone weights don't sum to one.
+    #[error("The bone weights of vertex {vertex} sum to {sum}")]
+    BoneWeightSum {
+        /// The index of the vertex in the positions data
+        vertex: usize,
+        /// The sum of the weights
+        sum: f32,
+    },
+}
+
+impl MeshProblem {
+    /// Whether or not the problem would cause us to read past the end of the mesh's data while
+    /// combining its vertex indices. Other problems, such as degenerate faces, can lead to
+    /// strange looking vertex data but don't stop us from combining the indices.
+    pub(crate) fn prevents_combining_indices(&self) -> bool {
+        match self {
+            MeshProblem::IndexOutOfRange { .. }
+            | MeshProblem::IndexCountMismatch { .. }
+            | MeshProblem::LengthNotMultipleOfSize { .. }
+            | MeshProblem::FaceVertexCountMismatch { .. }
+            | MeshProblem::TooFewFaceVertices { .. }
+            | MeshProblem::BoneInfluenceCountMismatch { .. } => true,
+            MeshProblem::NonFiniteValue { .. }
+            | MeshProblem::DegenerateFace { .. }
+            | MeshProblem::ZeroLengthNormal { .. }
+            | MeshProblem::MaterialIndexOutOfRange { .. }
+            | MeshProblem::BoneWeightSum { .. } => false,
+        }
+    }
+}
+
+impl BlenderMesh {
+    /// Look for data that would cause problems when using the mesh, such as indices that are
+    /// out of range or values that are NaN.
+    ///
+    /// Returns every problem that was found, so an empty list means that the mesh is valid.
+    ///
+    /// Vertices without any bone influences are allowed, but vertices that do have bone
+    /// influences should have weights that sum to one.
+    pub fn validate(&self) -> Vec<MeshProblem> {
+        let mut problems = vec![];
+        let multi = &self.multi_indexed_vertex_attributes;
+
+        let index_count = multi.positions.indices.len();
+        let face_vertex_count: usize = multi
+            .vertices_in_each_face
+            .iter()
+            .map(|count| *count as usize)
+            .sum();
+        if face_vertex_count != index_count {
+            problems.push(MeshProblem::FaceVertexCountMismatch {
+                face_vertex_count,
+                index_count,
+            });
+        }
+
+        for (name, attribute) in self.named_attributes() {
+            validate_attribute(&name, attribute, index_count, &mut problems);
+        }
+
+        if face_vertex_count == index_count {
+            self.validate_faces(&mut problems);
+        }
+
+        if let Some(normals) = multi.normals.as_ref() {
+            for (normal, values) in normals.attribute.data.chunks_exact(3).enumerate() {
+                if Vector3::new(values[0], values[1], values[2]).norm_squared() < EPSILON {
+                    problems.push(MeshProblem::ZeroLengthNormal { normal });
+                }
+            }
+        }
+
+        self.validate_bone_influences(&mut problems);
+
+        problems
+    }
+
+    /// Every attribute along with its name, in a consistent order.
+    fn named_attributes(&self) -> Vec<(String, &IndexedAttribute)> {
+        let multi = &self.multi_indexed_vertex_attributes;
+
+        let mut attributes = vec![("positions".to_string(), &multi.positions)];
+        let optional = [
+            ("normals", multi.normals.as_ref()),
+            ("uvs", multi.uvs.as_ref()),
+            ("colors", multi.colors.as_ref()),
+        ];
+        for (name, attribute) in optional.iter() {
+            if let Some(attribute) = attribute {
+                attributes.push((name.to_string(), attribute));
+            }
+        }
+
+        let mut uv_layers: Vec<_> = multi.uv_layers.iter().collect();
+        uv_layers.sort_by_key(|(name, _)| *name);
+        for (name, attribute) in uv_layers {
+            attributes.push((format!("uv layer {}", name), attribute));
+        }
+
+        let mut custom_attributes: Vec<_> = multi.custom_attributes.iter().collect();
+        custom_attributes.sort_by_key(|(name, _)| *name);
+        for (name, attribute) in custom_attributes {
+            attributes.push((format!("custom attribute {}", name), attribute));
+        }
+
+        attributes
+    }
+
+    fn validate_faces(&self, problems: &mut Vec<MeshProblem>) {
+        let multi = &self.multi_indexed_vertex_attributes;
+        let positions = &multi.positions;
+        let position_count = vertex_count(positions);
+
+        let mut face_pointer = 0;
+
+        for (face, num_verts_in_face) in multi.vertices_in_each_face.iter().enumerate() {
+            if *num_verts_in_face < 3 {
+                problems.push(MeshProblem::TooFewFaceVertices {
+                    face,
+                    vertex_count: *num_verts_in_face,
+                });
+            }
+
+            let num_verts_in_face = *num_verts_in_face as usize;
+            let indices = &positions.indices[face_pointer..face_pointer + num_verts_in_face];
+            face_pointer += num_verts_in_face;
+
+            if positions.attribute.attribute_size != 3
+                || indices
+                    .iter()
+                    .any(|index| *index as usize >= position_count)
+            {
+                continue;
+            }
+
+            let corners: Vec<Vector3<f32>> = indices
+                .iter()
+                .map(|index| Vector3::from_column_slice(positions.attribute.data_at_idx(*index)))
+                .collect();
+
+            // Twice the area vector of the polygon, using Newell's method so that quads and
+            // n-gons work too.
+            let area = corners
+                .iter()
+                .zip(corners.iter().cycle().skip(1))
+                .fold(Vector3::zeros(), |area, (current, next)| {
+                    area + current.cross(next)
+                });
+
+            if num_verts_in_face >= 3 && area.norm_squared() < EPSILON {
+                problems.push(MeshProblem::DegenerateFace { face });
+            }
+        }
+
+        let slot_count = self.material_slots.len();
+        for (face, material_index) in multi.material_indices.iter().enumerate() {
+            if *material_index as usize >= slot_count.max(1) {
+                problems.push(MeshProblem::MaterialIndexOutOfRange {
+                    face,
+                    material_index: *material_index,
+                    slot_count,
+                });
+            }
+        }
+    }
+
+    fn validate_bone_influences(&self, problems: &mut Vec<MeshProblem>) {
+        let multi = &self.multi_indexed_vertex_attributes;
+
+        let influences = match multi.bone_influences.as_ref() {
+            Some(influences) => influences,
+            None => return,
+        };
+
+        let position_count = multi.positions.attribute.data.len() / 3;
+        let counts: Vec<u8> = match &influences.bones_per_vertex {
+            BoneInfluencesPerVertex::NonUniform(counts) => counts.clone(),
+            BoneInfluencesPerVertex::Uniform(count) => vec![*count; position_count],
+        };
+
+        if counts.len() != position_count {
+            problems.push(MeshProblem::BoneInfluenceCountMismatch {
+                what: "vertices".to_string(),
+                count: counts.len(),
+                expected: position_count,
+            });
+        }
+
+        let influence_count: usize = counts.iter().map(|count| *count as usize).sum();
+        for (what, count) in [
+            ("bone indices", influences.bone_indices.len()),
+            ("bone weights", influences.bone_weights.len()),
+        ]
+        .iter()
+        {
+            if *count != influence_count {
+                problems.push(MeshProblem::BoneInfluenceCountMismatch {
+                    what: what.to_string(),
+                    count: *count,
+                    expected: influence_count,
+                });
+            }
+        }
+
+        let mut weights = influences.bone_weights.iter();
+        for (vertex, count) in counts.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+
+            let sum: f32 = weights.by_ref().take(*count as usize).sum();
+            if !sum.is_finite() || (sum - 1.0).abs() > BONE_WEIGHT_SUM_TOLERANCE {
+                problems.push(MeshProblem::BoneWeightSum { vertex, sum });
+            }
+        }
+    }
+}
+
+fn validate_attribute(
+    name: &str,
+    attribute: &IndexedAttribute,
+    index_count: usize,
+    problems: &mut Vec<MeshProblem>,
+) {
+    if attribute.indices.len() != index_count {
+        problems.push(MeshProblem::IndexCountMismatch {
+            attribute: name.to_string(),
+            count: attribute.indices.len(),
+            expected: index_count,
+        });
+    }
+
+    let data = &attribute.attribute.data;
+    let attribute_size = attribute.attribute.attribute_size;
+    if (attribute_size == 0 && !data.is_empty())
+        || (attribute_size != 0 && !data.len().is_multiple_of(attribute_size as usize))
+    {
+        problems.push(MeshProblem::LengthNotMultipleOfSize {
+            attribute: name.to_string(),
+            len: data.len(),
+            attribute_size,
+        });
+    }
+
+    let count = vertex_count(attribute);
+    for (face_vertex, index) in attribute.indices.iter().enumerate() {
+        if *index as usize >= count {
+            problems.push(MeshProblem::IndexOutOfRange {
+                attribute: name.to_string(),
+                face_vertex,
+                index: *index,
+                vertex_count: count,
+            });
+        }
+    }
+
+    for (data_index, value) in data.iter().enumerate() {
+        if !value.is_finite() {
+            problems.push(MeshProblem::NonFiniteValue {
+                attribute: name.to_string(),
+                data_index,
+                value: *value,
+            });
+        }
+    }
+}
+
+/// The number of complete vertices in the attribute's data.
+fn vertex_count(attribute: &IndexedAttribute) -> usize {
+    match attribute.attribute.attribute_size {
+        0 => 0,
+        size => attribute.attribute.data.len() / size as usize,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex_attributes::{
+        MultiIndexedVertexAttributes, VertexAttribute, VertexAttributeError, VertexBoneInfluences,
+    };
+
+    /// Verify that a valid mesh has no problems
+    #[test]
+    fn valid_mesh() {
+        assert_eq!(BlenderMesh::pbr_cube_without_textures().validate(), vec![]);
+    }
+
+    /// Verify that we catch problems with the indices and the data that they point to
+    #[test]
+    fn attribute_problems() {
+        let mut mesh = triangle();
+        let multi = &mut mesh.multi_indexed_vertex_attributes;
+        multi.positions.indices[2] = 5;
+        multi.positions.attribute.data[1] = f32::NAN;
+        multi.normals = Some(IndexedAttribute {
+            indices: vec![0, 0],
+            attribute: (vec![0.0, 0.0, 0.0, 1.0], 3).into(),
+        });
+
+        let problems = mesh.validate();
+
+        assert!(problems.contains(&MeshProblem::IndexOutOfRange {
+            attribute: "positions".to_string(),
+            face_vertex: 2,
+            index: 5,
+            vertex_count: 3
+        }));
+        assert!(problems.contains(&MeshProblem::IndexCountMismatch {
+            attribute: "normals".to_string(),
+            count: 2,
+            expected: 3
+        }));
+        assert!(problems.contains(&MeshProblem::LengthNotMultipleOfSize {
+            attribute: "normals".to_string(),
+            len: 4,
+            attribute_size: 3
+        }));
+        assert!(problems.contains(&MeshProblem::ZeroLengthNormal { normal: 0 }));
+        assert!(problems.iter().any(|problem| match problem {
+            MeshProblem::NonFiniteValue {
+                attribute,
+                data_index: 1,
+                ..
+            } => attribute == "positions",
+            _ => false,
+        }));
+    }
+
+    /// Verify that we catch faces without area and face vertex counts that don't match the
+    /// indices
+    #[test]
+    fn face_problems() {
+        let mut mesh = triangle();
+        mesh.multi_indexed_vertex_attributes
+            .positions
+            .attribute
+            .data = vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0];
+        assert_eq!(
+            mesh.validate(),
+            vec![MeshProblem::DegenerateFace { face: 0 }]
+        );
+
+        mesh.multi_indexed_vertex_attributes.vertices_in_each_face = vec![4];
+        assert_eq!(
+            mesh.validate(),
+            vec![MeshProblem::FaceVertexCountMismatch {
+                face_vertex_count: 4,
+                index_count: 3
+            }]
+        );
+    }
+
+    /// Verify that we catch bone weights that don't sum to one
+    #[test]
+    fn bone_weight_sum() {
+        let mut mesh = triangle();
+        mesh.multi_indexed_vertex_attributes.bone_influences = Some(VertexBoneInfluences {
+            bones_per_vertex: BoneInfluencesPerVertex::NonUniform(vec![2, 0, 1]),
+            bone_indices: vec![0, 1, 0],
+            bone_weights: vec![0.25, 0.75, 0.5],
+            bone_names: vec![],
+        });
+
+        assert_eq!(
+            mesh.validate(),
+            vec![MeshProblem::BoneWeightSum {
+                vertex: 2,
+                sum: 0.5
+            }]
+        );
+    }
+
+    /// Verify that vertex attributes can't be created with data that doesn't divide into vertices
+    #[test]
+    fn new_vertex_attribute() {
+        assert!(VertexAttribute::new(vec![0.0; 6], 3).is_ok());
+        assert_eq!(
+            VertexAttribute::new(vec![0.0; 4], 3),
+            Err(VertexAttributeError::LengthNotMultipleOfSize {
+                len: 4,
+                attribute_size: 3
+            })
+        );
+        assert_eq!(
+            VertexAttribute::<f32>::new(vec![], 0),
+            Err(VertexAttributeError::ZeroAttributeSize)
+        );
+    }
+
+    fn triangle() -> BlenderMesh {
+        BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![3],
+                positions: IndexedAttribute {
+                    indices: vec![0, 1, 2],
+                    attribute: (vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], 3).into(),
+                },
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        }
+    }
+}
@@ -1,7 +1,9 @@
 mod vertex_attribute;
 
-pub use self::vertex_attribute::{BoneAttributes, VertexAttribute};
+pub use self::vertex_attribute::{BoneAttributes, VertexAttribute, VertexAttributeError};
 use crate::bone::BoneInfluencesPerVertex;
+use crate::serialize_hashmap_deterministic;
+use std::collections::HashMap;
 
 mod single_indexed;
 pub use self::single_indexed::*;
@@ -53,7 +55,7 @@ pub use self::single_indexed::*;
 /// triangle.
 ///
 /// [`vertices_in_each_face`]: struct.MultiIndexVertexData.html#method.vertices_in_each_face
-pub type VertexIndices = Vec<u16>;
+pub type VertexIndices = Vec<u32>;
 
 /// Per vertex data from the BlenderMesh.
 ///
@@ -78,8 +80,6 @@ impl Default for VertexAttributes {
 /// Vertex data with multiple indices - not suited for OpenGL and other single index rendering
 /// pipelines, but good for on disk storage as their is less data duplicated when there are
 /// multiple indices.
-///
-/// TODO: A HashMap so that we can have arbitrary vertex attributes
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct MultiIndexedVertexAttributes {
     // The number of vertices that comprise each face of the mesh.
@@ -94,12 +94,101 @@ pub struct MultiIndexedVertexAttributes {
     // - Calculating vertex tangents, where all vertices in the same face will have the same
     //   tangent.
     pub(crate) vertices_in_each_face: Vec<u8>,
+    // The material slot that each face uses. Empty if the mesh has no material slots.
+    #[serde(default)]
+    pub(crate) material_indices: Vec<u16>,
     pub(crate) positions: IndexedAttribute,
     pub(crate) normals: Option<IndexedAttribute>,
+    // The active UV layer.
     pub(crate) uvs: Option<IndexedAttribute>,
+    // The name of the UV layer in `uvs`.
+    #[serde(default)]
+    pub(crate) active_uv_layer: Option<String>,
+    // Every UV layer other than the active one, keyed by name. For example, a lightmap UV.
+    #[serde(default, serialize_with = "serialize_hashmap_deterministic")]
+    pub(crate) uv_layers: HashMap<String, IndexedAttribute>,
     pub(crate) bone_influences: Option<VertexBoneInfluences>,
+    // Linear RGBA vertex colors, with an attribute size of 4 (or 3 for opaque RGB colors).
+    #[serde(default)]
+    pub(crate) colors: Option<IndexedAttribute>,
+    // Any other per vertex data, such as wind weights or painted masks, keyed by name.
+    //
+    // Each attribute can be of any size and has its own indices - one per face vertex, just like
+    // the positions.
+    #[serde(default, serialize_with = "serialize_hashmap_deterministic")]
+    pub(crate) custom_attributes: HashMap<String, IndexedAttribute>,
+}
+
+impl MultiIndexedVertexAttributes {
+    /// The name of the active UV layer, whose data is used for [`Vertex.uv`].
+    ///
+    /// [`Vertex.uv`]: struct.Vertex.html#method.uv
+    pub fn active_uv_layer(&self) -> Option<&String> {
+        self.active_uv_layer.as_ref()
+    }
+
+    /// The name of the bone that each bone index points to, or None if the mesh has no bone
+    /// influences.
+    ///
+    /// Empty for meshes that were exported before bone names were included.
+    pub fn bone_names(&self) -> Option<&[String]> {
+        self.bone_influences
+            .as_ref()
+            .map(|influences| influences.bone_names.as_slice())
+    }
+
+    /// The UV layers other than the active one, keyed by name.
+    pub fn uv_layers(&self) -> &HashMap<String, IndexedAttribute> {
+        &self.uv_layers
+    }
+
+    /// Get a UV layer by name, whether or not it is the active layer.
+    pub fn uv_layer(&self, name: &str) -> Option<&IndexedAttribute> {
+        match self.active_uv_layer.as_ref() {
+            Some(active) if active == name => self.uvs.as_ref(),
+            _ => self.uv_layers.get(name),
+        }
+    }
+
+    /// The material slot that each face uses, indexing into [`BlenderMesh.material_slots`].
+    ///
+    /// Empty if the mesh has no material slots.
+    ///
+    /// [`BlenderMesh.material_slots`]: ../struct.BlenderMesh.html#method.material_slots
+    pub fn material_indices(&self) -> &Vec<u16> {
+        &self.material_indices
+    }
+
+    /// Set the material slot that each face uses. There should be one index per face.
+    pub fn set_material_indices(&mut self, material_indices: Vec<u16>) {
+        self.material_indices = material_indices;
+    }
+
+    /// The mesh's linear RGBA vertex colors, if it has any.
+    pub fn colors(&self) -> Option<&IndexedAttribute> {
+        self.colors.as_ref()
+    }
+
+    /// Set the mesh's linear RGBA vertex colors. The attribute size should be 4, or 3 for opaque
+    /// RGB colors.
+    pub fn set_colors(&mut self, colors: Option<IndexedAttribute>) {
+        self.colors = colors;
+    }
+
+    /// Arbitrary named vertex attributes, such as wind weights or ambient occlusion.
+    pub fn custom_attributes(&self) -> &HashMap<String, IndexedAttribute> {
+        &self.custom_attributes
+    }
+
+    /// Insert a named vertex attribute, replacing any existing attribute with the same name.
+    ///
+    /// The attribute needs one index for every face vertex.
+    pub fn insert_custom_attribute(&mut self, name: String, attribute: IndexedAttribute) {
+        self.custom_attributes.insert(name, attribute);
+    }
 }
 
+/// A vertex attribute along with the indices into its data, one index per face vertex.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct IndexedAttribute {
     pub(crate) indices: VertexIndices,
@@ -111,6 +200,16 @@ impl IndexedAttribute {
     pub fn new(indices: VertexIndices, attribute: VertexAttribute<f32>) -> Self {
         IndexedAttribute { indices, attribute }
     }
+
+    /// One index into the attribute's data for each face vertex.
+    pub fn indices(&self) -> &VertexIndices {
+        &self.indices
+    }
+
+    /// The attribute's data.
+    pub fn attribute(&self) -> &VertexAttribute<f32> {
+        &self.attribute
+    }
 }
 
 impl From<(VertexIndices, VertexAttribute<f32>)> for IndexedAttribute {
@@ -154,4 +253,10 @@ pub struct VertexBoneInfluences {
     pub(crate) bone_indices: Vec<u8>,
     /// The corresponding weights of each bone index
     pub(crate) bone_weights: Vec<f32>,
+    /// The name of the bone that each bone index points to, in the order of the parent armature's
+    /// bones when the mesh was exported.
+    ///
+    /// Empty for meshes that were exported before bone names were included.
+    #[serde(default)]
+    pub(crate) bone_names: Vec<String>,
 }
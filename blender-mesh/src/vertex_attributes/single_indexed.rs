@@ -1,6 +1,10 @@
 mod interleave;
 
 pub use self::interleave::*;
+use crate::serialize_hashmap_deterministic;
+use crate::{CoordinateSystem, TriangulationWarning, VertexAttribute};
+use std::collections::HashMap;
+use std::ops::Range;
 
 /// Most 3D model file formats export vertex data with multiple indices.
 ///
@@ -14,21 +18,101 @@ pub use self::interleave::*;
 /// [`BlenderMesh.combine_vertex_indices`]: ../struct.BlenderMesh.html#method.combine_vertex_indices
 #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
 pub struct SingleIndexedVertexAttributes {
-    pub(crate) indices: Vec<u16>,
+    pub(crate) indices: Vec<u32>,
+    // Whether the indices fit into a u16 or need a u32.
+    #[serde(default)]
+    pub(crate) index_format: IndexFormat,
     pub(crate) vertices: Vec<Vertex>,
+    // The name of the UV layer that each `Vertex.uv` comes from.
+    #[serde(default)]
+    pub(crate) active_uv_layer: Option<String>,
+    // The mesh's other UV layers, with one entry per vertex.
+    #[serde(default, serialize_with = "serialize_hashmap_deterministic")]
+    pub(crate) uv_layers: HashMap<String, VertexAttribute<f32>>,
+    // The mesh's custom attributes, with one entry per vertex.
+    #[serde(default, serialize_with = "serialize_hashmap_deterministic")]
+    pub(crate) custom_attributes: HashMap<String, VertexAttribute<f32>>,
+    // Faces that could not be triangulated cleanly.
+    #[serde(default)]
+    pub(crate) triangulation_warnings: Vec<TriangulationWarning>,
+    // The range of indices that each material's triangles occupy, in order of material index.
+    #[serde(default)]
+    pub(crate) material_ranges: Vec<MaterialRange>,
+    // The coordinate system that the vertices are in.
+    #[serde(default)]
+    pub(crate) coordinate_system: CoordinateSystem,
+}
+
+/// A contiguous range of a mesh's indices whose triangles all use the same material slot.
+///
+/// Typically each range becomes its own draw call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialRange {
+    pub(crate) material_index: u16,
+    pub(crate) material_name: Option<String>,
+    pub(crate) indices: Range<usize>,
+}
+
+impl MaterialRange {
+    /// The index of the material slot that the triangles use.
+    pub fn material_index(&self) -> u16 {
+        self.material_index
+    }
+
+    /// The name of the material in the slot, or `None` if the slot is empty.
+    pub fn material_name(&self) -> Option<&String> {
+        self.material_name.as_ref()
+    }
+
+    /// The range within [`SingleIndexedVertexAttributes.indices`] that the triangles occupy.
+    ///
+    /// [`SingleIndexedVertexAttributes.indices`]: struct.SingleIndexedVertexAttributes.html#method.indices
+    pub fn indices(&self) -> Range<usize> {
+        self.indices.clone()
+    }
+}
+
+/// The integer type of a mesh's indices.
+///
+/// u16 indices use half of the memory, but can only point to 65,535 vertices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum IndexFormat {
+    /// Every index fits into a u16.
+    #[default]
+    U16,
+    /// The mesh has too many vertices for u16 indices, or u32 indices were requested.
+    U32,
+}
+
+impl IndexFormat {
+    /// The smallest index format that can point to every one of a mesh's vertices.
+    ///
+    /// We leave 65,535 unused in u16 indices since some graphics APIs always treat it as a
+    /// primitive restart.
+    pub fn for_vertex_count(vertex_count: usize) -> IndexFormat {
+        if vertex_count <= u16::MAX as usize {
+            IndexFormat::U16
+        } else {
+            IndexFormat::U32
+        }
+    }
 }
 
 /// A vertex within a mesh.
 ///
 /// You'll typically buffer the Vertex's data onto the GPU interleaved into a single buffer, and
 /// then index into that buffer using the indices from [`SingleIndexedVertexAttributes`].
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub struct Vertex {
     pub(crate) position: [f32; 3],
     pub(crate) normal: Option<[f32; 3]>,
     pub(crate) face_tangent: Option<[f32; 3]>,
+    #[serde(default)]
+    pub(crate) tangent: Option<[f32; 4]>,
     pub(crate) uv: Option<[f32; 2]>,
-    pub(crate) bones: Option<[BoneInfluence; 4]>,
+    #[serde(default)]
+    pub(crate) color: Option<[f32; 4]>,
+    pub(crate) bones: Option<Vec<BoneInfluence>>,
 }
 
 impl Vertex {
@@ -47,22 +131,39 @@ impl Vertex {
         self.face_tangent
     }
 
-    /// The UV coordinates for this Vertex
+    /// The MikkTSpace tangent for this Vertex. The w component is the handedness of the
+    /// bitangent, which is `w * cross(normal, tangent)`.
+    ///
+    /// Only calculated when [`CreateSingleIndexConfig.calculate_vertex_tangents`] is set.
+    ///
+    /// [`CreateSingleIndexConfig.calculate_vertex_tangents`]: struct.CreateSingleIndexConfig.html#structfield.calculate_vertex_tangents
+    pub fn tangent(&self) -> Option<[f32; 4]> {
+        self.tangent
+    }
+
+    /// The UV coordinates for this Vertex within the mesh's active UV layer.
+    ///
+    /// See [`SingleIndexedVertexAttributes.vertex_uv`] for the mesh's other UV layers.
+    ///
+    /// [`SingleIndexedVertexAttributes.vertex_uv`]: struct.SingleIndexedVertexAttributes.html#method.vertex_uv
     pub fn uv(&self) -> Option<[f32; 2]> {
         self.uv
     }
 
+    /// The linear RGBA vertex color.
+    pub fn color(&self) -> Option<[f32; 4]> {
+        self.color
+    }
+
     /// The bones that influence this Vertex.
     ///
-    /// Currently a maximum of 4 bones is supported for no other reason than it being uncommon to
-    /// need more than that.
+    /// Every vertex has [`CreateSingleIndexConfig.bone_influences_per_vertex`] bones, sorted from
+    /// the largest weight to the smallest. If fewer bones influence the vertex then the extra fake
+    /// bones will have weights of zero.
     ///
-    /// If this doesn't meet your needs pleas open an issue.
-    ///
-    /// If there are fewer than 4 influencing bones then the extra fake bones in this array will
-    /// have weights of zero.
-    pub fn bones(&self) -> Option<[BoneInfluence; 4]> {
-        self.bones
+    /// [`CreateSingleIndexConfig.bone_influences_per_vertex`]: struct.CreateSingleIndexConfig.html#structfield.bone_influences_per_vertex
+    pub fn bones(&self) -> Option<&[BoneInfluence]> {
+        self.bones.as_deref()
     }
 }
 
@@ -89,10 +190,25 @@ impl SingleIndexedVertexAttributes {
     /// For `SingleIndexVertexData` every 3 indices corresponds to one triangle.
     ///
     /// There can not be any other faces (quads, ngons) - only triangles.
-    pub fn indices(&self) -> &Vec<u16> {
+    pub fn indices(&self) -> &Vec<u32> {
         &self.indices
     }
 
+    /// The integer type that the indices should be buffered onto the GPU as.
+    pub fn index_format(&self) -> IndexFormat {
+        self.index_format
+    }
+
+    /// The indices as u16's, or `None` if the index format is [`IndexFormat::U32`].
+    ///
+    /// [`IndexFormat::U32`]: enum.IndexFormat.html#variant.U32
+    pub fn u16_indices(&self) -> Option<Vec<u16>> {
+        match self.index_format {
+            IndexFormat::U16 => Some(self.indices.iter().map(|idx| *idx as u16).collect()),
+            IndexFormat::U32 => None,
+        }
+    }
+
     /// All of the vertex data for the mesh.
     ///
     /// You can index into this data using [`SingleIndexedVertexAttributes#method.indices`]
@@ -100,7 +216,127 @@ impl SingleIndexedVertexAttributes {
         &self.vertices
     }
 
+    /// The number of bones that influence each vertex.
+    ///
+    /// None if the vertices don't have bone influences or if they have different numbers of them.
+    pub fn bone_influences_per_vertex(&self) -> Option<usize> {
+        let count = self.vertices.first()?.bones.as_ref()?.len();
+
+        self.vertices
+            .iter()
+            .all(|vertex| vertex.bones.as_ref().map(|bones| bones.len()) == Some(count))
+            .then_some(count)
+    }
+
+    /// The name of the UV layer that [`Vertex.uv`] comes from.
+    ///
+    /// [`Vertex.uv`]: struct.Vertex.html#method.uv
+    pub fn active_uv_layer(&self) -> Option<&String> {
+        self.active_uv_layer.as_ref()
+    }
+
+    /// The UV layers other than the active one keyed by name, with one entry per vertex.
+    pub fn uv_layers(&self) -> &HashMap<String, VertexAttribute<f32>> {
+        &self.uv_layers
+    }
+
+    /// The UV coordinates of a vertex within the named UV layer, whether or not it is the active
+    /// layer.
+    pub fn vertex_uv(&self, vertex_idx: u32, uv_layer: &str) -> Option<[f32; 2]> {
+        match self.active_uv_layer.as_ref() {
+            Some(active) if active == uv_layer => self.vertices.get(vertex_idx as usize)?.uv,
+            _ => {
+                let layer = self.uv_layers.get(uv_layer)?;
+                match layer
+                    .data
+                    .get(vertex_idx as usize * 2..vertex_idx as usize * 2 + 2)?
+                {
+                    &[u, v] => Some([u, v]),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Every vertex's color, with an attribute size of 4.
+    ///
+    /// Useful for [`SingleIndexedVertexAttributes::interleave`]ing colors with other attributes.
+    ///
+    /// [`SingleIndexedVertexAttributes::interleave`]: #method.interleave
+    pub fn colors(&self) -> Option<VertexAttribute<f32>> {
+        let colors = self
+            .vertices
+            .iter()
+            .map(|vertex| vertex.color)
+            .collect::<Option<Vec<[f32; 4]>>>()?;
+
+        Some(VertexAttribute {
+            data: colors.iter().flat_map(|color| color.to_vec()).collect(),
+            attribute_size: 4,
+        })
+    }
+
+    /// Faces that could not be triangulated cleanly, such as faces with no area.
+    ///
+    /// These faces were still triangulated, but they might not look the way that they did in
+    /// Blender.
+    pub fn triangulation_warnings(&self) -> &Vec<TriangulationWarning> {
+        &self.triangulation_warnings
+    }
+
+    /// The triangles of each material slot, grouped into contiguous ranges of the indices in
+    /// order of material index.
+    ///
+    /// Empty if the mesh has no material slots.
+    pub fn material_ranges(&self) -> &Vec<MaterialRange> {
+        &self.material_ranges
+    }
+
+    /// The ranges of the indices that can be reordered without mixing up materials - either
+    /// each material's range or, if there are none, all of the indices.
+    pub(crate) fn index_ranges(&self) -> Vec<Range<usize>> {
+        if self.material_ranges.is_empty() {
+            std::iter::once(0..self.indices.len()).collect()
+        } else {
+            self.material_ranges
+                .iter()
+                .map(|range| range.indices.clone())
+                .collect()
+        }
+    }
+
+    /// The mesh's custom attributes keyed by name, with one entry per vertex.
+    ///
+    /// You can index into these using the same indices as the vertices.
+    pub fn custom_attributes(&self) -> &HashMap<String, VertexAttribute<f32>> {
+        &self.custom_attributes
+    }
+
     pub(crate) fn vertices_mut(&mut self) -> &mut Vec<Vertex> {
         &mut self.vertices
     }
+
+    /// Replace the vertices, along with their uvs in other layers and custom attributes, with the
+    /// vertices at each index in `order`.
+    ///
+    /// The indices are left alone, so the caller needs to remap them.
+    pub(crate) fn reorder_vertices(&mut self, order: &[usize]) {
+        self.vertices = order
+            .iter()
+            .map(|idx| self.vertices[*idx].clone())
+            .collect();
+
+        for attribute in self
+            .uv_layers
+            .values_mut()
+            .chain(self.custom_attributes.values_mut())
+        {
+            let size = attribute.attribute_size as usize;
+
+            attribute.data = order
+                .iter()
+                .flat_map(|idx| attribute.data[idx * size..idx * size + size].to_vec())
+                .collect();
+        }
+    }
 }
@@ -71,6 +71,7 @@ impl SingleIndexedVertexAttributes {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Vertex;
 
     /// Interleave two vertex attributes
     #[test]
@@ -83,6 +84,25 @@ mod tests {
         assert_eq!(combined, vec![0., 1., 2., 50., 51., 3., 4., 5., 52., 53.]);
     }
 
+    /// Interleave vertex colors with a custom attribute
+    #[test]
+    fn interleave_colors() {
+        let vertex = |color: [f32; 4]| Vertex {
+            color: Some(color),
+            ..Vertex::default()
+        };
+        let single_indexed = SingleIndexedVertexAttributes {
+            vertices: vec![vertex([0., 1., 2., 3.]), vertex([4., 5., 6., 7.])],
+            ..SingleIndexedVertexAttributes::default()
+        };
+        let ao = VertexAttribute::new(vec![50., 51.], 1).unwrap();
+
+        let colors = single_indexed.colors().unwrap();
+        let combined = SingleIndexedVertexAttributes::interleave(&[&colors, &ao]).unwrap();
+
+        assert_eq!(combined, vec![0., 1., 2., 3., 50., 4., 5., 6., 7., 51.]);
+    }
+
     /// Trying to interleave one buffer is likely a mistake
     #[test]
     fn only_one_buffer_provided() {
@@ -96,7 +116,7 @@ mod tests {
     /// The lengths of all of the attributes should correspond to the same number of vertices
     #[test]
     fn error_if_incompatible_lengths() {
-        let positions = VertexAttribute::new(vec![0.], 3).unwrap();
+        let positions = VertexAttribute::new(vec![0., 1., 2.], 3).unwrap();
         let uvs = VertexAttribute::new(vec![50., 51., 52., 53.], 2).unwrap();
 
         match SingleIndexedVertexAttributes::interleave(&[&positions, &uvs]) {
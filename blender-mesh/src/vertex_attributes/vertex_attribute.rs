@@ -39,10 +39,23 @@ impl<T> From<(Vec<T>, u8)> for VertexAttribute<T> {
 }
 
 impl<T> VertexAttribute<T> {
-    /// TODO: Introduce thiserror and add error handling to this library
-    pub fn new(data: Vec<T>, attribute_size: u8) -> Result<VertexAttribute<T>, ()> {
-        if attribute_size as usize % data.len() != 0 {
-            // Return an error ...
+    /// Create a vertex attribute from its tightly packed data and the number of values per
+    /// vertex.
+    ///
+    /// The length of the data must be a multiple of the attribute size.
+    pub fn new(
+        data: Vec<T>,
+        attribute_size: u8,
+    ) -> Result<VertexAttribute<T>, VertexAttributeError> {
+        if attribute_size == 0 {
+            return Err(VertexAttributeError::ZeroAttributeSize);
+        }
+
+        if !data.len().is_multiple_of(attribute_size as usize) {
+            return Err(VertexAttributeError::LengthNotMultipleOfSize {
+                len: data.len(),
+                attribute_size,
+            });
         }
 
         Ok(VertexAttribute {
@@ -69,6 +82,24 @@ impl<T> VertexAttribute<T> {
     }
 }
 
+/// An error while creating a [`VertexAttribute`]
+///
+/// [`VertexAttribute`]: struct.VertexAttribute.html
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum VertexAttributeError {
+    /// Every vertex needs at least one value.
+    #[error("The attribute size must be at least 1")]
+    ZeroAttributeSize,
+    /// The data doesn't divide evenly into vertices.
+    #[error("{len} values can't be split into vertices of {attribute_size} values each")]
+    LengthNotMultipleOfSize {
+        /// The number of values in the data
+        len: usize,
+        /// The number of values per vertex
+        attribute_size: u8,
+    },
+}
+
 /// Used for vertex skinning
 #[derive(Debug, Serialize, Deserialize, PartialEq, Default)]
 pub struct BoneAttributes {
@@ -88,7 +119,7 @@ impl<T> VertexAttribute<T> {
     /// Given a vertex indexm return the data at that index.
     ///
     /// If there are 3 attributes per vertex the size will be 3, if 2 then 2, etc.
-    pub(crate) fn data_at_idx(&self, vertex_idx: u16) -> &[T] {
+    pub(crate) fn data_at_idx(&self, vertex_idx: u32) -> &[T] {
         let attribute_size = self.attribute_size as usize;
         let idx = (vertex_idx as usize) * attribute_size;
 
@@ -0,0 +1,310 @@
+use crate::{SingleIndexedVertexAttributes, Vertex};
+use std::collections::HashMap;
+
+/// Indicates an error while calculating the MikkTSpace tangents for a mesh's vertices
+#[derive(Debug, thiserror::Error)]
+pub enum VertexTangentError {
+    /// Tangents follow the direction that the uvs increase in.
+    #[error("Cannot calculate vertex tangents for a mesh with no uvs")]
+    NoVertexUvs,
+    /// Tangents are orthogonalized against the normal of each vertex.
+    #[error("Cannot calculate vertex tangents for a mesh with no normals")]
+    NoVertexNormals,
+    /// MikkTSpace could not process the mesh, such as when it has no faces.
+    #[error("MikkTSpace could not generate tangents for the mesh")]
+    MikkTSpace,
+}
+
+/// A face that we pass to MikkTSpace.
+pub(crate) struct TangentSpaceFace {
+    /// The vertex index of each of the face's corners. MikkTSpace supports triangles and quads.
+    pub corners: Vec<u32>,
+    /// The range within the triangulated indices that this face's triangles occupy.
+    pub triangulated: std::ops::Range<usize>,
+}
+
+impl SingleIndexedVertexAttributes {
+    /// Calculate a MikkTSpace tangent for every vertex, with the handedness of the bitangent in
+    /// the tangent's w component.
+    ///
+    /// MikkTSpace is what Blender uses when baking normal maps, so normal maps that were baked in
+    /// Blender will line up without seams, including across mirrored uvs.
+    ///
+    /// The bitangent is `w * cross(normal, tangent)`.
+    ///
+    /// Quads are passed to MikkTSpace as quads, just like Blender does, and every other face as
+    /// the triangles that it was triangulated into.
+    ///
+    /// Vertices whose corners end up with different tangents, such as a vertex on the seam of a
+    /// mirrored uv, are split into one vertex per tangent.
+    ///
+    /// @see http://www.mikktspace.com/
+    pub(crate) fn calculate_vertex_tangents(
+        &mut self,
+        faces: &[TangentSpaceFace],
+    ) -> Result<(), VertexTangentError> {
+        if self.vertices.iter().any(|vertex| vertex.uv.is_none()) {
+            return Err(VertexTangentError::NoVertexUvs);
+        }
+        if self.vertices.iter().any(|vertex| vertex.normal.is_none()) {
+            return Err(VertexTangentError::NoVertexNormals);
+        }
+
+        let mut geometry = MikkTSpaceGeometry {
+            vertices: &self.vertices,
+            faces,
+            tangents: faces
+                .iter()
+                .map(|face| vec![[0.0; 4]; face.corners.len()])
+                .collect(),
+        };
+
+        if !mikktspace::generate_tangents(&mut geometry) {
+            return Err(VertexTangentError::MikkTSpace);
+        }
+
+        let tangents = geometry.tangents;
+
+        // The vertex that was created for each vertex and tangent that had to be split off.
+        let mut split_vertices: HashMap<(u32, [u32; 4]), u32> = HashMap::new();
+
+        for (face, face_tangents) in faces.iter().zip(tangents) {
+            let mut remapped = HashMap::new();
+
+            for (corner, tangent) in face.corners.iter().zip(face_tangents) {
+                let vertex_idx = match self.vertices[*corner as usize].tangent {
+                    None => {
+                        self.vertices[*corner as usize].tangent = Some(tangent);
+                        *corner
+                    }
+                    Some(existing) if existing == tangent => *corner,
+                    Some(_) => {
+                        let key = (
+                            *corner,
+                            [
+                                tangent[0].to_bits(),
+                                tangent[1].to_bits(),
+                                tangent[2].to_bits(),
+                                tangent[3].to_bits(),
+                            ],
+                        );
+
+                        match split_vertices.get(&key) {
+                            Some(split) => *split,
+                            None => {
+                                let split = self.duplicate_vertex(*corner);
+                                self.vertices[split as usize].tangent = Some(tangent);
+                                split_vertices.insert(key, split);
+                                split
+                            }
+                        }
+                    }
+                };
+
+                remapped.insert(*corner, vertex_idx);
+            }
+
+            for index in self.indices[face.triangulated.clone()].iter_mut() {
+                *index = remapped[index];
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add a copy of a vertex, along with its uvs in other layers and custom attributes, and
+    /// return the copy's index.
+    fn duplicate_vertex(&mut self, vertex_idx: u32) -> u32 {
+        let vertex_idx = vertex_idx as usize;
+
+        self.vertices.push(self.vertices[vertex_idx].clone());
+
+        for attribute in self
+            .uv_layers
+            .values_mut()
+            .chain(self.custom_attributes.values_mut())
+        {
+            let size = attribute.attribute_size as usize;
+            let start = vertex_idx * size;
+
+            attribute.data.extend_from_within(start..start + size);
+        }
+
+        (self.vertices.len() - 1) as u32
+    }
+}
+
+struct MikkTSpaceGeometry<'a> {
+    vertices: &'a [Vertex],
+    faces: &'a [TangentSpaceFace],
+    tangents: Vec<Vec<[f32; 4]>>,
+}
+
+impl<'a> MikkTSpaceGeometry<'a> {
+    fn vertex(&self, face: usize, vert: usize) -> &Vertex {
+        &self.vertices[self.faces[face].corners[vert] as usize]
+    }
+}
+
+impl<'a> mikktspace::Geometry for MikkTSpaceGeometry<'a> {
+    fn num_faces(&self) -> usize {
+        self.faces.len()
+    }
+
+    fn num_vertices_of_face(&self, face: usize) -> usize {
+        self.faces[face].corners.len()
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).position
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+        self.vertex(face, vert).normal.unwrap()
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+        self.vertex(face, vert).uv.unwrap()
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+        self.tangents[face][vert] = tangent;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlenderMesh, CreateSingleIndexConfig, IndexedAttribute};
+    use crate::{MultiIndexedVertexAttributes, VertexAttribute};
+
+    /// Verify that the tangents of a flat quad point along the direction that u increases in
+    #[test]
+    fn quad_tangents() {
+        let single_indexed = combine_with_tangents(vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+
+        for vertex in single_indexed.vertices() {
+            assert_tangent_eq(vertex.tangent().unwrap(), [1.0, 0.0, 0.0, 1.0]);
+        }
+    }
+
+    /// Verify that mirrored uvs flip the handedness of the tangent
+    #[test]
+    fn mirrored_uv_handedness() {
+        let single_indexed = combine_with_tangents(vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0]);
+
+        for vertex in single_indexed.vertices() {
+            assert_tangent_eq(vertex.tangent().unwrap(), [-1.0, 0.0, 0.0, -1.0]);
+        }
+    }
+
+    /// Verify that a vertex that is shared by faces with mirrored uvs is split into one vertex per
+    /// tangent.
+    #[test]
+    fn split_vertices_with_different_tangents() {
+        let mut single_indexed = SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 2, 0, 2, 3],
+            vertices: vec![
+                vertex([0., 0., 0.], [0., 0.]),
+                vertex([1., 0., 0.], [1., 0.]),
+                vertex([1., 1., 0.], [1., 1.]),
+                vertex([0., 1., 0.], [1., 0.]),
+            ],
+            ..SingleIndexedVertexAttributes::default()
+        };
+        single_indexed.custom_attributes.insert(
+            "wind".to_string(),
+            VertexAttribute::new(vec![0., 1., 2., 3.], 1).unwrap(),
+        );
+
+        single_indexed
+            .calculate_vertex_tangents(&[
+                TangentSpaceFace {
+                    corners: vec![0, 1, 2],
+                    triangulated: 0..3,
+                },
+                TangentSpaceFace {
+                    corners: vec![0, 2, 3],
+                    triangulated: 3..6,
+                },
+            ])
+            .unwrap();
+
+        // The second triangle's uvs are mirrored, so both of the vertices that it shares with the
+        // first triangle are split.
+        assert_eq!(single_indexed.vertices().len(), 6);
+        assert_eq!(single_indexed.indices(), &vec![0, 1, 2, 4, 5, 3]);
+        assert_eq!(
+            single_indexed.vertices()[4].position(),
+            single_indexed.vertices()[0].position()
+        );
+        assert_eq!(
+            single_indexed.custom_attributes()["wind"].data(),
+            &vec![0., 1., 2., 3., 0., 2.]
+        );
+    }
+
+    /// Verify that we return an error when the mesh has no uvs
+    #[test]
+    fn error_if_no_uvs() {
+        let mut single_indexed = SingleIndexedVertexAttributes {
+            indices: vec![0, 1, 2],
+            vertices: vec![Vertex::default(); 3],
+            ..SingleIndexedVertexAttributes::default()
+        };
+
+        match single_indexed.calculate_vertex_tangents(&[]) {
+            Err(VertexTangentError::NoVertexUvs) => {}
+            _ => unreachable!(),
+        };
+    }
+
+    fn combine_with_tangents(uvs: Vec<f32>) -> SingleIndexedVertexAttributes {
+        let mut mesh = BlenderMesh {
+            multi_indexed_vertex_attributes: MultiIndexedVertexAttributes {
+                vertices_in_each_face: vec![4],
+                positions: IndexedAttribute::new(
+                    vec![0, 1, 2, 3],
+                    VertexAttribute::new(vec![0., 0., 0., 1., 0., 0., 1., 1., 0., 0., 1., 0.], 3)
+                        .unwrap(),
+                ),
+                normals: Some(IndexedAttribute::new(
+                    vec![0, 0, 0, 0],
+                    VertexAttribute::new(vec![0., 0., 1.], 3).unwrap(),
+                )),
+                uvs: Some(IndexedAttribute::new(
+                    vec![0, 1, 2, 3],
+                    VertexAttribute::new(uvs, 2).unwrap(),
+                )),
+                ..MultiIndexedVertexAttributes::default()
+            },
+            ..BlenderMesh::default()
+        };
+
+        mesh.combine_vertex_indices(&CreateSingleIndexConfig {
+            calculate_vertex_tangents: true,
+            ..CreateSingleIndexConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn vertex(position: [f32; 3], uv: [f32; 2]) -> Vertex {
+        Vertex {
+            position,
+            normal: Some([0., 0., 1.]),
+            uv: Some(uv),
+            ..Vertex::default()
+        }
+    }
+
+    fn assert_tangent_eq(actual: [f32; 4], expected: [f32; 4]) {
+        for idx in 0..4 {
+            assert!(
+                (actual[idx] - expected[idx]).abs() < 1e-5,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+}
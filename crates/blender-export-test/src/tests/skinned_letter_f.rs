@@ -138,7 +138,8 @@ fn expected_mesh_data() -> String {
                     "NonUniform": [1,1,1,1,2,2,2,2,3,3,3,3,2,2,3,3,2,2,4,4,2,2,3,3,1,1,1,1,1,1,1,1,2,2,1,1,1,1,1,1,1,1,2,3,2,2,1,1,1,1,1,1,1,1,2,3,2,1,1,1,1,1,1,1,1,2,2,2,1,1,1,2,2,1,1,1,1,1,1,1,1,1,1,1,2,2,2,2,2,2,2,2,3,4,4,3,3,4,4,3,2,2,2,3,2,2,2,3,3,3,3,4,3,3,3,4,3,3,3,3,3,3,3,3,1,1,1,1,1,2,2,1,1,1,1,1,2,2,1,1,1,1,2,2,2,1,1,1,1,2,2,2,3,2,1,1,1,1,1,3,3,1,1,1,1,1,3,2,1,1,1,1,1,3,2,2,1,1,1,1,1,1,1,1,1,2,3,1,1,1,1,1,3,3,1,1,1,1,1,2,3,1,1,1,1,2,2,3]
                 },
                 "bone_indices": [0,0,0,0,1,2,1,2,1,2,1,2,0,1,2,0,1,2,1,2,3,1,2,3,1,2,1,2,1,2,3,1,2,3,0,1,0,1,0,1,2,3,0,1,2,3,0,1,0,1,0,1,3,0,1,3,3,3,3,3,2,2,2,2,0,3,0,3,3,3,3,3,3,3,3,3,1,3,0,1,3,0,3,0,3,3,3,3,3,3,3,3,3,1,3,0,1,3,1,2,2,2,2,2,2,2,2,2,1,2,1,2,1,2,2,2,2,1,2,1,2,2,2,2,0,0,0,0,0,0,0,0,1,2,1,2,1,2,1,2,1,2,1,2,1,2,1,2,0,1,2,0,1,2,3,0,1,2,3,1,2,3,0,1,2,0,1,2,3,0,1,2,3,1,2,3,1,2,1,2,1,2,1,2,3,1,2,1,2,1,2,1,2,3,0,1,3,0,1,3,0,1,3,0,1,2,3,0,1,3,0,1,3,0,1,3,0,1,2,3,0,1,3,0,1,3,0,1,3,0,1,3,0,1,3,0,1,3,0,1,3,0,1,3,0,0,0,0,0,0,1,0,1,0,0,0,0,0,0,1,0,1,0,0,0,0,0,3,0,3,0,3,0,0,0,0,0,3,0,3,0,3,0,1,3,0,1,0,0,0,0,0,0,1,3,0,1,3,0,0,0,0,0,0,1,3,0,3,0,0,0,0,0,0,1,3,0,3,0,3,0,0,0,0,0,0,0,0,0,0,1,0,1,3,0,0,0,0,0,0,1,3,0,1,3,0,0,0,0,0,0,3,0,1,3,0,0,0,0,0,3,0,3,0,1,3],
-                "bone_weights": [0.99930483,0.9993267,0.9988469,0.9988882,0.5769585,0.41667557,0.5750416,0.41875833,0.08287623,0.9114626,0.0809439,0.9134449,0.052394267,0.88598174,0.050120413,0.052381232,0.88586444,0.050270952,0.6038401,0.21717286,0.16507955,0.60444063,0.2158349,0.16582415,0.8323886,0.14720343,0.8319144,0.14773157,0.1785458,0.7855405,0.015085268,0.18311681,0.77991176,0.017029043,0.22253782,0.7405494,0.22251287,0.740542,0.03593966,0.1644677,0.022933569,0.75609565,0.035559665,0.16221051,0.021494966,0.7592622,0.7325021,0.24111201,0.7324547,0.2411115,0.24104871,0.0171883,0.72107244,0.24646422,0.018294405,0.7150753,0.9979128,0.998135,0.99806494,0.99795896,0.9988047,0.9990297,0.99891806,0.9989621,0.10787421,0.8680739,0.045240935,0.9386733,0.9713774,0.9865636,0.9935986,0.9967849,0.9968572,0.9938901,0.98734754,0.9734771,0.014606806,0.9444226,0.0059462897,0.07222709,0.88400006,0.10569806,0.8703893,0.043480534,0.93957144,0.97172564,0.9866937,0.9936313,0.9967472,0.99692065,0.9939038,0.9873182,0.97335756,0.01533075,0.94405013,0.0057551004,0.07312979,0.88290054,0.024755668,0.9591396,0.98141676,0.99174035,0.99635047,0.9983034,0.99832433,0.9963541,0.99171793,0.9812937,0.025822569,0.9586212,0.07076583,0.915345,0.005161207,0.96714973,0.98713607,0.9948909,0.9978746,0.06926029,0.91721123,0.0041649975,0.9677716,0.9873414,0.99494904,0.99785924,0.99942935,0.99948114,0.99940383,0.99918497,0.9994379,0.99948144,0.99939644,0.9991656,0.52089864,0.47447312,0.4392018,0.55681866,0.2560472,0.73904645,0.14594676,0.84841776,0.52015466,0.47528905,0.43899456,0.5570616,0.25618187,0.73894024,0.14661598,0.8477633,0.035839584,0.8800026,0.05626422,0.014667104,0.86240315,0.06908087,0.022364985,0.00013051182,0.8054408,0.10276213,0.066731885,0.6983354,0.16329528,0.118703805,0.035808858,0.87989175,0.05644027,0.014632608,0.862282,0.069321305,0.022160817,0.00009224564,0.80524856,0.103189684,0.06651563,0.69797367,0.1641143,0.11826125,0.81223685,0.16827872,0.76778257,0.21257482,0.63745654,0.33860016,0.33775267,0.63089234,0.0029583946,0.8118751,0.16870442,0.7674167,0.21303833,0.6367719,0.33948454,0.33603087,0.63306826,0.0021218061,0.19665678,0.73637927,0.052574046,0.16256312,0.7119954,0.10919973,0.13064471,0.60218906,0.24547167,0.0899343,0.31702027,0.010822326,0.5626343,0.19670737,0.7363473,0.052616917,0.16264729,0.7118822,0.109325536,0.13077486,0.6018658,0.24584669,0.09006711,0.31613845,0.010086797,0.5637511,0.73304653,0.21381596,0.04714686,0.7181039,0.17633335,0.10093921,0.62774724,0.13753818,0.22940728,0.3697934,0.08491707,0.5393966,0.73318374,0.21381065,0.046922278,0.71842223,0.17632438,0.10065594,0.6285407,0.13754272,0.22864479,0.37184832,0.08502152,0.5372724,0.9988633,0.99763453,0.99480253,0.9884909,0.974564,0.94401485,0.039739665,0.87734264,0.10453632,0.99887,0.9976349,0.9947986,0.98848146,0.97454613,0.9439851,0.039737288,0.8773002,0.104535356,0.99794656,0.99544877,0.9894214,0.97517526,0.9416019,0.052505236,0.8625044,0.12556209,0.6763853,0.3008956,0.997966,0.99546796,0.9894605,0.9752745,0.9418646,0.052216694,0.8632105,0.12478506,0.6783151,0.29876667,0.8791952,0.08709458,0.013530838,0.9455255,0.021888305,0.9755683,0.9890888,0.9951352,0.99781674,0.9989788,0.87278545,0.06643052,0.058952127,0.9434719,0.002389647,0.009188458,0.9751048,0.9890814,0.9952143,0.99788773,0.99903166,0.8324441,0.05029952,0.115298815,0.9272245,0.052366655,0.9686239,0.9864858,0.9941699,0.99746424,0.99885434,0.7470455,0.021280993,0.21514823,0.89428943,0.08959707,0.95521534,0.025550488,0.98092675,0.9918466,0.9964818,0.9984181,0.99897635,0.9978189,0.99514294,0.98910654,0.975605,0.94559467,0.021895174,0.8793071,0.08709712,0.013317416,0.9990334,0.9978928,0.9952266,0.98911005,0.9751686,0.9436032,0.002405785,0.008915171,0.8730211,0.06643922,0.058716524,0.9988606,0.99747336,0.99418974,0.9865333,0.9687366,0.9274788,0.052095838,0.83295697,0.05032899,0.11476577,0.99843067,0.9964965,0.99187726,0.98100257,0.9554063,0.025134359,0.8947603,0.089086145,0.7481345,0.021453265,0.213979]
+                "bone_weights": [0.99930483,0.9993267,0.9988469,0.9988882,0.5769585,0.41667557,0.5750416,0.41875833,0.08287623,0.9114626,0.0809439,0.9134449,0.052394267,0.88598174,0.050120413,0.052381232,0.88586444,0.050270952,0.6038401,0.21717286,0.16507955,0.60444063,0.2158349,0.16582415,0.8323886,0.14720343,0.8319144,0.14773157,0.1785458,0.7855405,0.015085268,0.18311681,0.77991176,0.017029043,0.22253782,0.7405494,0.22251287,0.740542,0.03593966,0.1644677,0.022933569,0.75609565,0.035559665,0.16221051,0.021494966,0.7592622,0.7325021,0.24111201,0.7324547,0.2411115,0.24104871,0.0171883,0.72107244,0.24646422,0.018294405,0.7150753,0.9979128,0.998135,0.99806494,0.99795896,0.9988047,0.9990297,0.99891806,0.9989621,0.10787421,0.8680739,0.045240935,0.9386733,0.9713774,0.9865636,0.9935986,0.9967849,0.9968572,0.9938901,0.98734754,0.9734771,0.014606806,0.9444226,0.0059462897,0.07222709,0.88400006,0.10569806,0.8703893,0.043480534,0.93957144,0.97172564,0.9866937,0.9936313,0.9967472,0.99692065,0.9939038,0.9873182,0.97335756,0.01533075,0.94405013,0.0057551004,0.07312979,0.88290054,0.024755668,0.9591396,0.98141676,0.99174035,0.99635047,0.9983034,0.99832433,0.9963541,0.99171793,0.9812937,0.025822569,0.9586212,0.07076583,0.915345,0.005161207,0.96714973,0.98713607,0.9948909,0.9978746,0.06926029,0.91721123,0.0041649975,0.9677716,0.9873414,0.99494904,0.99785924,0.99942935,0.99948114,0.99940383,0.99918497,0.9994379,0.99948144,0.99939644,0.9991656,0.52089864,0.47447312,0.4392018,0.55681866,0.2560472,0.73904645,0.14594676,0.84841776,0.52015466,0.47528905,0.43899456,0.5570616,0.25618187,0.73894024,0.14661598,0.8477633,0.035839584,0.8800026,0.05626422,0.014667104,0.86240315,0.06908087,0.022364985,0.00013051182,0.8054408,0.10276213,0.066731885,0.6983354,0.16329528,0.118703805,0.035808858,0.87989175,0.05644027,0.014632608,0.862282,0.069321305,0.022160817,0.00009224564,0.80524856,0.103189684,0.06651563,0.69797367,0.1641143,0.11826125,0.81223685,0.16827872,0.76778257,0.21257482,0.63745654,0.33860016,0.33775267,0.63089234,0.0029583946,0.8118751,0.16870442,0.7674167,0.21303833,0.6367719,0.33948454,0.33603087,0.63306826,0.0021218061,0.19665678,0.73637927,0.052574046,0.16256312,0.7119954,0.10919973,0.13064471,0.60218906,0.24547167,0.0899343,0.31702027,0.010822326,0.5626343,0.19670737,0.7363473,0.052616917,0.16264729,0.7118822,0.109325536,0.13077486,0.6018658,0.24584669,0.09006711,0.31613845,0.010086797,0.5637511,0.73304653,0.21381596,0.04714686,0.7181039,0.17633335,0.10093921,0.62774724,0.13753818,0.22940728,0.3697934,0.08491707,0.5393966,0.73318374,0.21381065,0.046922278,0.71842223,0.17632438,0.10065594,0.6285407,0.13754272,0.22864479,0.37184832,0.08502152,0.5372724,0.9988633,0.99763453,0.99480253,0.9884909,0.974564,0.94401485,0.039739665,0.87734264,0.10453632,0.99887,0.9976349,0.9947986,0.98848146,0.97454613,0.9439851,0.039737288,0.8773002,0.104535356,0.99794656,0.99544877,0.9894214,0.97517526,0.9416019,0.052505236,0.8625044,0.12556209,0.6763853,0.3008956,0.997966,0.99546796,0.9894605,0.9752745,0.9418646,0.052216694,0.8632105,0.12478506,0.6783151,0.29876667,0.8791952,0.08709458,0.013530838,0.9455255,0.021888305,0.9755683,0.9890888,0.9951352,0.99781674,0.9989788,0.87278545,0.06643052,0.058952127,0.9434719,0.002389647,0.009188458,0.9751048,0.9890814,0.9952143,0.99788773,0.99903166,0.8324441,0.05029952,0.115298815,0.9272245,0.052366655,0.9686239,0.9864858,0.9941699,0.99746424,0.99885434,0.7470455,0.021280993,0.21514823,0.89428943,0.08959707,0.95521534,0.025550488,0.98092675,0.9918466,0.9964818,0.9984181,0.99897635,0.9978189,0.99514294,0.98910654,0.975605,0.94559467,0.021895174,0.8793071,0.08709712,0.013317416,0.9990334,0.9978928,0.9952266,0.98911005,0.9751686,0.9436032,0.002405785,0.008915171,0.8730211,0.06643922,0.058716524,0.9988606,0.99747336,0.99418974,0.9865333,0.9687366,0.9274788,0.052095838,0.83295697,0.05032899,0.11476577,0.99843067,0.9964965,0.99187726,0.98100257,0.9554063,0.025134359,0.8947603,0.089086145,0.7481345,0.021453265,0.213979],
+                "bone_names": ["Lower.Body","Upper.Body","Upper.Arm","Lower.Arm"]
             }
         },        
         "armature_name": "LetterFArmature",
@@ -167,7 +168,11 @@ fn expected_armature_data() -> String {
                   {
                     "frame": 20,
                     "bone": {
-                      "Matrix": [ -0.8025571, 0.0, -0.5965753, 0.0, 0.0, 1.0, 0.0, 0.0, 0.5965753, 0.0, -0.8025571, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, -0.94935692, 0.0, 0.31419969 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   }
                 ],
@@ -175,31 +180,51 @@ fn expected_armature_data() -> String {
                   {
                     "frame": 20,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 60,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 80,
                     "bone": {
-                      "Matrix": [ 0.70710677, 0.7071068, -6.983119e-8, 0.0, -0.7071068, 0.70710677, -1.6858738e-7, 0.0, -6.9831174e-8, 1.6858739e-7, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 9.1238835e-08, -4.3295688e-15, -0.38268344, 0.92387953 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 100,
                     "bone": {
-                      "Matrix": [ 0.9267767, 0.36811846, -3.4915598e-8, 0.0, -0.36811846, 0.9267767, -1.7553253e-7, 0.0, -3.4915583e-8, 1.7553253e-7, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 8.9483143e-08, -3.8233572e-15, -0.18765978, 0.98223409 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 120,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   }
                 ],
@@ -207,37 +232,61 @@ fn expected_armature_data() -> String {
                   {
                     "frame": 20,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 40,
                     "bone": {
-                      "Matrix": [ 0.70710677, -0.7071068, -1.2802761e-8, 0.0, 0.7071068, 0.70710677, 3.0908605e-8, 0.0, -1.2802766e-8, -3.0908602e-8, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ -1.6727616e-08, 1.3529903e-15, 0.38268344, 0.92387953 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 60,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 80,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 0.92677665, -0.36811852, 0.0, 0.0, 0.36811852, 0.92677665, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.18765982, 0.0, 0.0, 0.98223408 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 100,
                     "bone": {
-                      "Matrix": [ 1.0, -3.0908602e-8, 1.2802744e-8, 0.0, 3.0908588e-8, 0.70710677, -0.7071068, 0.0, 1.28027775e-8, 0.7071068, 0.70710677, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.38268344, -9.0650347e-15, 1.6727611e-08, 0.92387953 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 120,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   }
                 ],
@@ -245,19 +294,31 @@ fn expected_armature_data() -> String {
                   {
                     "frame": 20,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 40,
                     "bone": {
-                      "Matrix": [ 0.7071067, 0.7066466, -0.025508927, 0.0, -0.7066466, 0.7074879, 0.010559131, 0.0, 0.025508832, 0.010559361, 0.9996188, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 6.2237553e-08, -0.013805306, -0.38243439, 0.92387951 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   },
                   {
                     "frame": 60,
                     "bone": {
-                      "Matrix": [ 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0 ]
+                      "Trs": {
+                        "translation": [ 0.0, 0.0, 0.0 ],
+                        "rotation": [ 0.0, 0.0, 0.0, 1.0 ],
+                        "scale": [ 1.0, 1.0, 1.0 ]
+                      }
                     }
                   }
                 ]
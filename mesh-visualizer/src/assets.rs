@@ -68,7 +68,8 @@ impl Assets {
                 let attributes = mesh.combine_vertex_indices(&CreateSingleIndexConfig {
                     calculate_face_tangents: false,
                     bone_influences_per_vertex: None,
-                });
+                })
+                .unwrap();
                 mesh.triangulate();
                 mesh.y_up();
 